@@ -0,0 +1,147 @@
+use futures_util::StreamExt;
+use paperclip::actix::{
+	api_v2_operation, get,
+	web::{Data, Json, Query},
+};
+use wither::{
+	bson::{doc, DateTime, Document},
+	mongodb::{options::FindOptions, Database as MongoDatabase},
+	Model,
+};
+
+use crate::{audit::AuditEvent, role::AUDIT_READ_PERMISSION};
+
+use super::{AdminErrors, AuditEventListResponse, AuditEventQuery, AuthorizedUser, DEFAULT_PER_PAGE, MAX_PER_PAGE};
+
+fn audit_event_filter(query: &AuditEventQuery) -> Document {
+	let mut filter = Document::new();
+
+	if let Some(action) = &query.action {
+		filter.insert("action", action.as_str());
+	}
+	if let Some(actor) = &query.actor {
+		filter.insert("actor", actor);
+	}
+	if let Some(subject) = &query.subject {
+		filter.insert("subject", subject);
+	}
+	if let Some(client_id) = &query.client_id {
+		filter.insert("client_id", client_id);
+	}
+	if let Some(ip) = &query.ip {
+		filter.insert("ip", ip);
+	}
+	if let Some(request_id) = &query.request_id {
+		filter.insert("request_id", request_id);
+	}
+
+	let mut created = Document::new();
+	if let Some(after) = query.after {
+		created.insert("$gte", DateTime::from_millis(after.saturating_mul(1000)));
+	}
+	if let Some(before) = query.before {
+		created.insert("$lt", DateTime::from_millis(before.saturating_mul(1000)));
+	}
+	if !created.is_empty() {
+		filter.insert("created_at", created);
+	}
+
+	filter
+}
+
+/// ADMIN List audit events
+///
+/// Paginated security events, newest first, filtered by action, actor, subject, client, IP, request and date
+#[api_v2_operation]
+#[get("/audit")]
+pub async fn list_audit_events(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Query(query): Query<AuditEventQuery>,
+) -> Result<Json<AuditEventListResponse>, AdminErrors> {
+	admin.require(AUDIT_READ_PERMISSION)?;
+
+	let page = query.page.unwrap_or(1).max(1);
+	let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+	let filter = audit_event_filter(&query);
+
+	let total = AuditEvent::collection(&db)
+		.count_documents(filter.clone(), None)
+		.await?;
+
+	let options = FindOptions::builder()
+		.sort(doc! { "created_at": -1, "_id": -1 })
+		// Huge pages are only empty
+		.skip((page - 1).saturating_mul(per_page as u64))
+		.limit(per_page)
+		.build();
+	let mut cursor = AuditEvent::find(&db, filter, options).await?;
+
+	let mut items = Vec::new();
+	while let Some(event) = cursor.next().await {
+		items.push(event?.into());
+	}
+
+	Ok(Json(AuditEventListResponse {
+		items,
+		page,
+		per_page,
+		total,
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::audit::AuditAction;
+
+	use super::*;
+
+	#[test]
+	fn empty_query_matches_everything() {
+		assert!(audit_event_filter(&AuditEventQuery::default()).is_empty());
+	}
+
+	#[test]
+	fn filters_on_the_given_fields() {
+		let query = AuditEventQuery {
+			action: Some(AuditAction::LoginFailed),
+			subject: Some("5f1d7f3e9b1e8a3c2d4e6f70".to_string()),
+			ip: Some("203.0.113.7".to_string()),
+			..Default::default()
+		};
+		assert_eq!(
+			audit_event_filter(&query),
+			doc! { "action": "login-failed", "subject": "5f1d7f3e9b1e8a3c2d4e6f70", "ip": "203.0.113.7" }
+		);
+	}
+
+	#[test]
+	fn filters_on_the_date_range() {
+		let query = AuditEventQuery {
+			after: Some(1_600_000_000),
+			before: Some(1_700_000_000),
+			..Default::default()
+		};
+		assert_eq!(
+			audit_event_filter(&query),
+			doc! {
+				"created_at": {
+					"$gte": DateTime::from_millis(1_600_000_000_000),
+					"$lt": DateTime::from_millis(1_700_000_000_000),
+				}
+			}
+		);
+	}
+
+	#[test]
+	fn saturates_huge_dates() {
+		let query = AuditEventQuery {
+			before: Some(i64::MAX),
+			..Default::default()
+		};
+		assert_eq!(
+			audit_event_filter(&query),
+			doc! { "created_at": { "$lt": DateTime::from_millis(i64::MAX) } }
+		);
+	}
+}
@@ -0,0 +1,116 @@
+use actix_web::{http::StatusCode, Error as ActixError, HttpResponse, ResponseError};
+use handlebars::RenderError;
+use paperclip::actix::api_v2_errors;
+use serde::Serialize;
+use thiserror::Error;
+use validator::ValidationErrors;
+use wither::{bson::oid::Error as ObjectIdError, mongodb::error::Error as MongoError, WitherError};
+
+use crate::{
+	auth::{AuthErrors, LogoutErrors},
+	session::SessionStoreErrors,
+	user::UserErrors,
+};
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "Wrong input or invalid user id",
+	code = 401,
+	description = "Not logged in or expired session",
+	code = 403,
+	description = "The logged in user lacks the required permission",
+	code = 404,
+	description = "User, role, permission, group or invitation not found",
+	code = 409,
+	description = "The email, username or name is already taken",
+	code = 500,
+	description = "Internal server error, could be a db connection error, email server error"
+)]
+#[derive(Error, Debug)]
+pub enum AdminErrors {
+	#[error("Not logged in")]
+	Unauthorized,
+	#[error("Permission denied")]
+	Forbidden,
+	#[error("User not found")]
+	UserNotFound,
+	#[error("Role not found")]
+	RoleNotFound,
+	#[error("Permission not found")]
+	PermissionNotFound,
+	#[error("Group not found")]
+	GroupNotFound,
+	#[error("The email is already taken")]
+	EmailTaken,
+	#[error("The name is already taken")]
+	NameTaken,
+	#[error("Unknown roles: {0:?}")]
+	UnknownRoles(Vec<String>),
+	#[error("Unknown permissions: {0:?}")]
+	UnknownPermissions(Vec<String>),
+	#[error("Unknown groups: {0:?}")]
+	UnknownGroups(Vec<String>),
+	#[error("Invalid user id: {0}")]
+	InvalidId(#[from] ObjectIdError),
+	#[error("{0}")]
+	ValidationError(#[from] ValidationErrors),
+	#[error("Internal server error")]
+	ActixError(#[from] ActixError),
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("Internal server error")]
+	MongoError(#[from] MongoError),
+	#[error("Internal server error")]
+	SessionStoreError(#[from] SessionStoreErrors),
+	#[error("{0}")]
+	UserError(#[from] UserErrors),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+	#[error("Internal server error")]
+	LogoutError(#[from] LogoutErrors),
+	#[error("{0}")]
+	HandlebarsError(#[from] RenderError),
+	#[error("Invitation not found")]
+	InvitationNotFound,
+}
+
+impl ResponseError for AdminErrors {
+	fn error_response(&self) -> HttpResponse {
+		if let Self::AuthError(e) = self {
+			return e.error_response();
+		}
+		let error_response = ErrorResponse {
+			error: self.to_string(),
+		};
+		HttpResponse::build(self.status_code()).json(error_response)
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::Unauthorized => StatusCode::UNAUTHORIZED,
+			Self::Forbidden => StatusCode::FORBIDDEN,
+			Self::UserNotFound => StatusCode::NOT_FOUND,
+			Self::RoleNotFound => StatusCode::NOT_FOUND,
+			Self::PermissionNotFound => StatusCode::NOT_FOUND,
+			Self::GroupNotFound => StatusCode::NOT_FOUND,
+			Self::InvitationNotFound => StatusCode::NOT_FOUND,
+			Self::EmailTaken => StatusCode::CONFLICT,
+			Self::NameTaken => StatusCode::CONFLICT,
+			Self::UnknownRoles(_) => StatusCode::BAD_REQUEST,
+			Self::UnknownPermissions(_) => StatusCode::BAD_REQUEST,
+			Self::UnknownGroups(_) => StatusCode::BAD_REQUEST,
+			Self::InvalidId(_) => StatusCode::BAD_REQUEST,
+			Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::InvalidUsername(_)) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::ReservedUsername) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::UsernameTaken) => StatusCode::CONFLICT,
+			Self::AuthError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
@@ -0,0 +1,86 @@
+use actix_session::Session;
+use actix_web::{dev::Payload, web::Data, FromRequest, HttpRequest};
+use futures_util::future::LocalBoxFuture;
+use paperclip::{actix::OperationModifier, v2::schema::Apiv2Schema};
+use wither::mongodb::Database as MongoDatabase;
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	role::{UserAccess, ADMIN_ROLE, ROLES_WRITE_PERMISSION},
+	session::SessionErrors,
+	user::{User, UserErrors},
+};
+
+use super::AdminErrors;
+
+/// Extractor of the logged in user along with their effective roles and permissions
+pub struct AuthorizedUser {
+	pub user: User,
+	pub access: UserAccess,
+}
+
+impl AuthorizedUser {
+	/// Rejects the request unless the user has the permission
+	pub fn require(&self, permission: &str) -> Result<(), AdminErrors> {
+		if self.access.has_permission(permission) {
+			Ok(())
+		} else {
+			Err(AdminErrors::Forbidden)
+		}
+	}
+
+	/// Rejects giving roles without the roles write permission, only admins can give the admin role
+	pub fn require_grant(&self, roles: &[String]) -> Result<(), AdminErrors> {
+		self.require(ROLES_WRITE_PERMISSION)?;
+		if roles.iter().any(|role| role == ADMIN_ROLE) && !self.access.is_admin() {
+			return Err(AdminErrors::Forbidden);
+		}
+		Ok(())
+	}
+
+	/// Rejects managing an admin account, its email, status, password or existence, unless the user is an admin
+	pub async fn require_manage(&self, db: &MongoDatabase, user: &User) -> Result<(), AdminErrors> {
+		if !self.access.is_admin() && UserAccess::resolve(db, user).await?.is_admin() {
+			return Err(AdminErrors::Forbidden);
+		}
+		Ok(())
+	}
+
+	/// An admin action audit event, acted by this user
+	pub fn audit(&self, req: &HttpRequest, operation: &str) -> AuditEvent {
+		// Safe to unwrap, the user exists
+		AuditEvent::new(AuditAction::AdminAction, req)
+			.actor(&self.user.id.unwrap().to_hex())
+			.detail("operation", operation)
+	}
+}
+
+impl FromRequest for AuthorizedUser {
+	type Error = AdminErrors;
+	type Future = LocalBoxFuture<'static, Result<Self, Self::Error>>;
+
+	fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+		let req = req.clone();
+
+		Box::pin(async move {
+			// Safe to unwrap, the database is always registered
+			let db = req.app_data::<Data<MongoDatabase>>().unwrap();
+			let session = Session::extract(&req).await?;
+
+			let user = User::user_from_session(db, &session).await.map_err(|e| match e {
+				UserErrors::UserNotFound | UserErrors::SessionStateError(SessionErrors::SessionExpired) => {
+					AdminErrors::Unauthorized
+				}
+				UserErrors::AccountInactive(_) => AdminErrors::Forbidden,
+				e => e.into(),
+			})?;
+			let access = UserAccess::resolve(db, &user).await?;
+
+			Ok(Self { user, access })
+		})
+	}
+}
+
+impl Apiv2Schema for AuthorizedUser {}
+
+impl OperationModifier for AuthorizedUser {}
@@ -0,0 +1,127 @@
+use actix_web::HttpRequest;
+use futures_util::StreamExt;
+use paperclip::actix::{
+	api_v2_operation, delete, get, post,
+	web::{Data, Json, Path},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database as MongoDatabase,
+	Model,
+};
+
+use crate::{
+	auth::send_email_to_user,
+	role::{USERS_READ_PERMISSION, USERS_WRITE_PERMISSION},
+	session::unix_now,
+	settings::{APP_SETTINGS, HANDLEBARS, SIGNUP_INVITATION_TEMPLATE_NAME},
+	signup::SignupInvitation,
+	user::email_key,
+	utils::{generate_token, hash_token},
+};
+
+use super::{
+	ensure_roles_exist, AdminErrors, AuthorizedUser, DeletedResponse, SignupInvitationInput, SignupInvitationView,
+};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct SignupInvitationEMailData {
+	pub inviter: String,
+	pub token: String,
+}
+
+/// ADMIN Invite to sign up
+///
+/// Sends a single-use signup invitation by email, it works in every signup mode except `closed`
+#[api_v2_operation]
+#[post("/invitations")]
+pub async fn create_signup_invitation(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Json(signup_invitation_input): Json<SignupInvitationInput>,
+) -> Result<Json<SignupInvitationView>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+	signup_invitation_input.validate()?;
+	if !signup_invitation_input.roles.is_empty() {
+		admin.require_grant(&signup_invitation_input.roles)?;
+	}
+	ensure_roles_exist(&db, &signup_invitation_input.roles).await?;
+
+	let token = generate_token();
+	let mut invitation = SignupInvitation {
+		id: None,
+		email: email_key(&signup_invitation_input.email),
+		roles: signup_invitation_input.roles,
+		token: hash_token(&token),
+		invited_by: admin.user.id.clone().unwrap(),
+		expires_at: unix_now() + APP_SETTINGS.signup.invitation,
+	};
+	invitation.save(&db, None).await?;
+
+	let invitation_data = SignupInvitationEMailData {
+		inviter: admin.user.display_name(),
+		token,
+	};
+	let html_mail = HANDLEBARS.render(SIGNUP_INVITATION_TEMPLATE_NAME, &invitation_data)?;
+	let email_title = "You have been invited to Odysseus";
+
+	send_email_to_user(&invitation.email, &invitation.email, email_title, &html_mail)?;
+
+	admin
+		.audit(&req, "create-signup-invitation")
+		.detail("email", &invitation.email)
+		.detail("roles", invitation.roles.join(" "))
+		.record(&db)
+		.await;
+
+	Ok(Json(invitation.into()))
+}
+
+/// ADMIN List signup invitations
+///
+/// Lists the pending signup invitations
+#[api_v2_operation]
+#[get("/invitations")]
+pub async fn list_signup_invitations(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+) -> Result<Json<Vec<SignupInvitationView>>, AdminErrors> {
+	admin.require(USERS_READ_PERMISSION)?;
+
+	let mut cursor = SignupInvitation::find(&db, doc! { "expires_at": { "$gt": unix_now() } }, None).await?;
+	let mut invitations = Vec::new();
+	while let Some(invitation) = cursor.next().await {
+		invitations.push(invitation?.into());
+	}
+
+	Ok(Json(invitations))
+}
+
+/// ADMIN Revoke signup invitation
+#[api_v2_operation]
+#[delete("/invitations/{id}")]
+pub async fn revoke_signup_invitation(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+) -> Result<Json<DeletedResponse>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	let id = ObjectId::parse_str(&*id)?;
+	let invitation = SignupInvitation::find_one(&db, doc! { "_id": id }, None)
+		.await?
+		.ok_or(AdminErrors::InvitationNotFound)?;
+	invitation.delete(&db).await?;
+
+	admin
+		.audit(&req, "revoke-signup-invitation")
+		.detail("email", &invitation.email)
+		.record(&db)
+		.await;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
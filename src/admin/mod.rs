@@ -0,0 +1,15 @@
+pub mod audit;
+pub mod errors;
+pub mod guard;
+pub mod invitations;
+pub mod roles;
+pub mod routes;
+pub mod types;
+
+pub use audit::*;
+pub use errors::*;
+pub use guard::*;
+pub use invitations::*;
+pub use roles::*;
+pub use routes::*;
+pub use types::*;
@@ -0,0 +1,404 @@
+use actix_web::HttpRequest;
+use futures_util::StreamExt;
+use paperclip::actix::{
+	api_v2_operation, delete, get, patch, post,
+	web::{Data, Json, Path},
+};
+use validator::Validate;
+use wither::{bson::doc, mongodb::Database as MongoDatabase, Model};
+
+use crate::{
+	role::{
+		find_all, find_by_name, find_missing, Group, Permission, Role, ADMIN_ROLE, ROLES_READ_PERMISSION,
+		ROLES_WRITE_PERMISSION,
+	},
+	user::User,
+};
+
+use super::{
+	AdminErrors, AuthorizedUser, DeletedResponse, GroupInput, GroupView, PermissionInput, PermissionView, RoleInput,
+	RoleView, UpdateGroupInput, UpdateRoleInput,
+};
+
+/// Rejects role names without a document, the built-in admin role is always valid
+pub async fn ensure_roles_exist(db: &MongoDatabase, roles: &[String]) -> Result<(), AdminErrors> {
+	let roles: Vec<String> = roles.iter().filter(|role| *role != ADMIN_ROLE).cloned().collect();
+	let missing = find_missing::<Role>(db, &roles).await?;
+	if missing.is_empty() {
+		Ok(())
+	} else {
+		Err(AdminErrors::UnknownRoles(missing))
+	}
+}
+
+/// The roles given to the members of the groups
+pub async fn group_roles(db: &MongoDatabase, groups: &[String]) -> Result<Vec<String>, AdminErrors> {
+	let mut roles = Vec::new();
+	if groups.is_empty() {
+		return Ok(roles);
+	}
+
+	let mut cursor = Group::find(db, doc! { "name": { "$in": groups.to_vec() } }, None).await?;
+	while let Some(group) = cursor.next().await {
+		roles.extend(group?.roles);
+	}
+	Ok(roles)
+}
+
+/// Rejects permission names without a document
+pub async fn ensure_permissions_exist(db: &MongoDatabase, permissions: &[String]) -> Result<(), AdminErrors> {
+	let missing = find_missing::<Permission>(db, permissions).await?;
+	if missing.is_empty() {
+		Ok(())
+	} else {
+		Err(AdminErrors::UnknownPermissions(missing))
+	}
+}
+
+/// Rejects group names without a document
+pub async fn ensure_groups_exist(db: &MongoDatabase, groups: &[String]) -> Result<(), AdminErrors> {
+	let missing = find_missing::<Group>(db, groups).await?;
+	if missing.is_empty() {
+		Ok(())
+	} else {
+		Err(AdminErrors::UnknownGroups(missing))
+	}
+}
+
+/// ADMIN List permissions
+#[api_v2_operation]
+#[get("/permissions")]
+pub async fn list_permissions(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+) -> Result<Json<Vec<PermissionView>>, AdminErrors> {
+	admin.require(ROLES_READ_PERMISSION)?;
+
+	let permissions = find_all::<Permission>(&db).await?;
+	Ok(Json(permissions.into_iter().map(Into::into).collect()))
+}
+
+/// ADMIN Create permission
+#[api_v2_operation]
+#[post("/permissions")]
+pub async fn create_permission(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Json(permission_input): Json<PermissionInput>,
+) -> Result<Json<PermissionView>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+	permission_input.validate()?;
+
+	if find_by_name::<Permission>(&db, &permission_input.name).await?.is_some() {
+		return Err(AdminErrors::NameTaken);
+	}
+
+	let PermissionInput { name, description } = permission_input;
+	let mut permission = Permission {
+		id: None,
+		name,
+		description,
+	};
+	permission.save(&db, None).await?;
+
+	admin
+		.audit(&req, "create-permission")
+		.detail("permission", &permission.name)
+		.record(&db)
+		.await;
+
+	Ok(Json(permission.into()))
+}
+
+/// ADMIN Delete permission
+///
+/// Deletes the permission and removes it from all the roles
+#[api_v2_operation]
+#[delete("/permissions/{name}")]
+pub async fn delete_permission(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	name: Path<String>,
+) -> Result<Json<DeletedResponse>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+
+	let permission = find_by_name::<Permission>(&db, &name)
+		.await?
+		.ok_or(AdminErrors::PermissionNotFound)?;
+	permission.delete(&db).await?;
+
+	Role::collection(&db)
+		.update_many(
+			doc! { "permissions": name.as_str() },
+			doc! { "$pull": { "permissions": name.as_str() } },
+			None,
+		)
+		.await?;
+
+	admin
+		.audit(&req, "delete-permission")
+		.detail("permission", name.as_str())
+		.record(&db)
+		.await;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ADMIN List roles
+#[api_v2_operation]
+#[get("/roles")]
+pub async fn list_roles(admin: AuthorizedUser, db: Data<MongoDatabase>) -> Result<Json<Vec<RoleView>>, AdminErrors> {
+	admin.require(ROLES_READ_PERMISSION)?;
+
+	let roles = find_all::<Role>(&db).await?;
+	Ok(Json(roles.into_iter().map(Into::into).collect()))
+}
+
+/// ADMIN Create role
+#[api_v2_operation]
+#[post("/roles")]
+pub async fn create_role(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Json(role_input): Json<RoleInput>,
+) -> Result<Json<RoleView>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+	role_input.validate()?;
+
+	if find_by_name::<Role>(&db, &role_input.name).await?.is_some() {
+		return Err(AdminErrors::NameTaken);
+	}
+	ensure_permissions_exist(&db, &role_input.permissions).await?;
+
+	let RoleInput {
+		name,
+		description,
+		permissions,
+	} = role_input;
+	let mut role = Role {
+		id: None,
+		name,
+		description,
+		permissions,
+	};
+	role.save(&db, None).await?;
+
+	admin
+		.audit(&req, "create-role")
+		.detail("role", &role.name)
+		.detail("permissions", role.permissions.join(" "))
+		.record(&db)
+		.await;
+
+	Ok(Json(role.into()))
+}
+
+/// ADMIN Update role
+///
+/// Changes the given fields of the role
+#[api_v2_operation]
+#[patch("/roles/{name}")]
+pub async fn update_role(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	name: Path<String>,
+	Json(update_role_input): Json<UpdateRoleInput>,
+) -> Result<Json<RoleView>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+
+	let mut role = find_by_name::<Role>(&db, &name)
+		.await?
+		.ok_or(AdminErrors::RoleNotFound)?;
+
+	let UpdateRoleInput {
+		description,
+		permissions,
+	} = update_role_input;
+	if let Some(description) = description {
+		role.description = Some(description);
+	}
+	if let Some(permissions) = permissions {
+		ensure_permissions_exist(&db, &permissions).await?;
+		role.permissions = permissions;
+	}
+	role.save(&db, None).await?;
+
+	admin
+		.audit(&req, "update-role")
+		.detail("role", &role.name)
+		.detail("permissions", role.permissions.join(" "))
+		.record(&db)
+		.await;
+
+	Ok(Json(role.into()))
+}
+
+/// ADMIN Delete role
+///
+/// Deletes the role and removes it from all the users and groups
+#[api_v2_operation]
+#[delete("/roles/{name}")]
+pub async fn delete_role(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	name: Path<String>,
+) -> Result<Json<DeletedResponse>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+
+	let role = find_by_name::<Role>(&db, &name)
+		.await?
+		.ok_or(AdminErrors::RoleNotFound)?;
+	role.delete(&db).await?;
+
+	User::collection(&db)
+		.update_many(
+			doc! { "roles": name.as_str() },
+			doc! { "$pull": { "roles": name.as_str() } },
+			None,
+		)
+		.await?;
+	Group::collection(&db)
+		.update_many(
+			doc! { "roles": name.as_str() },
+			doc! { "$pull": { "roles": name.as_str() } },
+			None,
+		)
+		.await?;
+
+	admin
+		.audit(&req, "delete-role")
+		.detail("role", name.as_str())
+		.record(&db)
+		.await;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ADMIN List groups
+#[api_v2_operation]
+#[get("/groups")]
+pub async fn list_groups(admin: AuthorizedUser, db: Data<MongoDatabase>) -> Result<Json<Vec<GroupView>>, AdminErrors> {
+	admin.require(ROLES_READ_PERMISSION)?;
+
+	let groups = find_all::<Group>(&db).await?;
+	Ok(Json(groups.into_iter().map(Into::into).collect()))
+}
+
+/// ADMIN Create group
+#[api_v2_operation]
+#[post("/groups")]
+pub async fn create_group(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Json(group_input): Json<GroupInput>,
+) -> Result<Json<GroupView>, AdminErrors> {
+	admin.require_grant(&group_input.roles)?;
+	group_input.validate()?;
+
+	if find_by_name::<Group>(&db, &group_input.name).await?.is_some() {
+		return Err(AdminErrors::NameTaken);
+	}
+	ensure_roles_exist(&db, &group_input.roles).await?;
+
+	let GroupInput {
+		name,
+		description,
+		roles,
+	} = group_input;
+	let mut group = Group {
+		id: None,
+		name,
+		description,
+		roles,
+	};
+	group.save(&db, None).await?;
+
+	admin
+		.audit(&req, "create-group")
+		.detail("group", &group.name)
+		.detail("roles", group.roles.join(" "))
+		.record(&db)
+		.await;
+
+	Ok(Json(group.into()))
+}
+
+/// ADMIN Update group
+///
+/// Changes the given fields of the group
+#[api_v2_operation]
+#[patch("/groups/{name}")]
+pub async fn update_group(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	name: Path<String>,
+	Json(update_group_input): Json<UpdateGroupInput>,
+) -> Result<Json<GroupView>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+
+	let mut group = find_by_name::<Group>(&db, &name)
+		.await?
+		.ok_or(AdminErrors::GroupNotFound)?;
+
+	let UpdateGroupInput { description, roles } = update_group_input;
+	if let Some(description) = description {
+		group.description = Some(description);
+	}
+	if let Some(roles) = roles {
+		admin.require_grant(&roles)?;
+		ensure_roles_exist(&db, &roles).await?;
+		group.roles = roles;
+	}
+	group.save(&db, None).await?;
+
+	admin
+		.audit(&req, "update-group")
+		.detail("group", &group.name)
+		.detail("roles", group.roles.join(" "))
+		.record(&db)
+		.await;
+
+	Ok(Json(group.into()))
+}
+
+/// ADMIN Delete group
+///
+/// Deletes the group and removes all its members
+#[api_v2_operation]
+#[delete("/groups/{name}")]
+pub async fn delete_group(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	name: Path<String>,
+) -> Result<Json<DeletedResponse>, AdminErrors> {
+	admin.require(ROLES_WRITE_PERMISSION)?;
+
+	let group = find_by_name::<Group>(&db, &name)
+		.await?
+		.ok_or(AdminErrors::GroupNotFound)?;
+	group.delete(&db).await?;
+
+	User::collection(&db)
+		.update_many(
+			doc! { "groups": name.as_str() },
+			doc! { "$pull": { "groups": name.as_str() } },
+			None,
+		)
+		.await?;
+
+	admin
+		.audit(&req, "delete-group")
+		.detail("group", name.as_str())
+		.record(&db)
+		.await;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
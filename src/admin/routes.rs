@@ -0,0 +1,379 @@
+use actix_web::HttpRequest;
+use futures_util::StreamExt;
+use paperclip::actix::{
+	api_v2_operation, delete, get, patch, post, put,
+	web::{Data, Json, Path, Query},
+};
+use validator::Validate;
+use wither::{
+	bson::{doc, oid::ObjectId, Bson, Document},
+	mongodb::{options::FindOptions, Database as MongoDatabase},
+	Model,
+};
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::{revoke_hydra_sessions, send_password_reset_email, EmailSentResponse},
+	role::{UserAccess, METRICS_READ_PERMISSION, USERS_READ_PERMISSION, USERS_WRITE_PERMISSION},
+	session::{revoke_user_sessions, SharedSessionStore},
+	user::{email_key, normalize_email, AccountState, AccountStatus, User},
+	utils::{HasherMetricsSnapshot, HASHER_METRICS},
+};
+
+use super::{
+	ensure_groups_exist, ensure_roles_exist, group_roles, AdminErrors, AdminUserView, AuthorizedUser, DeletedResponse,
+	UpdateUserInput, UserListQuery, UserListResponse, DEFAULT_PER_PAGE, MAX_PER_PAGE,
+};
+
+/// Finds a user by the ID given in the path
+async fn find_user(db: &MongoDatabase, id: &str) -> Result<User, AdminErrors> {
+	let id = ObjectId::parse_str(id)?;
+	User::find_by_id(db, &id).await?.ok_or(AdminErrors::UserNotFound)
+}
+
+/// Escapes the regex metacharacters, the email filter is a plain substring
+fn escape_regex(value: &str) -> String {
+	let mut escaped = String::with_capacity(value.len());
+	for c in value.chars() {
+		if "\\^$.|?*+()[]{}".contains(c) {
+			escaped.push('\\');
+		}
+		escaped.push(c);
+	}
+	escaped
+}
+
+/// The smallest ObjectId generated at the given unix timestamp
+fn object_id_at(timestamp: i64) -> ObjectId {
+	let mut bytes = [0; 12];
+	bytes[..4].copy_from_slice(&(timestamp.clamp(0, u32::MAX.into()) as u32).to_be_bytes());
+	ObjectId::from_bytes(bytes)
+}
+
+fn user_list_filter(query: &UserListQuery) -> Document {
+	let mut filter = Document::new();
+
+	if let Some(email) = &query.email {
+		filter.insert("email", doc! { "$regex": escape_regex(email), "$options": "i" });
+	}
+	if let Some(verified) = query.verified {
+		filter.insert("email_verified", verified);
+	}
+	if let Some(role) = &query.role {
+		filter.insert("roles", role);
+	}
+	if let Some(state) = query.state {
+		// Accounts created before the account states have no status
+		match state {
+			AccountState::Active => filter.insert("status.state", doc! { "$in": [state.as_str(), Bson::Null] }),
+			state => filter.insert("status.state", state.as_str()),
+		};
+	}
+
+	// The creation time is part of the ID
+	let mut created = Document::new();
+	if let Some(created_after) = query.created_after {
+		created.insert("$gte", object_id_at(created_after));
+	}
+	if let Some(created_before) = query.created_before {
+		created.insert("$lt", object_id_at(created_before));
+	}
+	if !created.is_empty() {
+		filter.insert("_id", created);
+	}
+
+	filter
+}
+
+/// ADMIN List users
+///
+/// Paginated user listing, filtered by email, verified status, role and creation date
+#[api_v2_operation]
+#[get("/users")]
+pub async fn list_users(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	Query(query): Query<UserListQuery>,
+) -> Result<Json<UserListResponse>, AdminErrors> {
+	admin.require(USERS_READ_PERMISSION)?;
+
+	let page = query.page.unwrap_or(1).max(1);
+	let per_page = query.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+	let filter = user_list_filter(&query);
+
+	let total = User::collection(&db).count_documents(filter.clone(), None).await?;
+
+	let options = FindOptions::builder()
+		.sort(doc! { "_id": 1 })
+		// Huge pages are only empty
+		.skip((page - 1).saturating_mul(per_page as u64))
+		.limit(per_page)
+		.build();
+	let mut cursor = User::find(&db, filter, options).await?;
+
+	let mut items = Vec::new();
+	while let Some(user) = cursor.next().await {
+		items.push(user?.into());
+	}
+
+	Ok(Json(UserListResponse {
+		items,
+		page,
+		per_page,
+		total,
+	}))
+}
+
+/// ADMIN Get user
+#[api_v2_operation]
+#[get("/users/{id}")]
+pub async fn get_user(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+) -> Result<Json<AdminUserView>, AdminErrors> {
+	admin.require(USERS_READ_PERMISSION)?;
+
+	let user = find_user(&db, &id).await?;
+	Ok(Json(user.into()))
+}
+
+/// ADMIN Get user access
+///
+/// The roles of the user, directly assigned and inherited from groups, and the permissions they grant
+#[api_v2_operation]
+#[get("/users/{id}/access")]
+pub async fn get_user_access(
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+) -> Result<Json<UserAccess>, AdminErrors> {
+	admin.require(USERS_READ_PERMISSION)?;
+
+	let user = find_user(&db, &id).await?;
+	Ok(Json(UserAccess::resolve(&db, &user).await?))
+}
+
+/// ADMIN Update user
+///
+/// Changes the given fields of the user
+#[api_v2_operation]
+#[patch("/users/{id}")]
+pub async fn update_user(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+	Json(update_user_input): Json<UpdateUserInput>,
+) -> Result<Json<AdminUserView>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	update_user_input.validate()?;
+	let mut user = find_user(&db, &id).await?;
+	admin.require_manage(&db, &user).await?;
+
+	let mut event = admin.audit(&req, "update-user").subject(&id);
+	let previous_email = user.email_scope.email.clone();
+
+	let UpdateUserInput {
+		email,
+		email_verified,
+		preferred_username,
+		roles,
+		groups,
+	} = update_user_input;
+
+	if let Some(email) = email {
+		let email = normalize_email(&email);
+		if email != user.email_scope.email {
+			// Matches case insensitively, so it may be the user itself changing the case
+			if let Some(owner) = User::find_by_email(&db, &email).await? {
+				if owner.id != user.id {
+					return Err(AdminErrors::EmailTaken);
+				}
+			}
+			// The new address has not been proven yet, a different case is the same address
+			if email_key(&email) != email_key(&user.email_scope.email) {
+				user.email_scope.email_verified = false;
+			}
+			user.email_scope.email = email;
+		}
+	}
+	if let Some(email_verified) = email_verified {
+		user.email_scope.email_verified = email_verified;
+		event = event.detail("emailVerified", email_verified);
+	}
+	if let Some(preferred_username) = preferred_username {
+		user.set_username(&db, &preferred_username, false).await?;
+		event = event.detail("username", user.username.clone().unwrap_or_default());
+	}
+	if let Some(roles) = roles {
+		admin.require_grant(&roles)?;
+		ensure_roles_exist(&db, &roles).await?;
+		event = event.detail("roles", roles.join(" "));
+		user.roles = roles;
+	}
+	if let Some(groups) = groups {
+		admin.require_grant(&group_roles(&db, &groups).await?)?;
+		ensure_groups_exist(&db, &groups).await?;
+		event = event.detail("groups", groups.join(" "));
+		user.groups = groups;
+	}
+
+	user.save(&db, None).await?;
+
+	if user.email_scope.email != previous_email {
+		event = event.detail("email", &user.email_scope.email);
+		// Safe to unwrap, the admin exists
+		AuditEvent::new(AuditAction::EmailChanged, &req)
+			.actor(&admin.user.id.unwrap().to_hex())
+			.subject(&id)
+			.detail("previous", previous_email)
+			.detail("email", &user.email_scope.email)
+			.record(&db)
+			.await;
+	}
+	event.record(&db).await;
+
+	Ok(Json(user.into()))
+}
+
+/// ADMIN Delete user
+///
+/// Deletes the user and revokes all their sessions
+#[api_v2_operation]
+#[delete("/users/{id}")]
+pub async fn delete_user(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	session_store: Data<SharedSessionStore>,
+	id: Path<String>,
+) -> Result<Json<DeletedResponse>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	let user = find_user(&db, &id).await?;
+	admin.require_manage(&db, &user).await?;
+	// Safe to unwrap, the user exists. The sessions are keyed by the canonical ID, not the one typed in the path
+	let user_id = user.id.unwrap().to_hex();
+
+	user.delete(&db).await?;
+	revoke_user_sessions(&session_store, &user_id).await?;
+	revoke_hydra_sessions(&user_id).await?;
+
+	admin
+		.audit(&req, "delete-user")
+		.subject(&user_id)
+		.detail("email", &user.email_scope.email)
+		.record(&db)
+		.await;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ADMIN Verify user email
+///
+/// Marks the user email as verified without a code
+#[api_v2_operation]
+#[post("/users/{id}/verify-email")]
+pub async fn verify_user_email(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+) -> Result<Json<AdminUserView>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	let mut user = find_user(&db, &id).await?;
+
+	user.email_scope.email_verified = true;
+	user.save(&db, None).await?;
+
+	admin.audit(&req, "verify-user-email").subject(&id).record(&db).await;
+
+	Ok(Json(user.into()))
+}
+
+/// ADMIN Trigger password reset
+///
+/// Sends the password reset code to the user, as if they asked for it
+#[api_v2_operation]
+#[post("/users/{id}/password-reset")]
+pub async fn trigger_password_reset(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	id: Path<String>,
+) -> Result<Json<EmailSentResponse>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	let user = find_user(&db, &id).await?;
+	admin.require_manage(&db, &user).await?;
+
+	send_password_reset_email(&user)?;
+
+	admin
+		.audit(&req, "trigger-password-reset")
+		.subject(&id)
+		.record(&db)
+		.await;
+
+	Ok(Json(EmailSentResponse { email_sent: true }))
+}
+
+/// ADMIN Set user status
+///
+/// Suspends, locks, schedules for deletion or reactivates the account.
+/// Inactive accounts cannot log in, all their sessions and OAuth tokens are revoked
+#[api_v2_operation]
+#[put("/users/{id}/status")]
+pub async fn set_user_status(
+	req: HttpRequest,
+	admin: AuthorizedUser,
+	db: Data<MongoDatabase>,
+	session_store: Data<SharedSessionStore>,
+	id: Path<String>,
+	Json(status): Json<AccountStatus>,
+) -> Result<Json<AdminUserView>, AdminErrors> {
+	admin.require(USERS_WRITE_PERMISSION)?;
+
+	let mut user = find_user(&db, &id).await?;
+	admin.require_manage(&db, &user).await?;
+	// Safe to unwrap, the user exists. The sessions are keyed by the canonical ID, not the one typed in the path
+	let user_id = user.id.unwrap().to_hex();
+
+	user.status = status;
+	user.save(&db, None).await?;
+
+	if !user.is_active() {
+		revoke_user_sessions(&session_store, &user_id).await?;
+		revoke_hydra_sessions(&user_id).await?;
+		// Safe to unwrap, the admin exists
+		AuditEvent::new(AuditAction::SessionsRevoked, &req)
+			.actor(&admin.user.id.unwrap().to_hex())
+			.subject(&user_id)
+			.detail("reason", "account inactive")
+			.record(&db)
+			.await;
+	}
+
+	admin
+		.audit(&req, "set-user-status")
+		.subject(&user_id)
+		.detail("state", user.status.state)
+		.record(&db)
+		.await;
+
+	Ok(Json(user.into()))
+}
+
+/// ADMIN Hasher metrics
+///
+/// Load of the password hashing pool
+#[api_v2_operation]
+#[get("/metrics/hasher")]
+pub async fn hasher_metrics(admin: AuthorizedUser) -> Result<Json<HasherMetricsSnapshot>, AdminErrors> {
+	admin.require(METRICS_READ_PERMISSION)?;
+
+	Ok(Json(HASHER_METRICS.snapshot()))
+}
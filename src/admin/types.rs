@@ -0,0 +1,354 @@
+use std::collections::BTreeMap;
+
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::oid::ObjectId;
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	role::{Group, Permission, Role},
+	signup::SignupInvitation,
+	user::{
+		AccountState, AccountStatus, AddressScope, EmailScope, LinkedIdentity, PhoneScope, ProfileScope, User,
+		UsernameChange,
+	},
+	utils::serialize_object_id,
+};
+
+/// Default and maximum page sizes of the user and audit event listings
+pub const DEFAULT_PER_PAGE: i64 = 20;
+pub const MAX_PER_PAGE: i64 = 100;
+
+/// User listing filters and pagination
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListQuery {
+	/// Case insensitive substring of the email
+	pub email: Option<String>,
+	/// Only users with a verified (or unverified) email
+	pub verified: Option<bool>,
+	/// Only users with this role
+	pub role: Option<String>,
+	/// Only users in this account state
+	pub state: Option<AccountState>,
+	/// Only users created at or after this unix timestamp
+	pub created_after: Option<i64>,
+	/// Only users created before this unix timestamp
+	pub created_before: Option<i64>,
+	/// The page number, starting from 1
+	pub page: Option<u64>,
+	/// The page size, at most 100
+	pub per_page: Option<i64>,
+}
+
+/// User representation for admins, it includes the account state but never the password hash
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserView {
+	/// The ID of the user
+	#[serde(rename = "_id", serialize_with = "serialize_object_id")]
+	pub id: Option<ObjectId>,
+	/// The normalized username used to log in
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub username: Option<String>,
+	/// The past username changes
+	pub username_history: Vec<UsernameChange>,
+	/// The linked upstream provider accounts
+	pub identities: Vec<LinkedIdentity>,
+	/// The LDAP directory authenticating the account
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub directory: Option<String>,
+	/// OpenID Connect Email scope
+	#[serde(flatten)]
+	pub email_scope: EmailScope,
+	/// OpenID Connect Profile scope
+	#[serde(flatten)]
+	pub profile_scope: ProfileScope,
+	/// OpenID Connect phone scope
+	#[serde(flatten)]
+	pub phone_scope: PhoneScope,
+	/// OpenID Connect address scope
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub address: Option<AddressScope>,
+	/// The user's directly assigned roles
+	pub roles: Vec<String>,
+	/// The user's groups
+	pub groups: Vec<String>,
+	/// Only active accounts can log in
+	pub status: AccountStatus,
+	/// Creation unix timestamp, taken from the ID
+	pub created_at: Option<i64>,
+}
+
+impl From<User> for AdminUserView {
+	fn from(user: User) -> Self {
+		let User {
+			id,
+			username,
+			username_history,
+			identities,
+			directory,
+			email_scope,
+			profile_scope,
+			phone_scope,
+			address,
+			roles,
+			groups,
+			status,
+			..
+		} = user;
+
+		Self {
+			created_at: id.map(|id| id.timestamp().timestamp_millis() / 1000),
+			id,
+			username,
+			username_history,
+			identities,
+			directory,
+			email_scope,
+			profile_scope,
+			phone_scope,
+			address,
+			roles,
+			groups,
+			status,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserListResponse {
+	pub items: Vec<AdminUserView>,
+	/// The page number, starting from 1
+	pub page: u64,
+	pub per_page: i64,
+	/// The number of users matching the filters
+	pub total: u64,
+}
+
+/// Fields an admin can change, missing fields are left untouched
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateUserInput {
+	/// A new email, it is unverified unless `emailVerified` is also given
+	#[validate(email)]
+	pub email: Option<String>,
+	pub email_verified: Option<bool>,
+	/// A new username, checked against the username policy but not the change cooldown
+	pub preferred_username: Option<String>,
+	/// Replaces all the user's roles
+	pub roles: Option<Vec<String>>,
+	/// Replaces all the user's groups
+	pub groups: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct PermissionInput {
+	/// The unique permission name
+	#[validate(length(min = 1))]
+	pub name: String,
+	pub description: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct PermissionView {
+	pub name: String,
+	pub description: Option<String>,
+}
+
+impl From<Permission> for PermissionView {
+	fn from(permission: Permission) -> Self {
+		Self {
+			name: permission.name,
+			description: permission.description,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct RoleInput {
+	/// The unique role name
+	#[validate(length(min = 1))]
+	pub name: String,
+	pub description: Option<String>,
+	/// The names of the granted permissions
+	#[serde(default)]
+	pub permissions: Vec<String>,
+}
+
+/// Fields of a role that can be changed, missing fields are left untouched
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+pub struct UpdateRoleInput {
+	pub description: Option<String>,
+	/// Replaces all the granted permissions
+	pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct RoleView {
+	pub name: String,
+	pub description: Option<String>,
+	pub permissions: Vec<String>,
+}
+
+impl From<Role> for RoleView {
+	fn from(role: Role) -> Self {
+		Self {
+			name: role.name,
+			description: role.description,
+			permissions: role.permissions,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct GroupInput {
+	/// The unique group name
+	#[validate(length(min = 1))]
+	pub name: String,
+	pub description: Option<String>,
+	/// The names of the roles given to the members
+	#[serde(default)]
+	pub roles: Vec<String>,
+}
+
+/// Fields of a group that can be changed, missing fields are left untouched
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+pub struct UpdateGroupInput {
+	pub description: Option<String>,
+	/// Replaces all the roles given to the members
+	pub roles: Option<Vec<String>>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct GroupView {
+	pub name: String,
+	pub description: Option<String>,
+	pub roles: Vec<String>,
+}
+
+impl From<Group> for GroupView {
+	fn from(group: Group) -> Self {
+		Self {
+			name: group.name,
+			description: group.description,
+			roles: group.roles,
+		}
+	}
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletedResponse {
+	/// This should always be true
+	pub deleted: bool,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct SignupInvitationInput {
+	/// The invited email, the invitation code is sent there
+	#[validate(email)]
+	pub email: String,
+	/// The roles given to the new user
+	#[serde(default)]
+	pub roles: Vec<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct SignupInvitationView {
+	#[serde(serialize_with = "serialize_object_id")]
+	pub id: Option<ObjectId>,
+	pub email: String,
+	pub roles: Vec<String>,
+	/// Expiration unix timestamp
+	pub expires_at: i64,
+}
+
+impl From<SignupInvitation> for SignupInvitationView {
+	fn from(invitation: SignupInvitation) -> Self {
+		Self {
+			id: invitation.id,
+			email: invitation.email,
+			roles: invitation.roles,
+			expires_at: invitation.expires_at,
+		}
+	}
+}
+
+/// Audit event listing filters and pagination
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventQuery {
+	/// Only events of this action
+	pub action: Option<AuditAction>,
+	/// Only events of this acting user ID
+	pub actor: Option<String>,
+	/// Only events on this user ID
+	pub subject: Option<String>,
+	/// Only events of this OAuth client
+	pub client_id: Option<String>,
+	/// Only events from this IP address
+	pub ip: Option<String>,
+	/// Only events of this request
+	pub request_id: Option<String>,
+	/// Only events at or after this unix timestamp
+	pub after: Option<i64>,
+	/// Only events before this unix timestamp
+	pub before: Option<i64>,
+	/// The page number, starting from 1
+	pub page: Option<u64>,
+	/// The page size, at most 100
+	pub per_page: Option<i64>,
+}
+
+/// Audit event representation for admins
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventView {
+	#[serde(serialize_with = "serialize_object_id")]
+	pub id: Option<ObjectId>,
+	pub action: AuditAction,
+	pub actor: Option<String>,
+	pub subject: Option<String>,
+	pub client_id: Option<String>,
+	pub factor: Option<String>,
+	pub ip: String,
+	pub user_agent: String,
+	pub request_id: String,
+	pub details: BTreeMap<String, String>,
+	/// Event unix timestamp
+	pub created_at: i64,
+}
+
+impl From<AuditEvent> for AuditEventView {
+	fn from(event: AuditEvent) -> Self {
+		Self {
+			id: event.id,
+			action: event.action,
+			actor: event.actor,
+			subject: event.subject,
+			client_id: event.client_id,
+			factor: event.factor,
+			ip: event.ip,
+			user_agent: event.user_agent,
+			request_id: event.request_id,
+			details: event.details,
+			created_at: event.created_at.timestamp_millis() / 1000,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEventListResponse {
+	/// The newest events first
+	pub items: Vec<AuditEventView>,
+	/// The page number, starting from 1
+	pub page: u64,
+	pub per_page: i64,
+	/// The number of events matching the filters
+	pub total: u64,
+}
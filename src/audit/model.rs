@@ -0,0 +1,179 @@
+use std::{collections::BTreeMap, time::Duration};
+
+use actix_web::HttpRequest;
+use log::error;
+use ory_hydra_client::models::OAuth2Client;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId, DateTime},
+	mongodb::{error::Error as MongoError, options::IndexOptions, Database, IndexModel},
+	prelude::*,
+};
+
+use crate::{
+	settings::APP_SETTINGS,
+	utils::{client_ip, request_id, user_agent},
+};
+
+/// The name of the index expiring the events after the retention period
+const RETENTION_INDEX_NAME: &str = "created_at_retention";
+
+/// The recorded security events
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AuditAction {
+	Signup,
+	LoginSucceeded,
+	LoginFailed,
+	EmailVerified,
+	ConsentGranted,
+	SessionsRevoked,
+	Logout,
+	PasswordChanged,
+	PasswordReset,
+	UsernameChanged,
+	EmailChanged,
+	AdminAction,
+}
+
+impl AuditAction {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Signup => "signup",
+			Self::LoginSucceeded => "login-succeeded",
+			Self::LoginFailed => "login-failed",
+			Self::EmailVerified => "email-verified",
+			Self::ConsentGranted => "consent-granted",
+			Self::SessionsRevoked => "sessions-revoked",
+			Self::Logout => "logout",
+			Self::PasswordChanged => "password-changed",
+			Self::PasswordReset => "password-reset",
+			Self::UsernameChanged => "username-changed",
+			Self::EmailChanged => "email-changed",
+			Self::AdminAction => "admin-action",
+		}
+	}
+}
+
+/// A security event, kept for the configured retention period.
+///
+/// Built with `AuditEvent::new` and the setters, then recorded with `record`
+#[derive(Debug, Model, Serialize, Deserialize)]
+pub struct AuditEvent {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	pub action: AuditAction,
+	/// The ID of the user who acted, missing for anonymous requests
+	pub actor: Option<String>,
+	/// The ID of the user acted upon
+	pub subject: Option<String>,
+	/// The OAuth client of the login or consent
+	pub client_id: Option<String>,
+	/// The authentication factor of the login, e.g. `password` or `login-code`
+	pub factor: Option<String>,
+	pub ip: String,
+	pub user_agent: String,
+	pub request_id: String,
+	/// Event specific details, e.g. the failure reason or the changed fields
+	#[serde(default)]
+	pub details: BTreeMap<String, String>,
+	/// A date rather than a unix timestamp, the retention index needs it
+	pub created_at: DateTime,
+}
+
+impl AuditEvent {
+	/// An event of the request
+	pub fn new(action: AuditAction, req: &HttpRequest) -> Self {
+		Self {
+			id: None,
+			action,
+			actor: None,
+			subject: None,
+			client_id: None,
+			factor: None,
+			ip: client_ip(req),
+			user_agent: user_agent(req),
+			request_id: request_id(req),
+			details: BTreeMap::new(),
+			created_at: DateTime::now(),
+		}
+	}
+
+	pub fn actor(mut self, actor: &str) -> Self {
+		self.actor = Some(actor.to_string());
+		self
+	}
+
+	pub fn subject(mut self, subject: &str) -> Self {
+		self.subject = Some(subject.to_string());
+		self
+	}
+
+	/// Sets both the actor and the subject, for the users acting on their own account
+	pub fn user(self, user_id: &str) -> Self {
+		self.actor(user_id).subject(user_id)
+	}
+
+	pub fn client(mut self, client: Option<&OAuth2Client>) -> Self {
+		self.client_id = client.and_then(|client| client.client_id.clone());
+		self
+	}
+
+	pub fn factor(mut self, factor: &str) -> Self {
+		self.factor = Some(factor.to_string());
+		self
+	}
+
+	pub fn detail(mut self, key: &str, value: impl ToString) -> Self {
+		self.details.insert(key.to_string(), value.to_string());
+		self
+	}
+
+	/// Saves the event, never failing the request, the errors are only logged
+	pub async fn record(mut self, db: &Database) {
+		if let Err(e) = self.save(db, None).await {
+			error!("Could not record the audit event {:?}: {:?}", self, e);
+		}
+	}
+
+	/// Creates the query indexes and the retention index, updating the retention when it changed
+	pub async fn sync_indexes(db: &Database) -> Result<(), MongoError> {
+		let collection = Self::collection(db);
+
+		// Safe to unwrap, the retention is validated when the settings are loaded
+		let expire_after = APP_SETTINGS.audit.retention_seconds().unwrap();
+		let retention_index = IndexModel::builder()
+			.keys(doc! { "created_at": 1 })
+			.options(
+				IndexOptions::builder()
+					.name(RETENTION_INDEX_NAME.to_string())
+					.expire_after(Duration::from_secs(expire_after))
+					.build(),
+			)
+			.build();
+		// The options of an existing index cannot be replaced by creating it again
+		if collection.create_index(retention_index, None).await.is_err() {
+			let command = doc! {
+				"collMod": Self::COLLECTION_NAME,
+				"index": { "name": RETENTION_INDEX_NAME, "expireAfterSeconds": expire_after as i64 },
+			};
+			db.run_command(command, None).await?;
+		}
+
+		let query_indexes = vec![
+			IndexModel::builder()
+				.keys(doc! { "subject": 1, "created_at": -1 })
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "actor": 1, "created_at": -1 })
+				.build(),
+			IndexModel::builder()
+				.keys(doc! { "action": 1, "created_at": -1 })
+				.build(),
+		];
+		collection.create_indexes(query_indexes, None).await?;
+
+		Ok(())
+	}
+}
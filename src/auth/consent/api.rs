@@ -1,17 +1,27 @@
+use actix_web::HttpRequest;
 use log::{error, info};
 use ory_hydra_client::{
 	apis::admin_api,
-	models::{AcceptConsentRequest, CompletedRequest, ConsentRequest, ConsentRequestSession},
+	models::{AcceptConsentRequest, CompletedRequest, ConsentRequest, ConsentRequestSession, RejectRequest},
 };
+use serde_json::{json, Map};
 use wither::{bson::oid::ObjectId, mongodb::Database as MongoDatabase};
 
 use crate::{
+	audit::{AuditAction, AuditEvent},
+	organization::user_memberships,
+	role::UserAccess,
 	settings::ORY_HYDRA_CONFIGURATION,
 	user::{User, UserInfo},
 };
 
 use super::ConsentErrors;
 
+/// Scope granting the `roles` and `permissions` claims
+pub const ROLES_SCOPE: &str = "roles";
+/// Scope granting the `org` claim, the organization memberships
+pub const ORG_SCOPE: &str = "org";
+
 pub async fn create_user_session(
 	subject: &str,
 	db: &MongoDatabase,
@@ -40,12 +50,36 @@ pub async fn create_user_session(
 	// 	id_token: Some(serde_json::to_value(&user_info)?),
 	// 	access_token: Some(serde_json::to_value(&user_info)?),
 	// };
-	let session = ConsentRequestSession::new();
+	let mut session = ConsentRequestSession::new();
+	let mut claims = Map::new();
+
+	// Effective roles, directly assigned and inherited from groups, and their permissions
+	if scopes.iter().any(|scope| scope == ROLES_SCOPE) {
+		let access = UserAccess::resolve(db, &user).await?;
+		claims.insert("roles".to_string(), json!(access.roles));
+		claims.insert("permissions".to_string(), json!(access.permissions));
+	}
+
+	if scopes.iter().any(|scope| scope == ORG_SCOPE) {
+		let memberships = user_memberships(db, &id).await?;
+		let org: Vec<_> = memberships
+			.into_iter()
+			.map(|(organization, role)| json!({ "name": organization.name, "role": role }))
+			.collect();
+		claims.insert("org".to_string(), json!(org));
+	}
+
+	if !claims.is_empty() {
+		session.id_token = Some(claims.clone().into());
+		session.access_token = Some(claims.into());
+	}
 	info!("{:?}", &session);
 	Ok(session)
 }
 
+/// Accepts the consent request with the granted scopes, and records the grant in the audit log
 pub async fn handle_accept_consent_request(
+	req: &HttpRequest,
 	subject: &str,
 	db: &MongoDatabase,
 	ask_consent_request: &ConsentRequest,
@@ -53,6 +87,14 @@ pub async fn handle_accept_consent_request(
 	consent_challenge: &str,
 ) -> Result<CompletedRequest, ConsentErrors> {
 	info!("Handling accept_consent_request");
+
+	// The account may have been suspended since the login
+	let id = ObjectId::parse_str(subject)?;
+	let active = User::find_by_id(db, &id).await?.map_or(false, |user| user.is_active());
+	if !active {
+		return handle_reject_consent_request(consent_challenge, "The account is not active").await;
+	}
+
 	let mut body = AcceptConsentRequest::new();
 	body.grant_access_token_audience = ask_consent_request.requested_access_token_audience.clone();
 	body.grant_scope = Some(scopes.to_vec());
@@ -68,5 +110,34 @@ pub async fn handle_accept_consent_request(
 				error!("{:?}", e);
 				ConsentErrors::HydraError
 			})?;
+
+	AuditEvent::new(AuditAction::ConsentGranted, req)
+		.user(subject)
+		.client(ask_consent_request.client.as_deref())
+		.detail("scopes", scopes.join(" "))
+		.record(db)
+		.await;
+
 	Ok(accept_consent_request)
 }
+
+pub async fn handle_reject_consent_request(
+	consent_challenge: &str,
+	error_description: &str,
+) -> Result<CompletedRequest, ConsentErrors> {
+	info!("Rejecting consent request");
+
+	let mut body = RejectRequest::new();
+	body.error = Some("access_denied".to_string());
+	body.error_description = Some(error_description.to_string());
+
+	let reject_consent_request =
+		admin_api::reject_consent_request(&ORY_HYDRA_CONFIGURATION, consent_challenge, Some(body))
+			.await
+			.map_err(|e| {
+				error!("{:?}", e);
+				ConsentErrors::HydraError
+			})?;
+
+	Ok(reject_consent_request)
+}
@@ -3,6 +3,7 @@ use crate::{
 	settings::{APP_SETTINGS, ORY_HYDRA_CONFIGURATION},
 };
 
+use actix_web::HttpRequest;
 use log::{error, info};
 use ory_hydra_client::{apis::admin_api, models::ConsentRequest};
 use paperclip::actix::{
@@ -21,6 +22,7 @@ use super::{ConsentErrors, OAuthConsentBody, OAuthConsentRequest};
 #[api_v2_operation]
 #[get("/consent")]
 pub async fn get_consent(
+	req: HttpRequest,
 	oauth_request: Query<OAuthConsentRequest>,
 	db: Data<MongoDatabase>,
 ) -> Result<HttpResponse, ConsentErrors> {
@@ -53,25 +55,14 @@ pub async fn get_consent(
 
 	// info!("{:?}", &redirect_to);
 
-	let metadata = match client {
-		Some(client) => {
-			if let Some(metadata) = client.metadata {
-				if let Ok(parsed_metadata) = serde_json::from_value(metadata) {
-					parsed_metadata
-				}
-				Metadata::default()
-			} else {
-				Metadata::default()
-			}
-		}
-		None => Metadata::default(),
-	};
+	let metadata = Metadata::from_client(client.as_deref());
 
 	let mut redirect_to;
 
 	// If the oauth client is trusted or the User has already given consent, accept the consent
 	if metadata.is_trusted || ask_consent_request.skip.unwrap_or(false) {
 		let accept_consent_request = handle_accept_consent_request(
+			&req,
 			&subject,
 			&db,
 			&ask_consent_request,
@@ -107,6 +98,7 @@ pub async fn get_consent(
 #[api_v2_operation]
 #[post("/consent")]
 pub async fn post_consent(
+	req: HttpRequest,
 	oauth_request: Query<OAuthConsentRequest>,
 	data: Json<OAuthConsentBody>,
 	db: Data<MongoDatabase>,
@@ -128,8 +120,15 @@ pub async fn post_consent(
 		.as_ref()
 		.ok_or(ConsentErrors::UserNotFound)?;
 
-	let accept_consent_request =
-		handle_accept_consent_request(subject, &db, &ask_consent_request, &data.scopes, consent_challenge).await?;
+	let accept_consent_request = handle_accept_consent_request(
+		&req,
+		subject,
+		&db,
+		&ask_consent_request,
+		&data.scopes,
+		consent_challenge,
+	)
+	.await?;
 
 	info!("{:?}", &accept_consent_request);
 
@@ -0,0 +1,56 @@
+use wither::{mongodb::Database as MongoDatabase, WitherError};
+
+use crate::{
+	auth::find_provider,
+	settings::APP_SETTINGS,
+	user::{normalize_email, User},
+};
+
+use super::LoginMethod;
+
+/// Finds the provider of the realm rule matching the email domain
+fn realm_provider(email: &str) -> Option<&'static str> {
+	let domain = normalize_email(email)
+		.rsplit('@')
+		.next()
+		.unwrap_or_default()
+		.to_string();
+	APP_SETTINGS
+		.realm
+		.rules
+		.iter()
+		.find(|rule| rule.email_domains().contains(&domain))
+		.map(|rule| rule.provider.as_str())
+		.filter(|provider| find_provider(provider).is_ok())
+}
+
+/// Picks the login method from the domain rules, then from the methods of the account.
+///
+/// Unknown logins get the password form, like the accounts with a password
+pub async fn discover_login_method(
+	db: &MongoDatabase,
+	login: &str,
+) -> Result<(LoginMethod, Option<String>), WitherError> {
+	if login.contains('@') {
+		if let Some(provider) = realm_provider(login) {
+			return Ok((LoginMethod::Federation, Some(provider.to_string())));
+		}
+	}
+
+	if let Some(user) = User::find_by_login(db, login).await? {
+		// Accounts without a password log in with their most recently used provider
+		if !user.has_password() && user.directory.is_none() {
+			let provider = user
+				.identities
+				.iter()
+				.filter(|identity| find_provider(&identity.provider).is_ok())
+				.max_by_key(|identity| identity.last_used_at.unwrap_or(identity.linked_at))
+				.map(|identity| identity.provider.clone());
+			if provider.is_some() {
+				return Ok((LoginMethod::Federation, provider));
+			}
+		}
+	}
+
+	Ok((LoginMethod::Password, None))
+}
@@ -0,0 +1,7 @@
+pub mod api;
+pub mod routes;
+pub mod types;
+
+pub use api::*;
+pub use routes::*;
+pub use types::*;
@@ -0,0 +1,86 @@
+use actix_web::HttpRequest;
+use paperclip::actix::{
+	api_v2_operation, post,
+	web::{Data, Json},
+};
+use url::Url;
+use wither::mongodb::Database as MongoDatabase;
+
+use crate::{
+	auth::{fetch_login_request, AuthErrors, LoginErrors},
+	settings::APP_SETTINGS,
+	throttle::{Throttle, ThrottleAction},
+	utils::client_ip,
+};
+
+use super::{discover_login_method, DiscoveryInput, DiscoveryResponse, LoginMethod};
+
+/// LOCAL Discover login method
+///
+/// Identifier-first login: tells whether to show the password form or to redirect to an upstream provider,
+/// from the email domain rules and the login methods of the account
+#[api_v2_operation]
+#[post("/discover")]
+pub async fn discover(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	Json(discovery_input): Json<DiscoveryInput>,
+) -> Result<Json<DiscoveryResponse>, LoginErrors> {
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::Discovery, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+	// Every request counts against the IP, accounts could be probed otherwise
+	throttle
+		.record_failure(ThrottleAction::Discovery, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	let DiscoveryInput { login, login_challenge } = discovery_input;
+	let login = match (login, &login_challenge) {
+		(Some(login), _) => Some(login),
+		(None, Some(login_challenge)) => fetch_login_request(login_challenge)
+			.await?
+			.oidc_context
+			.and_then(|oidc_context| oidc_context.login_hint),
+		(None, None) => None,
+	};
+	let login = match login
+		.map(|login| login.trim().to_string())
+		.filter(|login| !login.is_empty())
+	{
+		Some(login) => login,
+		None => {
+			return Ok(Json(DiscoveryResponse {
+				method: LoginMethod::Password,
+				login: None,
+				provider: None,
+				redirect_to: None,
+			}))
+		}
+	};
+
+	let (method, provider) = discover_login_method(&db, &login).await?;
+	let redirect_to = match &provider {
+		Some(provider) => {
+			let mut redirect_to = Url::parse(&APP_SETTINGS.federation.authorize_uri(provider))?;
+			if let Some(login_challenge) = &login_challenge {
+				redirect_to
+					.query_pairs_mut()
+					.append_pair("login_challenge", login_challenge);
+			}
+			redirect_to.query_pairs_mut().append_pair("login_hint", &login);
+			Some(redirect_to.to_string())
+		}
+		None => None,
+	};
+
+	Ok(Json(DiscoveryResponse {
+		method,
+		login: Some(login),
+		provider,
+		redirect_to,
+	}))
+}
@@ -0,0 +1,37 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+/// Home realm discovery input, at least one of the fields is needed
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryInput {
+	/// The email or username typed by the user.
+	pub login: Option<String>,
+	/// The OAuth login challenge, its `login_hint` is used when no login is given.
+	pub login_challenge: Option<String>,
+}
+
+/// How the user logs in
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+	/// The password form, checked locally or by the LDAP directory of the email domain
+	Password,
+	/// A redirect to an upstream identity provider
+	Federation,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoveryResponse {
+	pub method: LoginMethod,
+	/// The login to prefill the password form with
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub login: Option<String>,
+	/// The upstream provider identifier, for the `federation` method
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub provider: Option<String>,
+	/// The URL starting the login with the upstream provider, for the `federation` method
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redirect_to: Option<String>,
+}
@@ -1,29 +1,52 @@
-use actix_web::{http::StatusCode, Error as ActixError, HttpResponse, ResponseError};
+use actix_web::{
+	http::{header, StatusCode},
+	Error as ActixError, HttpResponse, ResponseError,
+};
 use handlebars::RenderError;
 use lettre::{address::AddressError, error::Error as LettreError};
 use paperclip::actix::api_v2_errors;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use thiserror::Error;
 use url::ParseError;
 use wither::WitherError;
 
-use crate::{user::UserErrors, utils::PasswordErrors};
+use crate::{
+	directory::DirectoryErrors,
+	password::{PasswordPolicyErrors, PasswordViolation},
+	session::{SessionErrors, SessionStoreErrors},
+	signup::SignupErrors,
+	throttle::ThrottleErrors,
+	user::UserErrors,
+	utils::PasswordErrors,
+};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Serialize)]
 struct ErrorResponse {
 	error: String,
+	#[serde(skip_serializing_if = "Vec::is_empty")]
+	violations: Vec<PasswordViolation>,
 }
 
 // TODO: fix errors
 #[api_v2_errors(
 	code = 400,
-	description = "Wrong input",
+	description = "Wrong input, password policy violations are listed in `violations`",
 	code = 401,
-	description = "Wrong password",
+	description = "Invalid credentials or expired session",
+	code = 403,
+	description = "The account is not active, the signup policy does not allow the email or the username cannot be changed yet",
 	code = 404,
-	description = "User not found, probably wrong credentials",
+	description = "User not found",
+	code = 409,
+	description = "The username is taken",
+	code = 429,
+	description = "Too many attempts, retry after the seconds in the Retry-After header",
 	code = 500,
-	description = "Internal server error, could be a db connection error, email server error"
+	description = "Internal server error, could be a db connection error, email server error",
+	code = 502,
+	description = "The LDAP directory is unavailable",
+	code = 503,
+	description = "Too many concurrent logins, retry later"
 )]
 #[derive(Error, Debug)]
 pub enum AuthErrors {
@@ -45,14 +68,31 @@ pub enum AuthErrors {
 	InvalidEmailAddress(#[from] AddressError),
 	#[error("Could not send email!")]
 	SendEmailError,
+	#[error("{0}")]
+	ThrottleError(#[from] ThrottleErrors),
+	#[error("Internal server error")]
+	SessionStoreError(#[from] SessionStoreErrors),
+	#[error("{0}")]
+	PasswordPolicyError(#[from] PasswordPolicyErrors),
+	#[error("{0}")]
+	SignupError(#[from] SignupErrors),
 }
 
 impl ResponseError for AuthErrors {
 	fn error_response(&self) -> HttpResponse {
+		let violations = match self {
+			Self::PasswordPolicyError(PasswordPolicyErrors::Violations(violations)) => violations.clone(),
+			_ => Vec::new(),
+		};
 		let error_response = ErrorResponse {
 			error: self.to_string(),
+			violations,
 		};
-		HttpResponse::build(self.status_code()).json(error_response)
+		let mut response = HttpResponse::build(self.status_code());
+		if let Self::ThrottleError(ThrottleErrors::TooManyAttempts(retry_after)) = self {
+			response.insert_header((header::RETRY_AFTER, retry_after.to_string()));
+		}
+		response.json(error_response)
 	}
 
 	fn status_code(&self) -> StatusCode {
@@ -60,9 +100,26 @@ impl ResponseError for AuthErrors {
 			Self::UserError(UserErrors::DatabaseError(_)) => StatusCode::BAD_REQUEST,
 			Self::UserError(UserErrors::ValidationError(_)) => StatusCode::BAD_REQUEST,
 			Self::UserError(UserErrors::UserNotFound) => StatusCode::NOT_FOUND,
-			Self::UserError(UserErrors::UserWithEmailNotFound(_)) => StatusCode::NOT_FOUND,
+			Self::UserError(UserErrors::InvalidCredentials) => StatusCode::UNAUTHORIZED,
+			Self::UserError(UserErrors::InvalidCode) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::AccountInactive(_)) => StatusCode::FORBIDDEN,
+			Self::UserError(UserErrors::InvalidUsername(_)) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::ReservedUsername) => StatusCode::BAD_REQUEST,
+			Self::UserError(UserErrors::UsernameTaken) => StatusCode::CONFLICT,
+			Self::UserError(UserErrors::UsernameCooldown(_)) => StatusCode::FORBIDDEN,
 			Self::UserError(UserErrors::HashError(PasswordErrors::InvalidPassword)) => StatusCode::UNAUTHORIZED,
+			Self::UserError(UserErrors::SessionStateError(SessionErrors::SessionExpired)) => StatusCode::UNAUTHORIZED,
+			Self::UserError(UserErrors::HashError(PasswordErrors::Overloaded)) => StatusCode::SERVICE_UNAVAILABLE,
+			Self::UserError(UserErrors::DirectoryError(DirectoryErrors::NotProvisioned)) => StatusCode::FORBIDDEN,
+			Self::UserError(UserErrors::DirectoryError(DirectoryErrors::ForeignEmail)) => StatusCode::FORBIDDEN,
+			Self::UserError(UserErrors::DirectoryError(_)) => StatusCode::BAD_GATEWAY,
+			Self::PasswordError(PasswordErrors::Overloaded) => StatusCode::SERVICE_UNAVAILABLE,
 			Self::PasswordError(_) => StatusCode::BAD_REQUEST,
+			Self::ThrottleError(ThrottleErrors::TooManyAttempts(_)) => StatusCode::TOO_MANY_REQUESTS,
+			Self::PasswordPolicyError(PasswordPolicyErrors::Violations(_)) => StatusCode::BAD_REQUEST,
+			Self::SignupError(SignupErrors::DatabaseError(_)) => StatusCode::INTERNAL_SERVER_ERROR,
+			Self::SignupError(SignupErrors::InvalidInvitation) => StatusCode::BAD_REQUEST,
+			Self::SignupError(_) => StatusCode::FORBIDDEN,
 			_ => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
@@ -0,0 +1,102 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use paperclip::actix::api_v2_errors;
+use reqwest::Error as ReqwestError;
+use serde::Serialize;
+use serde_json::Error as JSONError;
+use thiserror::Error;
+use wither::WitherError;
+
+use crate::auth::{AuthErrors, LoginErrors};
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "The login was not started in this browser, or has expired, or there is no account to link",
+	code = 401,
+	description = "The provider answer could not be trusted",
+	code = 403,
+	description = "No account is linked, the email is unverified, the account is inactive, or the logged in account is not the one to link",
+	code = 404,
+	description = "Unknown provider or linked account",
+	code = 409,
+	description = "The provider account is linked to another user, or it is the last login method",
+	code = 502,
+	description = "The provider failed, or denied the authorization",
+	code = 500,
+	description = "Internal server error, could be a db connection error, Hydra error"
+)]
+#[derive(Error, Debug)]
+pub enum FederationErrors {
+	#[error("Unknown identity provider")]
+	UnknownProvider,
+	#[error("The identity provider is not configured correctly")]
+	MisconfiguredProvider,
+	#[error("Invalid or expired login, start again")]
+	InvalidState,
+	#[error("The identity provider answered with an error")]
+	UpstreamError,
+	#[error("Invalid ID token: {0}")]
+	InvalidIdToken(&'static str),
+	#[error("The identity provider did not give the `{0}` claim")]
+	MissingClaim(String),
+	#[error("The identity provider did not verify the email, it cannot be used to sign up or link an account")]
+	UnverifiedEmail,
+	#[error("No account is linked to this identity provider account")]
+	NotLinked,
+	#[error("No provider account is waiting to be linked, or the link has expired")]
+	NoPendingLink,
+	#[error("Log in to the account using the email of the provider account to link it")]
+	PendingLinkMismatch,
+	#[error("This provider account is linked to another user")]
+	IdentityInUse,
+	#[error("Linked account not found")]
+	IdentityNotFound,
+	#[error("The last login method cannot be removed, set a password or link another account first")]
+	LastLoginMethod,
+	#[error("Could not reach the identity provider")]
+	HttpError(#[from] ReqwestError),
+	#[error("Invalid answer from the identity provider")]
+	JSONParseError(#[from] JSONError),
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+	#[error("{0}")]
+	LoginError(#[from] LoginErrors),
+}
+
+impl ResponseError for FederationErrors {
+	fn error_response(&self) -> HttpResponse {
+		match self {
+			Self::AuthError(e) => e.error_response(),
+			Self::LoginError(e) => e.error_response(),
+			_ => {
+				let error_response = ErrorResponse {
+					error: self.to_string(),
+				};
+				HttpResponse::build(self.status_code()).json(error_response)
+			}
+		}
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::UnknownProvider => StatusCode::NOT_FOUND,
+			Self::InvalidState | Self::NoPendingLink => StatusCode::BAD_REQUEST,
+			Self::InvalidIdToken(_) => StatusCode::UNAUTHORIZED,
+			Self::NotLinked | Self::PendingLinkMismatch | Self::UnverifiedEmail => StatusCode::FORBIDDEN,
+			Self::IdentityNotFound => StatusCode::NOT_FOUND,
+			Self::IdentityInUse | Self::LastLoginMethod => StatusCode::CONFLICT,
+			Self::UpstreamError | Self::MissingClaim(_) | Self::HttpError(_) | Self::JSONParseError(_) => {
+				StatusCode::BAD_GATEWAY
+			}
+			Self::AuthError(e) => e.status_code(),
+			Self::LoginError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
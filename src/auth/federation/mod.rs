@@ -0,0 +1,9 @@
+pub mod errors;
+pub mod provider;
+pub mod routes;
+pub mod types;
+
+pub use errors::*;
+pub use provider::*;
+pub use routes::*;
+pub use types::*;
@@ -0,0 +1,516 @@
+use actix_session::Session;
+use actix_web::{http::header::LOCATION, HttpRequest};
+use log::info;
+use paperclip::actix::{
+	api_v2_operation, delete, get, post,
+	web::{Data, HttpResponse, Json, Path, Query},
+};
+use url::Url;
+use wither::{mongodb::Database as MongoDatabase, Model};
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::{check_login_device, complete_login_request, fetch_login_request, AuthErrors},
+	session::{start_session, unix_now},
+	settings::{ProviderSettings, APP_SETTINGS},
+	signup::check_signup_email,
+	user::{check_username, normalize_email, EmailScope, LinkedIdentity, ProfileScope, User, UserErrors},
+	utils::generate_token,
+};
+
+use super::{
+	code_challenge, fetch_upstream_account, find_provider, generate_code_verifier, provider_metadata, AuthorizeQuery,
+	CallbackQuery, FederationErrors, FederationState, IdentitiesResponse, IdentityLinkedResponse, PendingLink,
+	PendingLinkResponse, ProviderView, UpstreamAccount, FEDERATION_STATE_KEY, PENDING_LINK_KEY, PENDING_LINK_LIFETIME,
+};
+
+/// Stores the federated login state and redirects to the provider
+async fn authorization_redirect(
+	provider: &str,
+	session: &Session,
+	login_challenge: Option<String>,
+	login_hint: Option<String>,
+	link_user: Option<String>,
+) -> Result<HttpResponse, FederationErrors> {
+	let settings = find_provider(provider)?;
+	let metadata = provider_metadata(provider, settings).await?;
+
+	let federation_state = FederationState {
+		provider: provider.to_string(),
+		state: generate_token(),
+		nonce: generate_token(),
+		verifier: generate_code_verifier(),
+		login_challenge,
+		link_user,
+	};
+
+	let mut redirect_to =
+		Url::parse(&metadata.authorization_endpoint).map_err(|_| FederationErrors::MisconfiguredProvider)?;
+	redirect_to
+		.query_pairs_mut()
+		.append_pair("response_type", "code")
+		.append_pair("client_id", &settings.clientid)
+		.append_pair("redirect_uri", &APP_SETTINGS.federation.redirect_uri(provider))
+		.append_pair("scope", &settings.scopes)
+		.append_pair("state", &federation_state.state)
+		.append_pair("nonce", &federation_state.nonce)
+		.append_pair("code_challenge", &code_challenge(&federation_state.verifier))
+		.append_pair("code_challenge_method", "S256");
+	if let Some(login_hint) = &login_hint {
+		redirect_to.query_pairs_mut().append_pair("login_hint", login_hint);
+	}
+
+	session
+		.insert(FEDERATION_STATE_KEY, &federation_state)
+		.map_err(AuthErrors::from)?;
+
+	Ok(
+		HttpResponse::Found()
+			.insert_header((LOCATION, redirect_to.as_str()))
+			.finish(),
+	)
+}
+
+/// Links the upstream account to the user and saves it, linking it again is a no-op
+async fn link_identity(
+	db: &MongoDatabase,
+	user: &mut User,
+	provider: &str,
+	subject: &str,
+) -> Result<(), FederationErrors> {
+	if let Some(linked_user) = User::find_by_identity(db, provider, subject).await? {
+		if linked_user.id == user.id {
+			return Ok(());
+		}
+		return Err(FederationErrors::IdentityInUse);
+	}
+
+	user.identities.push(LinkedIdentity {
+		provider: provider.to_string(),
+		subject: subject.to_string(),
+		linked_at: unix_now(),
+		last_used_at: None,
+	});
+	user.save(db, None).await?;
+
+	Ok(())
+}
+
+/// Creates the user of an upstream account, when the provider allows it
+async fn provision_user(
+	db: &MongoDatabase,
+	provider: &str,
+	settings: &ProviderSettings,
+	account: UpstreamAccount,
+	email: String,
+) -> Result<User, FederationErrors> {
+	if !settings.provision {
+		return Err(FederationErrors::NotLinked);
+	}
+	if !account.email_verified {
+		return Err(FederationErrors::UnverifiedEmail);
+	}
+	// The provider configuration acts as an invitation, closed signups create no account
+	check_signup_email(&email, true).map_err(AuthErrors::from)?;
+
+	// The upstream username is kept only when it fits the policy and is free
+	let mut username = None;
+	if let Some(Ok(normalized)) = account.username.as_deref().map(check_username) {
+		if User::find_by_username(db, &normalized).await?.is_none() {
+			username = Some(normalized);
+		}
+	}
+
+	let now = unix_now();
+	let mut user = User {
+		id: None,
+		// No password, the user can set one with a password reset
+		password: String::new(),
+		profile_scope: ProfileScope {
+			preferred_username: username.as_ref().and(account.username),
+			given_name: account.given_name,
+			family_name: account.family_name,
+			..Default::default()
+		},
+		username,
+		email_scope: EmailScope {
+			email,
+			email_verified: true,
+		},
+		identities: vec![LinkedIdentity {
+			provider: provider.to_string(),
+			subject: account.subject,
+			linked_at: now,
+			last_used_at: Some(now),
+		}],
+		..Default::default()
+	};
+	user.save(db, None).await?;
+
+	Ok(user)
+}
+
+/// Redirects to the page where the user logs in to the existing account to link the upstream account
+fn pending_link_redirect(
+	session: &Session,
+	provider: &str,
+	subject: String,
+	user: &User,
+	login_challenge: Option<String>,
+) -> Result<HttpResponse, FederationErrors> {
+	let pending_link = PendingLink {
+		provider: provider.to_string(),
+		subject,
+		// Safe to unwrap, the user exists
+		user: user.id.unwrap().to_hex(),
+		email: user.email_scope.email.clone(),
+		login_challenge,
+		expires_at: unix_now() + PENDING_LINK_LIFETIME,
+	};
+	session
+		.insert(PENDING_LINK_KEY, &pending_link)
+		.map_err(AuthErrors::from)?;
+
+	let mut redirect_to = Url::parse(&APP_SETTINGS.server.clienturi).map_err(AuthErrors::from)?;
+	redirect_to = redirect_to.join("link-account").map_err(AuthErrors::from)?;
+	redirect_to.set_query(Some(&format!("provider={}", provider)));
+
+	Ok(
+		HttpResponse::Found()
+			.insert_header((LOCATION, redirect_to.as_str()))
+			.finish(),
+	)
+}
+
+/// Reads the pending link of the session, expired links are dropped
+fn pending_link(session: &Session) -> Result<PendingLink, FederationErrors> {
+	let pending_link: PendingLink = session
+		.get(PENDING_LINK_KEY)
+		.map_err(AuthErrors::from)?
+		.ok_or(FederationErrors::NoPendingLink)?;
+	if pending_link.expires_at <= unix_now() {
+		session.remove(PENDING_LINK_KEY);
+		return Err(FederationErrors::NoPendingLink);
+	}
+
+	Ok(pending_link)
+}
+
+/// FEDERATION List providers
+///
+/// Lists the upstream identity providers that can be used to log in
+#[api_v2_operation]
+#[get("/providers")]
+pub async fn list_providers() -> Result<Json<Vec<ProviderView>>, FederationErrors> {
+	let mut providers: Vec<ProviderView> = APP_SETTINGS
+		.federation
+		.providers
+		.iter()
+		.map(|(id, provider)| ProviderView {
+			id: id.clone(),
+			name: provider.name.clone(),
+		})
+		.collect();
+	providers.sort_by(|a, b| a.name.cmp(&b.name));
+
+	Ok(Json(providers))
+}
+
+/// FEDERATION Start login
+///
+/// Redirects to the upstream provider, the login continues in its callback.
+/// When started during an OAuth login the login request is accepted once logged in
+#[api_v2_operation]
+#[get("/{provider}/authorize")]
+pub async fn federation_authorize(
+	provider: Path<String>,
+	session: Session,
+	Query(authorize_query): Query<AuthorizeQuery>,
+) -> Result<HttpResponse, FederationErrors> {
+	let AuthorizeQuery {
+		login_challenge,
+		login_hint,
+	} = authorize_query;
+	authorization_redirect(&provider, &session, login_challenge, login_hint, None).await
+}
+
+/// FEDERATION Start link
+///
+/// Redirects to the upstream provider, its account is linked to the logged in user in the callback
+#[api_v2_operation]
+#[get("/{provider}/link")]
+pub async fn federation_link(
+	provider: Path<String>,
+	db: Data<MongoDatabase>,
+	session: Session,
+) -> Result<HttpResponse, FederationErrors> {
+	let user = User::user_from_session(&db, &session).await.map_err(AuthErrors::from)?;
+
+	// Safe to unwrap, the user exists
+	authorization_redirect(&provider, &session, None, None, Some(user.id.unwrap().to_hex())).await
+}
+
+/// FEDERATION Login callback
+///
+/// Logs in with the upstream account, creating the user when the provider allows it.
+/// Redirects to the OAuth login request redirect, or to the Odysseus client.
+/// When an account already uses the email, redirects to the client `link-account` page instead: the user must log in
+/// to that account and confirm the link
+#[api_v2_operation]
+#[get("/{provider}/callback")]
+pub async fn federation_callback(
+	req: HttpRequest,
+	provider: Path<String>,
+	db: Data<MongoDatabase>,
+	session: Session,
+	Query(callback_query): Query<CallbackQuery>,
+) -> Result<HttpResponse, FederationErrors> {
+	let result = federation_login(&req, &provider, &db, &session, callback_query).await;
+
+	if let Err(e) = &result {
+		AuditEvent::new(AuditAction::LoginFailed, &req)
+			.factor("federation")
+			.detail("provider", provider.as_str())
+			.detail("reason", e)
+			.record(&db)
+			.await;
+	}
+
+	result
+}
+
+async fn federation_login(
+	req: &HttpRequest,
+	provider: &str,
+	db: &MongoDatabase,
+	session: &Session,
+	callback_query: CallbackQuery,
+) -> Result<HttpResponse, FederationErrors> {
+	let settings = find_provider(provider)?;
+
+	// Single use, whatever the outcome
+	let federation_state: FederationState = session
+		.get(FEDERATION_STATE_KEY)
+		.map_err(AuthErrors::from)?
+		.ok_or(FederationErrors::InvalidState)?;
+	session.remove(FEDERATION_STATE_KEY);
+	if federation_state.provider != provider || callback_query.state.as_deref() != Some(federation_state.state.as_str()) {
+		return Err(FederationErrors::InvalidState);
+	}
+
+	if let Some(error) = callback_query.error {
+		info!(
+			"The provider {} denied the authorization: {}",
+			provider,
+			callback_query.error_description.unwrap_or(error)
+		);
+		return Err(FederationErrors::UpstreamError);
+	}
+	let code = callback_query.code.ok_or(FederationErrors::InvalidState)?;
+
+	let metadata = provider_metadata(provider, settings).await?;
+	let account = fetch_upstream_account(
+		provider,
+		settings,
+		&metadata,
+		&code,
+		&federation_state.verifier,
+		&federation_state.nonce,
+	)
+	.await?;
+
+	// Linking to the logged in user, who must not have changed in the meantime
+	if let Some(link_user) = &federation_state.link_user {
+		let mut user = User::user_from_session(db, session).await.map_err(AuthErrors::from)?;
+		if user.id.map(|id| id.to_hex()).as_ref() != Some(link_user) {
+			return Err(FederationErrors::InvalidState);
+		}
+		link_identity(db, &mut user, provider, &account.subject).await?;
+
+		return Ok(
+			HttpResponse::Found()
+				.insert_header((LOCATION, APP_SETTINGS.server.clienturi.as_str()))
+				.finish(),
+		);
+	}
+
+	let user = match User::find_by_identity(db, provider, &account.subject).await? {
+		Some(mut user) => {
+			if let Some(identity) = user
+				.identities
+				.iter_mut()
+				.find(|identity| identity.provider == provider && identity.subject == account.subject)
+			{
+				identity.last_used_at = Some(unix_now());
+			}
+			user.save(db, None).await?;
+			user
+		}
+		None => {
+			let email = account
+				.email
+				.as_deref()
+				.map(normalize_email)
+				.ok_or_else(|| FederationErrors::MissingClaim(settings.claims.email.clone()))?;
+			// An unverified email could be anyone's, it must not reach the account of its owner
+			if !account.email_verified {
+				return Err(FederationErrors::UnverifiedEmail);
+			}
+			// Never merged silently, the provider does not prove the ownership of the existing account
+			if let Some(existing) = User::find_by_email(db, &email).await? {
+				return pending_link_redirect(
+					session,
+					provider,
+					account.subject,
+					&existing,
+					federation_state.login_challenge,
+				);
+			}
+			let user = provision_user(db, provider, settings, account, email).await?;
+			// Safe to unwrap since the user exists
+			AuditEvent::new(AuditAction::Signup, req)
+				.user(&user.id.unwrap().to_hex())
+				.detail("method", "federation")
+				.detail("provider", provider)
+				.record(db)
+				.await;
+			user
+		}
+	};
+	if !user.is_active() {
+		return Err(AuthErrors::from(UserErrors::AccountInactive(user.status.state)).into());
+	}
+
+	// Safe to unwrap since the user exists
+	let subject = user.id.unwrap().to_hex();
+	start_session(session, &subject).map_err(|e| AuthErrors::from(UserErrors::from(e)))?;
+	check_login_device(db, &user, req).await;
+
+	let login_request = match &federation_state.login_challenge {
+		Some(login_challenge) => Some(fetch_login_request(login_challenge).await?),
+		None => None,
+	};
+	let client = login_request
+		.as_ref()
+		.map(|login_request| login_request.client.as_ref());
+	AuditEvent::new(AuditAction::LoginSucceeded, req)
+		.user(&subject)
+		.client(client)
+		.factor("federation")
+		.detail("provider", provider)
+		.record(db)
+		.await;
+
+	let redirect_to = match &login_request {
+		Some(login_request) => {
+			// Accept login request, unless the client does not allow the user
+			let completed_login_request = complete_login_request(db, login_request, &subject, &["fed"]).await?;
+			completed_login_request.redirect_to
+		}
+		None => APP_SETTINGS.server.clienturi.clone(),
+	};
+
+	Ok(HttpResponse::Found().insert_header((LOCATION, redirect_to)).finish())
+}
+
+/// FEDERATION Get pending link
+///
+/// Gets the upstream account waiting to be linked, and the email of the account to log in to
+#[api_v2_operation]
+#[get("/link")]
+pub async fn get_pending_link(session: Session) -> Result<Json<PendingLinkResponse>, FederationErrors> {
+	let pending_link = pending_link(&session)?;
+	let settings = find_provider(&pending_link.provider)?;
+
+	Ok(Json(PendingLinkResponse {
+		provider: pending_link.provider,
+		name: settings.name.clone(),
+		email: pending_link.email,
+	}))
+}
+
+/// FEDERATION Confirm pending link
+///
+/// Links the upstream account waiting to be linked, once logged in to the account using its email.
+/// When it was found during an OAuth login the login request is accepted, and `redirectTo` must be followed
+#[api_v2_operation]
+#[post("/link")]
+pub async fn confirm_pending_link(
+	db: Data<MongoDatabase>,
+	session: Session,
+) -> Result<Json<IdentityLinkedResponse>, FederationErrors> {
+	let pending_link = pending_link(&session)?;
+	let mut user = User::user_from_session(&db, &session).await.map_err(AuthErrors::from)?;
+
+	// Safe to unwrap, the user exists
+	let subject = user.id.unwrap().to_hex();
+	if subject != pending_link.user {
+		return Err(FederationErrors::PendingLinkMismatch);
+	}
+
+	link_identity(&db, &mut user, &pending_link.provider, &pending_link.subject).await?;
+	session.remove(PENDING_LINK_KEY);
+
+	let redirect_to = match &pending_link.login_challenge {
+		Some(login_challenge) => {
+			let login_request = fetch_login_request(login_challenge).await?;
+			// Accept login request, unless the client does not allow the user
+			let completed_login_request = complete_login_request(&db, &login_request, &subject, &[]).await?;
+			Some(completed_login_request.redirect_to)
+		}
+		None => None,
+	};
+
+	Ok(Json(IdentityLinkedResponse {
+		identities: user.identities,
+		redirect_to,
+	}))
+}
+
+/// FEDERATION List linked accounts
+///
+/// Lists the upstream accounts linked to the logged in user
+#[api_v2_operation]
+#[get("/identities")]
+pub async fn list_identities(
+	db: Data<MongoDatabase>,
+	session: Session,
+) -> Result<Json<IdentitiesResponse>, FederationErrors> {
+	let user = User::user_from_session(&db, &session).await.map_err(AuthErrors::from)?;
+
+	Ok(Json(IdentitiesResponse {
+		has_password: user.has_password(),
+		identities: user.identities,
+	}))
+}
+
+/// FEDERATION Unlink account
+///
+/// Unlinks an upstream account from the logged in user, the last way to log in cannot be removed
+#[api_v2_operation]
+#[delete("/identities/{provider}/{subject}")]
+pub async fn unlink_identity(
+	path: Path<(String, String)>,
+	db: Data<MongoDatabase>,
+	session: Session,
+) -> Result<Json<IdentitiesResponse>, FederationErrors> {
+	let (provider, subject) = path.into_inner();
+	let mut user = User::user_from_session(&db, &session).await.map_err(AuthErrors::from)?;
+
+	let position = user
+		.identities
+		.iter()
+		.position(|identity| identity.provider == provider && identity.subject == subject)
+		.ok_or(FederationErrors::IdentityNotFound)?;
+	if user.login_methods() <= 1 {
+		return Err(FederationErrors::LastLoginMethod);
+	}
+
+	user.identities.remove(position);
+	user.save(&db, None).await?;
+
+	Ok(Json(IdentitiesResponse {
+		has_password: user.has_password(),
+		identities: user.identities,
+	}))
+}
@@ -0,0 +1,98 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+use crate::user::LinkedIdentity;
+
+/// The session state key holding the pending federated login
+pub const FEDERATION_STATE_KEY: &str = "federation";
+/// The session state key holding the upstream account waiting to be linked to an existing account
+pub const PENDING_LINK_KEY: &str = "federation_pending_link";
+/// Seconds to log in to the existing account and confirm the link
+pub const PENDING_LINK_LIFETIME: i64 = 600;
+
+/// A federated login started in this browser, checked by the callback
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FederationState {
+	pub provider: String,
+	pub state: String,
+	pub nonce: String,
+	/// PKCE code verifier
+	pub verifier: String,
+	/// The Hydra login request to accept once logged in
+	pub login_challenge: Option<String>,
+	/// The logged in user the upstream account is linked to, instead of logging in
+	#[serde(default)]
+	pub link_user: Option<String>,
+}
+
+/// An upstream account using the email of an existing account, linked once the user logs in to that account
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingLink {
+	pub provider: String,
+	pub subject: String,
+	/// The ID of the existing account
+	pub user: String,
+	pub email: String,
+	/// The Hydra login request to accept once linked
+	pub login_challenge: Option<String>,
+	pub expires_at: i64,
+}
+
+/// Federated login start query
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct AuthorizeQuery {
+	/// The OAuth login challenge, when logging in during an OAuth login.
+	pub login_challenge: Option<String>,
+	/// The email or username already known, forwarded to the provider.
+	pub login_hint: Option<String>,
+}
+
+/// Authorization response of the upstream provider
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct CallbackQuery {
+	pub code: Option<String>,
+	pub state: Option<String>,
+	/// Set by the provider when the authorization failed or was denied
+	pub error: Option<String>,
+	pub error_description: Option<String>,
+}
+
+/// A configured upstream provider
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+pub struct ProviderView {
+	/// The provider identifier, used in the routes
+	pub id: String,
+	/// The name shown on the login button
+	pub name: String,
+}
+
+/// An upstream account waiting to be linked
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct PendingLinkResponse {
+	/// The provider identifier
+	pub provider: String,
+	/// The provider name
+	pub name: String,
+	/// The email of the account to log in to
+	pub email: String,
+}
+
+/// The upstream accounts linked to the logged in user
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentitiesResponse {
+	pub identities: Vec<LinkedIdentity>,
+	/// Whether the user can also log in with a password
+	pub has_password: bool,
+}
+
+/// Response of the link confirmation
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityLinkedResponse {
+	pub identities: Vec<LinkedIdentity>,
+	/// Set when the link was made during an OAuth login, the URL to follow
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redirect_to: Option<String>,
+}
@@ -1,9 +1,20 @@
+use actix_session::Session;
+use actix_web::HttpRequest;
 use lettre::{message::MultiPart, Message, Transport};
 use log::{error, info};
+use ory_hydra_client::models::OAuth2Client;
+use serde::{Deserialize, Serialize};
+use wither::mongodb::Database as MongoDatabase;
 
 use crate::{
+	audit::{AuditAction, AuditEvent},
 	auth::AuthErrors,
-	settings::{SMTPSettings, APP_SETTINGS, SMTP_CLIENT},
+	settings::{
+		SMTPSettings, ACCOUNT_LOCKED_TEMPLATE_NAME, APP_SETTINGS, HANDLEBARS, RESET_PASSWORD_TEMPLATE_NAME, SMTP_CLIENT,
+	},
+	throttle::{Throttle, ThrottleAction},
+	user::{email_key, normalize_username, User, UserErrors},
+	utils::{client_ip, hash_token},
 };
 
 pub fn send_email_to_user(
@@ -42,3 +53,152 @@ pub fn send_email_to_user(
 
 	Ok(())
 }
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct AccountLockedEMailData {
+	pub username: String,
+	pub minutes: i64,
+}
+
+/// The throttled account of a login, the email of the user so that the email and the username share the attempts
+async fn login_account(db: &MongoDatabase, login: &str) -> Result<String, AuthErrors> {
+	match User::find_by_login(db, login).await? {
+		Some(user) => Ok(email_key(&user.email_scope.email)),
+		None if login.contains('@') => Ok(email_key(login)),
+		None => Ok(normalize_username(login)),
+	}
+}
+
+/// A short keyed hash of an unknown login, enough to group the attempts on the same login.
+///
+/// Keyed with the server secret, so that a password typed as the login cannot be guessed back from the audit log
+fn unknown_login_hash(login: &str) -> String {
+	let normalized = if login.contains('@') {
+		email_key(login)
+	} else {
+		normalize_username(login)
+	};
+	hash_token(&format!("{}_{}", normalized, APP_SETTINGS.totp.secret))[..16].to_string()
+}
+
+/// Logs in the user with the brute-force protection applied, shared by the local and the OAuth login.
+///
+/// Records the outcome in the audit log, along with the OAuth client of the login request
+pub async fn login_throttled(
+	db: &MongoDatabase,
+	session: &Session,
+	throttle: &Throttle,
+	login: &str,
+	password: &str,
+	req: &HttpRequest,
+	client: Option<&OAuth2Client>,
+) -> Result<User, AuthErrors> {
+	let result = try_login(db, session, throttle, login, password, &client_ip(req)).await;
+
+	match &result {
+		Ok(user) => {
+			// Safe to unwrap, the user exists
+			let subject = user.id.unwrap().to_hex();
+			let factor = if user.directory.is_some() { "ldap" } else { "password" };
+			AuditEvent::new(AuditAction::LoginSucceeded, req)
+				.user(&subject)
+				.client(client)
+				.factor(factor)
+				.record(db)
+				.await;
+		}
+		Err(e) => {
+			let mut event = AuditEvent::new(AuditAction::LoginFailed, req)
+				.client(client)
+				.detail("reason", e);
+			event = match User::find_by_login(db, login).await {
+				// Safe to unwrap, the user exists
+				Ok(Some(user)) => event.subject(&user.id.unwrap().to_hex()),
+				// Never the typed login, it may be a password pasted in the wrong field
+				_ => event.detail("loginHash", unknown_login_hash(login)),
+			};
+			event.record(db).await;
+		}
+	}
+
+	result
+}
+
+async fn try_login(
+	db: &MongoDatabase,
+	session: &Session,
+	throttle: &Throttle,
+	login: &str,
+	password: &str,
+	ip: &str,
+) -> Result<User, AuthErrors> {
+	let account = login_account(db, login).await?;
+
+	throttle.check(ThrottleAction::Login, Some(&account), ip).await?;
+
+	match User::login_with_session(db, session, login, password).await {
+		Ok(user) => {
+			throttle.record_success(ThrottleAction::Login, &account, ip).await?;
+			Ok(user)
+		}
+		Err(e @ UserErrors::InvalidCredentials) => {
+			let account_locked = throttle.record_failure(ThrottleAction::Login, Some(&account), ip).await?;
+			if account_locked {
+				notify_account_locked(db, login).await;
+			}
+			Err(e.into())
+		}
+		Err(e) => Err(e.into()),
+	}
+}
+
+/// Warns the account owner that the account has been locked, failures are only logged
+async fn notify_account_locked(db: &MongoDatabase, login: &str) {
+	let user = match User::find_by_login(db, login).await {
+		Ok(Some(user)) => user,
+		Ok(None) => return,
+		Err(e) => {
+			error!("{:?}", e);
+			return;
+		}
+	};
+
+	let username = user.display_name();
+
+	let account_locked_data = AccountLockedEMailData {
+		username: username.clone(),
+		minutes: APP_SETTINGS.throttle.lockout / 60,
+	};
+
+	let result = HANDLEBARS
+		.render(ACCOUNT_LOCKED_TEMPLATE_NAME, &account_locked_data)
+		.map_err(AuthErrors::from)
+		.and_then(|html_mail| {
+			let email_title = "Your Odysseus account has been temporarily locked";
+			send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)
+		});
+
+	if let Err(e) = result {
+		error!("{:?}", e);
+	}
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct ResetPasswordEMailData {
+	pub username: String,
+	pub code: String,
+}
+
+/// Sends the password reset code to the user
+pub fn send_password_reset_email(user: &User) -> Result<(), AuthErrors> {
+	let username = user.display_name();
+	let reset_password_data = ResetPasswordEMailData {
+		username: username.clone(),
+		code: user.password_reset_code(),
+	};
+
+	let html_mail = HANDLEBARS.render(RESET_PASSWORD_TEMPLATE_NAME, &reset_password_data)?;
+	let email_title = "Reset your Odysseus password";
+
+	send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)
+}
@@ -1,20 +1,36 @@
 use crate::{
-	auth::{AuthErrors, LoginInput},
-	settings::{init_keyed_totp_long, EMAIL_VERIFIED_TEMPLATE_NAME, HANDLEBARS, SIGNUP_TEMPLATE_NAME},
-	user::{User, UserErrors, UserInfo},
+	audit::{AuditAction, AuditEvent},
+	auth::{
+		check_login_device, AuthErrors, EmailSentResponse, LoginInput, PasswordChangedResponse, SignupInvitationResponse,
+		UsernameAvailabilityResponse,
+	},
+	password::check_password_policy,
+	session::{renew_session, revoke_user_sessions, SharedSessionStore},
+	settings::{
+		init_keyed_totp_long, APP_SETTINGS, EMAIL_VERIFIED_TEMPLATE_NAME, HANDLEBARS, SIGNUP_EXISTING_TEMPLATE_NAME,
+		SIGNUP_TEMPLATE_NAME,
+	},
+	signup::{check_signup_email, SignupErrors, ValidInvitation},
+	throttle::{Throttle, ThrottleAction},
+	user::{check_username, email_key, normalize_username, User, UserErrors, UserInfo},
+	utils::{client_ip, hash_password},
 };
 
-// use actix_session::Session;
-
+use actix_session::Session;
+use actix_web::HttpRequest;
 use paperclip::actix::{
-	api_v2_operation, get, post,
-	web::{Data, Json},
+	api_v2_operation, get, post, put,
+	web::{Data, Json, Query},
 };
 use serde::{Deserialize, Serialize};
 use validator::Validate;
-use wither::mongodb::Database as MongoDatabase;
+use wither::{mongodb::Database as MongoDatabase, Model};
 
-use super::{send_email_to_user, NewUserInput, ValidateCode};
+use super::{
+	login_throttled, send_email_to_user, send_password_reset_email, ChangePasswordInput, ChangeUsernameInput,
+	ForgotPasswordInput, NewUserInput, ResetPasswordInput, SignupInvitationInput, UsernameAvailabilityQuery,
+	ValidateCode,
+};
 
 #[derive(Default, Debug, Clone, Serialize, Deserialize)]
 struct SignupEMailData {
@@ -24,26 +40,83 @@ struct SignupEMailData {
 
 /// LOCAL User signup
 ///
-/// Creates a new user but doesn't log in the user
+/// Creates a new user but doesn't log in the user, the signup policy may require an invitation.
+/// The response is the same whether the email is already taken or not,
+/// the owner of an existing account is notified by email instead
 #[api_v2_operation]
 #[post("/signup")]
 pub async fn signup(
+	req: HttpRequest,
 	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
 	Json(new_user_input): Json<NewUserInput>,
-) -> Result<Json<UserInfo>, AuthErrors> {
+) -> Result<Json<EmailSentResponse>, AuthErrors> {
+	let ip = client_ip(&req);
+	throttle.check(ThrottleAction::Signup, None, &ip).await?;
+	// Every signup counts against the IP
+	throttle.record_failure(ThrottleAction::Signup, None, &ip).await?;
+
 	match new_user_input.validate() {
 		Ok(_) => {
+			// Checked before looking for the email, so taken emails are not disclosed
+			let NewUserInput {
+				username,
+				password,
+				email,
+				invitation,
+			} = &new_user_input;
+			let invitation = match invitation {
+				Some(token) => {
+					let invitation = ValidInvitation::find_by_token(&db, token)
+						.await?
+						.ok_or(SignupErrors::InvalidInvitation)?;
+					if invitation.email() != email_key(email) {
+						return Err(SignupErrors::InvitationEmailMismatch.into());
+					}
+					Some(invitation)
+				}
+				None => None,
+			};
+			if let Some(username) = username {
+				check_username(username)?;
+			}
+			check_signup_email(email, invitation.is_some())?;
+			check_password_policy(password, &[email.as_str(), username.as_deref().unwrap_or_default()])?;
+
+			if let Some(existing_user) = User::find_by_email(&db, &new_user_input.email).await? {
+				// Spend the same time a real signup would
+				hash_password(&new_user_input.password).await?;
+
+				let username = existing_user.display_name();
+				let signup_existing_data = SignupExistingEMailData {
+					username: username.clone(),
+				};
+
+				let html_mail = HANDLEBARS.render(SIGNUP_EXISTING_TEMPLATE_NAME, &signup_existing_data)?;
+				let email_title = "Someone tried to sign up in Odysseus with your email";
+
+				send_email_to_user(&existing_user.email_scope.email, &username, email_title, &html_mail)?;
+
+				return Ok(Json(EmailSentResponse { email_sent: true }));
+			}
+
 			// Create a user
-			let user = User::create_user(&db, new_user_input).await?;
+			let mut user = User::create_user(&db, new_user_input).await?;
+			let invited = invitation.is_some();
+			if let Some(invitation) = invitation {
+				invitation.redeem(&db, &mut user).await?;
+			}
 
-			let username = user
-				.profile_scope
-				.preferred_username
-				.clone()
-				.unwrap_or_else(|| "Anonymous".to_string());
+			let username = user.display_name();
 
 			// Safe to unwrap
 			let user_id = user.id.clone().unwrap();
+			AuditEvent::new(AuditAction::Signup, &req)
+				.user(&user_id.to_hex())
+				.detail("method", "password")
+				.detail("invited", invited)
+				.record(&db)
+				.await;
 			let generator = init_keyed_totp_long(&user_id.to_hex());
 			let code = generator.generate();
 
@@ -57,27 +130,200 @@ pub async fn signup(
 
 			send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)?;
 
-			Ok(Json(user.into()))
+			Ok(Json(EmailSentResponse { email_sent: true }))
+		}
+		Err(e) => Err(AuthErrors::UserError(UserErrors::ValidationError(e))),
+	}
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct SignupExistingEMailData {
+	pub username: String,
+}
+
+/// LOCAL Signup invitation
+///
+/// Gets the signup form fields given by an invitation
+#[api_v2_operation]
+#[post("/signup-invitation")]
+pub async fn signup_invitation(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	Json(signup_invitation_input): Json<SignupInvitationInput>,
+) -> Result<Json<SignupInvitationResponse>, AuthErrors> {
+	let ip = client_ip(&req);
+	throttle.check(ThrottleAction::Signup, None, &ip).await?;
+
+	let invitation = match ValidInvitation::find_by_token(&db, &signup_invitation_input.invitation).await? {
+		Some(invitation) => invitation,
+		None => {
+			// Invalid tokens count against the IP, so the invitations cannot be guessed
+			throttle.record_failure(ThrottleAction::Signup, None, &ip).await?;
+			return Err(SignupErrors::InvalidInvitation.into());
+		}
+	};
+
+	Ok(Json(SignupInvitationResponse {
+		email: invitation.email().to_string(),
+	}))
+}
+
+/// LOCAL User forgot password
+///
+/// Sends a password reset code by email, the response is the same whether the account exists or not
+#[api_v2_operation]
+#[post("/forgot-password")]
+pub async fn forgot_password(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	Json(forgot_password_input): Json<ForgotPasswordInput>,
+) -> Result<Json<EmailSentResponse>, AuthErrors> {
+	match forgot_password_input.validate() {
+		Ok(_) => {
+			let ip = client_ip(&req);
+			throttle.check(ThrottleAction::PasswordReset, None, &ip).await?;
+			// Every request counts against the IP
+			throttle.record_failure(ThrottleAction::PasswordReset, None, &ip).await?;
+
+			if let Some(user) = User::find_by_email(&db, &forgot_password_input.email).await? {
+				send_password_reset_email(&user)?;
+			}
+
+			Ok(Json(EmailSentResponse { email_sent: true }))
+		}
+		Err(e) => Err(AuthErrors::UserError(UserErrors::ValidationError(e))),
+	}
+}
+
+/// LOCAL User reset password
+///
+/// Replaces the password using the code received by email, all the user sessions are revoked
+#[api_v2_operation]
+#[post("/reset-password")]
+pub async fn reset_password(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session_store: Data<SharedSessionStore>,
+	Json(reset_password_input): Json<ResetPasswordInput>,
+) -> Result<Json<PasswordChangedResponse>, AuthErrors> {
+	match reset_password_input.validate() {
+		Ok(_) => {
+			let ResetPasswordInput { email, code, password } = &reset_password_input;
+			let account = email_key(email);
+			let ip = client_ip(&req);
+
+			throttle.check(ThrottleAction::PasswordReset, Some(&account), &ip).await?;
+			check_password_policy(password, &[email.as_str()])?;
+
+			// Unknown users get the same answer as wrong codes
+			let result = match User::find_by_email(&db, email).await? {
+				Some(mut user) => user.reset_password(&db, code, password).await.map(|_| user),
+				None => {
+					hash_password(password).await?;
+					Err(UserErrors::InvalidCode)
+				}
+			};
+
+			let user = match result {
+				Ok(user) => user,
+				Err(e) => {
+					if let UserErrors::InvalidCode = e {
+						throttle
+							.record_failure(ThrottleAction::PasswordReset, Some(&account), &ip)
+							.await?;
+					}
+					return Err(e.into());
+				}
+			};
+			throttle.record_success(ThrottleAction::PasswordReset, &account, &ip).await?;
+
+			// Whoever knew the old password must lose access
+			let user_id = user.id.clone().unwrap().to_hex();
+			revoke_user_sessions(&session_store, &user_id).await?;
+			AuditEvent::new(AuditAction::PasswordReset, &req)
+				.user(&user_id)
+				.record(&db)
+				.await;
+
+			Ok(Json(PasswordChangedResponse { password_changed: true }))
 		}
 		Err(e) => Err(AuthErrors::UserError(UserErrors::ValidationError(e))),
 	}
 }
 
+/// LOCAL User change password
+///
+/// Replaces the password of the logged in user, all the other user sessions are revoked
+#[api_v2_operation]
+#[post("/change-password")]
+pub async fn change_password(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session_store: Data<SharedSessionStore>,
+	session: Session,
+	Json(change_password_input): Json<ChangePasswordInput>,
+) -> Result<Json<PasswordChangedResponse>, AuthErrors> {
+	let ChangePasswordInput {
+		current_password,
+		new_password,
+	} = &change_password_input;
+
+	// Get user from session
+	let mut user = User::user_from_session(&db, &session).await?;
+	let account = email_key(&user.email_scope.email);
+	let ip = client_ip(&req);
+
+	throttle.check(ThrottleAction::Login, Some(&account), &ip).await?;
+	check_password_policy(
+		new_password,
+		&[
+			user.email_scope.email.as_str(),
+			user.profile_scope.preferred_username.as_deref().unwrap_or_default(),
+		],
+	)?;
+
+	if let Err(e) = user.change_password(&db, current_password, new_password).await {
+		if let UserErrors::InvalidCredentials = e {
+			throttle.record_failure(ThrottleAction::Login, Some(&account), &ip).await?;
+		}
+		return Err(e.into());
+	}
+	throttle.record_success(ThrottleAction::Login, &account, &ip).await?;
+
+	// Revoke the other sessions, the current one survives with a renewed key
+	let user_id = user.id.clone().unwrap().to_hex();
+	revoke_user_sessions(&session_store, &user_id).await?;
+	renew_session(&session);
+	AuditEvent::new(AuditAction::PasswordChanged, &req)
+		.user(&user_id)
+		.record(&db)
+		.await;
+
+	Ok(Json(PasswordChangedResponse { password_changed: true }))
+}
+
 /// LOCAL User login
 ///
 /// Logs in the user into Odysseus
 #[api_v2_operation]
 #[post("/login")]
 pub async fn local_login(
+	req: HttpRequest,
 	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
 	Json(login_input): Json<LoginInput>,
 	session: Session,
 ) -> Result<Json<UserInfo>, AuthErrors> {
 	// Destructure login
-	let LoginInput { email, password } = &login_input;
+	let LoginInput { login, password } = &login_input;
 
 	// Login the user, will also persist the session
-	let user = User::login_with_session(&db, &session, email, password).await?;
+	let user = login_throttled(&db, &session, &throttle, login, password, &req, None).await?;
+	check_login_device(&db, &user, &req).await;
 
 	Ok(Json(user.into()))
 }
@@ -93,7 +339,9 @@ struct ValidatedEMailData {
 #[api_v2_operation]
 #[post("/validate-email")]
 pub async fn validate_email(
+	req: HttpRequest,
 	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
 	session: Session,
 	code_input: Json<ValidateCode>,
 ) -> Result<Json<UserInfo>, AuthErrors> {
@@ -101,8 +349,26 @@ pub async fn validate_email(
 		Ok(_) => {
 			// Get user from session
 			let mut user = User::user_from_session(&db, &session).await?;
+			// Safe to unwrap since the user exists
+			let account = user.id.clone().unwrap().to_hex();
+			let ip = client_ip(&req);
+
 			// Validate email
-			user.validate_email(&db, &code_input.code).await?;
+			throttle.check(ThrottleAction::ValidateCode, Some(&account), &ip).await?;
+			if let Err(e) = user.validate_email(&db, &code_input.code).await {
+				if let UserErrors::InvalidCode = e {
+					throttle
+						.record_failure(ThrottleAction::ValidateCode, Some(&account), &ip)
+						.await?;
+				}
+				return Err(e.into());
+			}
+			throttle.record_success(ThrottleAction::ValidateCode, &account, &ip).await?;
+			AuditEvent::new(AuditAction::EmailVerified, &req)
+				.user(&account)
+				.detail("email", &user.email_scope.email)
+				.record(&db)
+				.await;
 
 			let username = user
 				.profile_scope
@@ -135,3 +401,67 @@ pub async fn user_info(db: Data<MongoDatabase>, session: Session) -> Result<Json
 	let user = User::user_from_session(&db, &session).await?;
 	Ok(Json(user.into()))
 }
+
+/// LOCAL Username availability
+///
+/// Checks if a username can be taken, the response gives its normalized form
+#[api_v2_operation]
+#[get("/username-availability")]
+pub async fn username_availability(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	Query(username_query): Query<UsernameAvailabilityQuery>,
+) -> Result<Json<UsernameAvailabilityResponse>, AuthErrors> {
+	let ip = client_ip(&req);
+	// Every lookup counts against the IP, so the usernames cannot be enumerated
+	throttle
+		.rate_limit(ThrottleAction::UsernameLookup, &ip, APP_SETTINGS.throttle.lookups)
+		.await?;
+
+	let response = match check_username(&username_query.username) {
+		Ok(username) => {
+			let available = User::find_by_username(&db, &username).await?.is_none();
+			UsernameAvailabilityResponse {
+				username,
+				available,
+				reason: (!available).then(|| UserErrors::UsernameTaken.to_string()),
+			}
+		}
+		Err(e) => UsernameAvailabilityResponse {
+			username: normalize_username(&username_query.username),
+			available: false,
+			reason: Some(e.to_string()),
+		},
+	};
+
+	Ok(Json(response))
+}
+
+/// LOCAL Change username
+///
+/// Changes the username of the logged in user, it can be changed again only after the cooldown
+#[api_v2_operation]
+#[put("/username")]
+pub async fn change_username(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	session: Session,
+	Json(change_username_input): Json<ChangeUsernameInput>,
+) -> Result<Json<UserInfo>, AuthErrors> {
+	let mut user = User::user_from_session(&db, &session).await?;
+	let previous = user.username.clone().unwrap_or_default();
+
+	user.set_username(&db, &change_username_input.username, true).await?;
+	user.save(&db, None).await?;
+
+	// Safe to unwrap since the user exists
+	AuditEvent::new(AuditAction::UsernameChanged, &req)
+		.user(&user.id.clone().unwrap().to_hex())
+		.detail("previous", previous)
+		.detail("username", user.username.clone().unwrap_or_default())
+		.record(&db)
+		.await;
+
+	Ok(Json(user.into()))
+}
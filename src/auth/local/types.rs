@@ -5,13 +5,15 @@ use validator::Validate;
 /// New user input data
 #[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
 pub struct NewUserInput {
-	/// The new user username.
+	/// The new user username, must be unique.
 	pub username: Option<String>,
 	/// The new user password.
 	pub password: String,
 	/// User email, must be unique.
 	#[validate(email)]
 	pub email: String,
+	/// The invitation code received by email, required when the signup is invite-only.
+	pub invitation: Option<String>,
 }
 
 /// Code validation input
@@ -20,3 +22,54 @@ pub struct ValidateCode {
 	/// The TOTP code.
 	pub code: String,
 }
+
+/// Forgot password input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct ForgotPasswordInput {
+	/// The account email.
+	#[validate(email)]
+	pub email: String,
+}
+
+/// Reset password input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+pub struct ResetPasswordInput {
+	/// The account email.
+	#[validate(email)]
+	pub email: String,
+	/// The code received by email.
+	pub code: String,
+	/// The new password.
+	pub password: String,
+}
+
+/// Change password input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordInput {
+	/// The current password.
+	pub current_password: String,
+	/// The new password.
+	pub new_password: String,
+}
+
+/// Signup invitation lookup input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct SignupInvitationInput {
+	/// The invitation code received by email.
+	pub invitation: String,
+}
+
+/// Username availability query
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct UsernameAvailabilityQuery {
+	/// The wanted username.
+	pub username: String,
+}
+
+/// Change username input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct ChangeUsernameInput {
+	/// The new username.
+	pub username: String,
+}
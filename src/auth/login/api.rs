@@ -1,22 +1,38 @@
 use log::{error, info};
 use ory_hydra_client::{
 	apis::admin_api,
-	models::{AcceptLoginRequest, CompletedRequest},
+	models::{AcceptLoginRequest, CompletedRequest, LoginRequest, RejectRequest},
 };
+use wither::{bson::oid::ObjectId, mongodb::Database as MongoDatabase};
 
-use crate::settings::ORY_HYDRA_CONFIGURATION;
+use crate::{auth::Metadata, organization::is_member_of_any, settings::ORY_HYDRA_CONFIGURATION, user::User};
 
 use super::LoginErrors;
 
+/// Fetches the Hydra login request of the challenge
+pub async fn fetch_login_request(login_challenge: &str) -> Result<LoginRequest, LoginErrors> {
+	admin_api::get_login_request(&ORY_HYDRA_CONFIGURATION, login_challenge)
+		.await
+		.map_err(|e| {
+			error!("{:?}", e);
+			LoginErrors::HydraError
+		})
+}
+
+/// Accepts the login request, `amr` lists the authentication methods used when they are not the password
 pub async fn handle_accept_login_request(
 	subject: &str,
 	login_challenge: &str,
+	amr: &[&str],
 ) -> Result<CompletedRequest, LoginErrors> {
 	info!("Accepting login request");
 
 	let mut body = AcceptLoginRequest::new(subject.to_string());
 	body.remember = Some(true);
 	body.remember_for = Some(0);
+	if !amr.is_empty() {
+		body.amr = Some(amr.iter().map(ToString::to_string).collect());
+	}
 
 	let accept_login_request = admin_api::accept_login_request(&ORY_HYDRA_CONFIGURATION, login_challenge, Some(body))
 		.await
@@ -27,3 +43,64 @@ pub async fn handle_accept_login_request(
 
 	Ok(accept_login_request)
 }
+
+pub async fn handle_reject_login_request(
+	login_challenge: &str,
+	error_description: &str,
+) -> Result<CompletedRequest, LoginErrors> {
+	info!("Rejecting login request");
+
+	let mut body = RejectRequest::new();
+	body.error = Some("access_denied".to_string());
+	body.error_description = Some(error_description.to_string());
+
+	let reject_login_request = admin_api::reject_login_request(&ORY_HYDRA_CONFIGURATION, login_challenge, Some(body))
+		.await
+		.map_err(|e| {
+			error!("{:?}", e);
+			LoginErrors::HydraError
+		})?;
+
+	Ok(reject_login_request)
+}
+
+/// Checks if the user can log in through the client, the client metadata can restrict the login to some organizations
+pub async fn client_allows_user(
+	db: &MongoDatabase,
+	login_request: &LoginRequest,
+	user_id: &ObjectId,
+) -> Result<bool, LoginErrors> {
+	let metadata = Metadata::from_client(Some(login_request.client.as_ref()));
+	if metadata.organizations.is_empty() {
+		return Ok(true);
+	}
+
+	Ok(is_member_of_any(db, user_id, &metadata.organizations).await?)
+}
+
+/// Accepts the login request, or rejects it when the account is not active or the client does not allow the subject
+pub async fn complete_login_request(
+	db: &MongoDatabase,
+	login_request: &LoginRequest,
+	subject: &str,
+	amr: &[&str],
+) -> Result<CompletedRequest, LoginErrors> {
+	// Skipped logins reach this point without a password check, the account state must be checked here too
+	let user_id = ObjectId::parse_str(subject)?;
+	let active = User::find_by_id(db, &user_id)
+		.await?
+		.map_or(false, |user| user.is_active());
+	if !active {
+		return handle_reject_login_request(&login_request.challenge, "The account is not active").await;
+	}
+
+	if client_allows_user(db, login_request, &user_id).await? {
+		handle_accept_login_request(subject, &login_request.challenge, amr).await
+	} else {
+		handle_reject_login_request(
+			&login_request.challenge,
+			"The user is not a member of the organizations allowed by this client",
+		)
+		.await
+	}
+}
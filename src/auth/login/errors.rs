@@ -3,9 +3,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Error as JSONError;
 use thiserror::Error;
 use url::ParseError;
-use wither::WitherError;
+use wither::{bson::oid::Error as ObjectIdError, WitherError};
 
-use crate::{user::UserErrors, utils::PasswordErrors};
+use crate::{auth::AuthErrors, user::UserErrors, utils::PasswordErrors};
 
 #[derive(Debug, Deserialize, Serialize)]
 struct ErrorResponse {
@@ -28,12 +28,19 @@ pub enum LoginErrors {
 	InvalidUrl(#[from] ParseError),
 	#[error("Internal server error: {0}")]
 	JSONParseError(#[from] JSONError),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+	#[error("Invalid OID: {0}")]
+	ObjectIdError(#[from] ObjectIdError),
 	// #[error("missing required parameters")]
 	// MissingRequiredParameters,
 }
 
 impl ResponseError for LoginErrors {
 	fn error_response(&self) -> HttpResponse {
+		if let Self::AuthError(e) = self {
+			return e.error_response();
+		}
 		let error_response = ErrorResponse {
 			error: self.to_string(),
 		};
@@ -45,6 +52,7 @@ impl ResponseError for LoginErrors {
 			Self::UserCreationError(UserErrors::DatabaseError(_)) => StatusCode::BAD_REQUEST,
 			// Self::MissingRequiredParameters => StatusCode::BAD_REQUEST,
 			Self::PasswordError(_) => StatusCode::BAD_REQUEST,
+			Self::AuthError(e) => e.status_code(),
 			_ => StatusCode::INTERNAL_SERVER_ERROR,
 		}
 	}
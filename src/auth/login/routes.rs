@@ -1,13 +1,11 @@
 use crate::{
-	auth::{handle_accept_login_request, AcceptedRequest},
+	auth::{check_login_device, complete_login_request, fetch_login_request, login_throttled, AcceptedRequest},
 	settings::APP_SETTINGS,
-	settings::ORY_HYDRA_CONFIGURATION,
-	user::User,
+	throttle::Throttle,
 };
 
 use actix_session::Session;
-use log::error;
-use ory_hydra_client::apis::admin_api;
+use actix_web::HttpRequest;
 use paperclip::actix::{
 	api_v2_operation, get, post,
 	web::{Data, HttpResponse, Json, Query},
@@ -22,16 +20,14 @@ use super::{LoginErrors, LoginInput, OAuthLoginRequest};
 /// Starts the OAuth login flow, responds with a redirect
 #[api_v2_operation]
 #[get("/login")]
-pub async fn get_login(oauth_request: Query<OAuthLoginRequest>) -> Result<HttpResponse, LoginErrors> {
+pub async fn get_login(
+	oauth_request: Query<OAuthLoginRequest>,
+	db: Data<MongoDatabase>,
+) -> Result<HttpResponse, LoginErrors> {
 	// let login_challenge = oauth_request.login_challenge;
 
 	// Get login request
-	let ask_login_request = admin_api::get_login_request(&ORY_HYDRA_CONFIGURATION, &oauth_request.login_challenge)
-		.await
-		.map_err(|e| {
-			error!("{:?}", e);
-			LoginErrors::HydraError
-		})?;
+	let ask_login_request = fetch_login_request(&oauth_request.login_challenge).await?;
 
 	let mut redirect_to;
 	// User is already authenticated
@@ -43,11 +39,11 @@ pub async fn get_login(oauth_request: Query<OAuthLoginRequest>) -> Result<HttpRe
 		// Set login challenge
 		redirect_to.set_query(Some(&format!("login_challenge={}", ask_login_request.challenge)));
 	} else {
-		// Accept login request
-		let accept_login_request =
-			handle_accept_login_request(&ask_login_request.subject, &oauth_request.login_challenge).await?;
+		// Accept login request, unless the client does not allow the user
+		let completed_login_request =
+			complete_login_request(&db, &ask_login_request, &ask_login_request.subject, &[]).await?;
 		// Set redirect
-		redirect_to = Url::parse(&accept_login_request.redirect_to)?;
+		redirect_to = Url::parse(&completed_login_request.redirect_to)?;
 	}
 
 	// info!("{:?}", &redirect_to);
@@ -65,25 +61,40 @@ pub async fn get_login(oauth_request: Query<OAuthLoginRequest>) -> Result<HttpRe
 #[api_v2_operation]
 #[post("/login")]
 pub async fn post_login(
+	req: HttpRequest,
 	Json(login_input): Json<LoginInput>,
 	login_request: Query<OAuthLoginRequest>,
 	session: Session,
 	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
 ) -> Result<Json<AcceptedRequest>, LoginErrors> {
 	// Destructure login
-	let LoginInput { email, password } = &login_input;
+	let LoginInput { login, password } = &login_input;
+
+	// Fetched first, the audit log records the client of the login
+	let ask_login_request = fetch_login_request(&login_request.login_challenge).await?;
 
 	// Try to login user
-	let user = User::login_with_session(&db, &session, email, password).await?;
+	let user = login_throttled(
+		&db,
+		&session,
+		&throttle,
+		login,
+		password,
+		&req,
+		Some(ask_login_request.client.as_ref()),
+	)
+	.await?;
+	check_login_device(&db, &user, &req).await;
 
 	// Safe to unwrap since the user exists
 	let subject = user.id.clone().unwrap().to_string();
 	// TODO: add support for 2fa
 
-	// Accept login request
-	let accept_login_request = handle_accept_login_request(&subject, &login_request.login_challenge)
+	// Accept login request, unless the client does not allow the user
+	let completed_login_request = complete_login_request(&db, &ask_login_request, &subject, &[])
 		.await?
 		.into();
 
-	Ok(Json(accept_login_request))
+	Ok(Json(completed_login_request))
 }
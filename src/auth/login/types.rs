@@ -5,8 +5,9 @@ use serde::{Deserialize, Serialize};
 #[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
 #[serde(rename_all = "camelCase")]
 pub struct LoginInput {
-	/// The new user username, must be unique.
-	pub email: String,
+	/// The user email or username.
+	#[serde(alias = "email")]
+	pub login: String,
 	/// The new user password.
 	pub password: String,
 }
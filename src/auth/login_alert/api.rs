@@ -0,0 +1,186 @@
+use std::net::{IpAddr, SocketAddr};
+
+use actix_web::HttpRequest;
+use log::{error, info};
+use maxminddb::{geoip2, Reader};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use url::Url;
+use wither::{
+	bson::{doc, DateTime},
+	mongodb::{options::FindOneAndUpdateOptions, Database as MongoDatabase},
+	Model,
+};
+
+use crate::{
+	auth::{send_email_to_user, AuthErrors},
+	session::unix_now,
+	settings::{APP_SETTINGS, HANDLEBARS, LOGIN_ALERT_TEMPLATE_NAME},
+	user::User,
+	utils::{client_ip, generate_token, hash_token, user_agent},
+};
+
+use super::{KnownDevice, LoginAlert, LoginAlertErrors};
+
+/// The GeoIP database, opened once
+static GEOIP: Lazy<Option<Reader<Vec<u8>>>> = Lazy::new(|| {
+	let path = APP_SETTINGS.loginalert.geoip.as_ref()?;
+	Reader::open_readfile(path)
+		.map_err(|e| error!("Could not open the GeoIP database: {:?}", e))
+		.ok()
+});
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct LoginAlertEMailData {
+	pub username: String,
+	pub reason: String,
+	pub time: String,
+	pub location: String,
+	pub ip: String,
+	pub device: String,
+	pub link: String,
+	pub hours: i64,
+}
+
+/// Parses the client address, which may carry a port when it is the peer address
+fn parse_ip(ip: &str) -> Option<IpAddr> {
+	ip.parse()
+		.ok()
+		.or_else(|| ip.parse::<SocketAddr>().ok().map(|address| address.ip()))
+}
+
+/// The IPv4 /24 or IPv6 /48 network of the address, so that address changes within a network go unnoticed
+fn network_of(ip: &str) -> String {
+	match parse_ip(ip) {
+		Some(IpAddr::V4(ip)) => {
+			let [a, b, c, _] = ip.octets();
+			format!("{}.{}.{}.0/24", a, b, c)
+		}
+		Some(IpAddr::V6(ip)) => {
+			let segments = ip.segments();
+			format!("{:x}:{:x}:{:x}::/48", segments[0], segments[1], segments[2])
+		}
+		None => ip.to_string(),
+	}
+}
+
+/// The approximate location of the address, from the GeoIP database
+fn locate(ip: &str) -> String {
+	let unknown = "Unknown location".to_string();
+	let (reader, ip) = match (GEOIP.as_ref(), parse_ip(ip)) {
+		(Some(reader), Some(ip)) => (reader, ip),
+		_ => return unknown,
+	};
+	let city: geoip2::City = match reader.lookup(ip) {
+		Ok(city) => city,
+		Err(_) => return unknown,
+	};
+
+	let city_name = city
+		.city
+		.and_then(|city| city.names)
+		.and_then(|names| names.get("en").map(|name| name.to_string()));
+	let country_name = city
+		.country
+		.and_then(|country| country.names)
+		.and_then(|names| names.get("en").map(|name| name.to_string()));
+	match (city_name, country_name) {
+		(Some(city), Some(country)) => format!("{}, {}", city, country),
+		(None, Some(country)) => country,
+		(Some(city), None) => city,
+		(None, None) => unknown,
+	}
+}
+
+/// Remembers the device of the login, and emails the user when it comes from a new device or network.
+///
+/// Never fails the login, the errors are only logged
+pub async fn check_login_device(db: &MongoDatabase, user: &User, req: &HttpRequest) {
+	if !APP_SETTINGS.loginalert.enabled {
+		return;
+	}
+	if let Err(e) = record_login_device(db, user, req).await {
+		error!("Could not check the login device: {:?}", e);
+	}
+}
+
+async fn record_login_device(db: &MongoDatabase, user: &User, req: &HttpRequest) -> Result<(), LoginAlertErrors> {
+	// Safe to unwrap, the user exists
+	let user_id = user.id.clone().unwrap();
+	let ip = client_ip(req);
+	let device = user_agent(req);
+	let agent = hash_token(&device);
+	let network = network_of(&ip);
+
+	let first_login = KnownDevice::find_one(db, doc! { "user": user_id }, None)
+		.await?
+		.is_none();
+	let new_agent = KnownDevice::find_one(db, doc! { "user": user_id, "agent": &agent }, None)
+		.await?
+		.is_none();
+	let new_network = KnownDevice::find_one(db, doc! { "user": user_id, "network": &network }, None)
+		.await?
+		.is_none();
+
+	let now = unix_now();
+	let options = FindOneAndUpdateOptions::builder().upsert(true).build();
+	KnownDevice::find_one_and_update(
+		db,
+		doc! { "user": user_id, "agent": &agent, "network": &network },
+		doc! { "$set": { "last_seen": now } },
+		Some(options),
+	)
+	.await?;
+
+	// The first login of the account has nothing to compare with
+	if first_login || !(new_agent || new_network) {
+		return Ok(());
+	}
+
+	info!("Login from a new device or network, alerting the user");
+	LoginAlert::delete_many(db, doc! { "expires_at": { "$lte": now } }, None).await?;
+
+	let token = generate_token();
+	let mut login_alert = LoginAlert {
+		id: None,
+		token: hash_token(&token),
+		user: user_id,
+		agent,
+		network,
+		expires_at: now + APP_SETTINGS.loginalert.lifetime,
+	};
+	login_alert.save(db, None).await?;
+
+	let mut link = Url::parse(&APP_SETTINGS.server.clienturi).map_err(AuthErrors::from)?;
+	link = link.join("not-me").map_err(AuthErrors::from)?;
+	link.set_query(Some(&format!("token={}", token)));
+
+	let reason = match (new_agent, new_network) {
+		(true, true) => "a new device and network",
+		(true, false) => "a new device",
+		_ => "a new network",
+	};
+	let username = user.display_name();
+	let login_alert_data = LoginAlertEMailData {
+		username: username.clone(),
+		reason: reason.to_string(),
+		time: DateTime::from_millis(now * 1000).to_rfc3339_string(),
+		location: locate(&ip),
+		ip,
+		device: if device.is_empty() {
+			"Unknown device".to_string()
+		} else {
+			device
+		},
+		link: link.to_string(),
+		hours: APP_SETTINGS.loginalert.lifetime / 3600,
+	};
+	let html_mail = HANDLEBARS
+		.render(LOGIN_ALERT_TEMPLATE_NAME, &login_alert_data)
+		.map_err(AuthErrors::from)?;
+	let email_title = "New login to your Odysseus account";
+
+	send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)?;
+
+	Ok(())
+}
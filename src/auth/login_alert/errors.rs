@@ -0,0 +1,59 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use paperclip::actix::api_v2_errors;
+use serde::Serialize;
+use thiserror::Error;
+use wither::{mongodb::error::Error as MongoError, WitherError};
+
+use crate::{
+	auth::{AuthErrors, LogoutErrors},
+	session::SessionStoreErrors,
+};
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "Wrong input, or the link is invalid or expired",
+	code = 429,
+	description = "Too many attempts, retry after the seconds in the Retry-After header",
+	code = 500,
+	description = "Internal server error, could be a db connection error, email server error, Hydra error"
+)]
+#[derive(Error, Debug)]
+pub enum LoginAlertErrors {
+	#[error("Invalid or expired link")]
+	InvalidLink,
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("Internal server error")]
+	MongoError(#[from] MongoError),
+	#[error("Internal server error")]
+	SessionStoreError(#[from] SessionStoreErrors),
+	#[error("Internal server error")]
+	LogoutError(#[from] LogoutErrors),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+}
+
+impl ResponseError for LoginAlertErrors {
+	fn error_response(&self) -> HttpResponse {
+		if let Self::AuthError(e) = self {
+			return e.error_response();
+		}
+		let error_response = ErrorResponse {
+			error: self.to_string(),
+		};
+		HttpResponse::build(self.status_code()).json(error_response)
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::InvalidLink => StatusCode::BAD_REQUEST,
+			Self::AuthError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
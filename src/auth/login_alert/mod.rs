@@ -0,0 +1,11 @@
+pub mod api;
+pub mod errors;
+pub mod model;
+pub mod routes;
+pub mod types;
+
+pub use api::*;
+pub use errors::*;
+pub use model::*;
+pub use routes::*;
+pub use types::*;
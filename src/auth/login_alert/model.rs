@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	prelude::*,
+	WitherError,
+};
+
+use crate::{session::unix_now, utils::hash_token};
+
+/// A device and network a user already logged in from
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(
+	keys = r#"doc!{"user": 1, "agent": 1, "network": 1}"#,
+	options = r#"doc!{"unique": true}"#
+))]
+pub struct KnownDevice {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	pub user: ObjectId,
+	/// SHA-256 of the user agent
+	pub agent: String,
+	/// The IPv4 /24 or IPv6 /48 network of the client
+	pub network: String,
+	pub last_seen: i64,
+}
+
+/// A sent new login notification, its link forgets the device and secures the account
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"token": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct LoginAlert {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// SHA-256 of the token sent by email, the token itself is never stored
+	pub token: String,
+	pub user: ObjectId,
+	pub agent: String,
+	pub network: String,
+	pub expires_at: i64,
+}
+
+impl LoginAlert {
+	/// Takes the unexpired alert of the token, so each link works once
+	pub async fn take_valid(db: &Database, token: &str) -> Result<Option<Self>, WitherError> {
+		let filter = doc! {
+			"token": hash_token(token),
+			"expires_at": { "$gt": unix_now() },
+		};
+		LoginAlert::find_one_and_delete(db, filter, None).await
+	}
+}
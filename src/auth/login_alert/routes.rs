@@ -0,0 +1,72 @@
+use actix_web::HttpRequest;
+use paperclip::actix::{
+	api_v2_operation, post,
+	web::{Data, Json},
+};
+use wither::{bson::doc, mongodb::Database as MongoDatabase, Model};
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::{revoke_hydra_sessions, send_password_reset_email, AuthErrors, EmailSentResponse},
+	session::{revoke_user_sessions, SharedSessionStore},
+	throttle::{Throttle, ThrottleAction},
+	user::User,
+	utils::client_ip,
+};
+
+use super::{KnownDevice, LoginAlert, LoginAlertErrors, NotMeInput};
+
+/// LOCAL This wasn't me
+///
+/// Answers a new login notification: forgets the device of the login, logs the account out everywhere and emails a
+/// password reset code. Each link works once
+#[api_v2_operation]
+#[post("/not-me")]
+pub async fn not_me(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session_store: Data<SharedSessionStore>,
+	Json(not_me_input): Json<NotMeInput>,
+) -> Result<Json<EmailSentResponse>, LoginAlertErrors> {
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::ValidateCode, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+	// Every request counts against the IP
+	throttle
+		.record_failure(ThrottleAction::ValidateCode, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	let login_alert = LoginAlert::take_valid(&db, &not_me_input.token)
+		.await?
+		.ok_or(LoginAlertErrors::InvalidLink)?;
+
+	KnownDevice::collection(&db)
+		.delete_one(
+			doc! {
+				"user": login_alert.user,
+				"agent": &login_alert.agent,
+				"network": &login_alert.network,
+			},
+			None,
+		)
+		.await?;
+
+	let user_id = login_alert.user.to_hex();
+	revoke_user_sessions(&session_store, &user_id).await?;
+	revoke_hydra_sessions(&user_id).await?;
+	AuditEvent::new(AuditAction::SessionsRevoked, &req)
+		.user(&user_id)
+		.detail("reason", "login reported by the user")
+		.record(&db)
+		.await;
+
+	if let Some(user) = User::find_by_id(&db, &login_alert.user).await? {
+		send_password_reset_email(&user)?;
+	}
+
+	Ok(Json(EmailSentResponse { email_sent: true }))
+}
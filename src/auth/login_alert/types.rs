@@ -0,0 +1,9 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+/// "This wasn't me" input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct NotMeInput {
+	/// The token of the link received by email.
+	pub token: String,
+}
@@ -0,0 +1,66 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use paperclip::actix::api_v2_errors;
+use serde::Serialize;
+use thiserror::Error;
+use wither::{mongodb::error::Error as MongoError, WitherError};
+
+use crate::auth::{AuthErrors, LoginErrors};
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "Wrong input, wrong code, or no pending login in this browser",
+	code = 403,
+	description = "The account is not active",
+	code = 429,
+	description = "Too many attempts, retry after the seconds in the Retry-After header",
+	code = 500,
+	description = "Internal server error, could be a db connection error, email server error, Hydra error"
+)]
+#[derive(Error, Debug)]
+pub enum LoginCodeErrors {
+	#[error("Invalid code")]
+	InvalidCode,
+	#[error("Too many wrong codes, ask for a new code")]
+	TooManyFailures,
+	#[error("No pending login, or the code expired")]
+	NoPendingLogin,
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("Internal server error")]
+	MongoError(#[from] MongoError),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+	#[error("{0}")]
+	LoginError(#[from] LoginErrors),
+}
+
+impl ResponseError for LoginCodeErrors {
+	fn error_response(&self) -> HttpResponse {
+		match self {
+			Self::AuthError(e) => e.error_response(),
+			Self::LoginError(e) => e.error_response(),
+			_ => {
+				let error_response = ErrorResponse {
+					error: self.to_string(),
+				};
+				HttpResponse::build(self.status_code()).json(error_response)
+			}
+		}
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::InvalidCode => StatusCode::BAD_REQUEST,
+			Self::TooManyFailures => StatusCode::BAD_REQUEST,
+			Self::NoPendingLogin => StatusCode::BAD_REQUEST,
+			Self::AuthError(e) => e.status_code(),
+			Self::LoginError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
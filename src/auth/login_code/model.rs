@@ -0,0 +1,60 @@
+use rand::{rngs::OsRng, Rng};
+use serde::{Deserialize, Serialize};
+use subtle::ConstantTimeEq;
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	prelude::*,
+	WitherError,
+};
+
+use crate::{session::unix_now, settings::APP_SETTINGS, utils::hash_token};
+
+/// The session state key holding the token of the pending login attempt
+pub const LOGIN_CODE_ATTEMPT_KEY: &str = "login_code_attempt";
+
+/// A pending login by a code sent by email, deleted once used or after too many wrong codes
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"attempt": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct LoginCode {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// SHA-256 of the attempt token kept in the session of the browser that started the login
+	pub attempt: String,
+	pub user: ObjectId,
+	/// SHA-256 of the attempt token and the code, the code alone is too short to be hashed on its own
+	pub code: String,
+	/// Wrong codes entered so far
+	pub failures: i64,
+	/// The Hydra login request to accept once logged in
+	pub login_challenge: Option<String>,
+	pub expires_at: i64,
+}
+
+impl LoginCode {
+	/// Generates a code of the configured number of digits
+	pub fn generate_code() -> String {
+		let digits = APP_SETTINGS.logincode.digits();
+		let code = OsRng.gen_range(0..10_u32.pow(digits));
+		format!("{:0width$}", code, width = digits as usize)
+	}
+
+	pub fn hash_code(attempt: &str, code: &str) -> String {
+		hash_token(&format!("{}:{}", attempt, code))
+	}
+
+	/// Finds the unexpired login of the attempt token
+	pub async fn find_pending(db: &Database, attempt: &str) -> Result<Option<Self>, WitherError> {
+		let filter = doc! {
+			"attempt": hash_token(attempt),
+			"expires_at": { "$gt": unix_now() },
+		};
+		LoginCode::find_one(db, filter, None).await
+	}
+
+	/// Checks the code in constant time
+	pub fn matches(&self, attempt: &str, code: &str) -> bool {
+		let hash = Self::hash_code(attempt, code.trim());
+		hash.as_bytes().ct_eq(self.code.as_bytes()).into()
+	}
+}
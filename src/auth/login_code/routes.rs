@@ -0,0 +1,239 @@
+use actix_session::Session;
+use actix_web::HttpRequest;
+use paperclip::actix::{
+	api_v2_operation, post,
+	web::{Data, Json},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::{bson::doc, mongodb::Database as MongoDatabase, Model};
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::{
+		check_login_device, complete_login_request, fetch_login_request, send_email_to_user, AuthErrors, EmailSentResponse,
+		PasswordlessLoginResponse,
+	},
+	session::{start_session, unix_now},
+	settings::{APP_SETTINGS, HANDLEBARS, LOGIN_CODE_TEMPLATE_NAME},
+	throttle::{Throttle, ThrottleAction},
+	user::{User, UserErrors},
+	utils::{client_ip, generate_token, hash_token},
+};
+
+use super::{LoginCode, LoginCodeErrors, LoginCodeInput, LoginCodeLoginInput, LOGIN_CODE_ATTEMPT_KEY};
+
+/// Authentication methods reported to Hydra for the logins by code
+const LOGIN_CODE_AMR: &[&str] = &["otp", "email"];
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct LoginCodeEMailData {
+	pub username: String,
+	pub code: String,
+	pub minutes: i64,
+}
+
+/// Creates the pending login, replacing the previous ones of the user, and sends the code by email
+async fn send_login_code(
+	db: &MongoDatabase,
+	user: &User,
+	attempt: &str,
+	login_challenge: Option<String>,
+) -> Result<(), LoginCodeErrors> {
+	// Safe to unwrap, the user exists
+	let user_id = user.id.clone().unwrap();
+	let now = unix_now();
+	LoginCode::delete_many(
+		db,
+		doc! { "$or": [{ "user": user_id }, { "expires_at": { "$lte": now } }] },
+		None,
+	)
+	.await?;
+
+	let code = LoginCode::generate_code();
+	let mut login_code = LoginCode {
+		id: None,
+		attempt: hash_token(attempt),
+		user: user_id,
+		code: LoginCode::hash_code(attempt, &code),
+		failures: 0,
+		login_challenge,
+		expires_at: now + APP_SETTINGS.logincode.lifetime,
+	};
+	login_code.save(db, None).await?;
+
+	let username = user.display_name();
+	let login_code_data = LoginCodeEMailData {
+		username: username.clone(),
+		code,
+		minutes: APP_SETTINGS.logincode.lifetime / 60,
+	};
+	let html_mail = HANDLEBARS
+		.render(LOGIN_CODE_TEMPLATE_NAME, &login_code_data)
+		.map_err(AuthErrors::from)?;
+	let email_title = "Your Odysseus login code";
+
+	send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)?;
+
+	Ok(())
+}
+
+/// LOCAL Request login code
+///
+/// Emails a single-use login code, the response is the same whether the account exists or not.
+/// The code can be entered only in the browser that asked for it
+#[api_v2_operation]
+#[post("/login-code")]
+pub async fn request_login_code(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session: Session,
+	Json(login_code_input): Json<LoginCodeInput>,
+) -> Result<Json<EmailSentResponse>, LoginCodeErrors> {
+	login_code_input
+		.validate()
+		.map_err(|e| AuthErrors::from(UserErrors::ValidationError(e)))?;
+
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::LoginCode, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+	// Every request counts against the IP
+	throttle
+		.record_failure(ThrottleAction::LoginCode, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	// Started before looking for the user, so unknown emails are not disclosed
+	let attempt = generate_token();
+	session
+		.insert(LOGIN_CODE_ATTEMPT_KEY, &attempt)
+		.map_err(AuthErrors::from)?;
+
+	let LoginCodeInput { email, login_challenge } = login_code_input;
+	if let Some(user) = User::find_by_email(&db, &email).await? {
+		if user.is_active() {
+			send_login_code(&db, &user, &attempt, login_challenge).await?;
+		}
+	}
+
+	Ok(Json(EmailSentResponse { email_sent: true }))
+}
+
+/// LOCAL Login code login
+///
+/// Logs in with the code received by email, in the browser that asked for it.
+/// When the code was asked during an OAuth login the login request is accepted, and `redirectTo` must be followed
+#[api_v2_operation]
+#[post("/login-code/login")]
+pub async fn login_code_login(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session: Session,
+	Json(login_code_login_input): Json<LoginCodeLoginInput>,
+) -> Result<Json<PasswordlessLoginResponse>, LoginCodeErrors> {
+	let attempt: String = session
+		.get(LOGIN_CODE_ATTEMPT_KEY)
+		.map_err(AuthErrors::from)?
+		.ok_or(LoginCodeErrors::NoPendingLogin)?;
+	let login_code = LoginCode::find_pending(&db, &attempt)
+		.await?
+		.ok_or(LoginCodeErrors::NoPendingLogin)?;
+
+	let account = login_code.user.to_hex();
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::ValidateCode, Some(&account), &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	if !login_code.matches(&attempt, &login_code_login_input.code) {
+		throttle
+			.record_failure(ThrottleAction::ValidateCode, Some(&account), &ip)
+			.await
+			.map_err(AuthErrors::from)?;
+		AuditEvent::new(AuditAction::LoginFailed, &req)
+			.subject(&account)
+			.factor("login-code")
+			.detail("reason", LoginCodeErrors::InvalidCode)
+			.record(&db)
+			.await;
+
+		// Counted in the database, so concurrent guesses cannot exceed the limit
+		let failures = LoginCode::find_one_and_update(
+			&db,
+			doc! { "_id": login_code.id },
+			doc! { "$inc": { "failures": 1 } },
+			None,
+		)
+		.await?
+		.map_or(i64::MAX, |login_code| login_code.failures + 1);
+		if failures >= APP_SETTINGS.logincode.attempts {
+			LoginCode::collection(&db)
+				.delete_one(doc! { "_id": login_code.id }, None)
+				.await?;
+			session.remove(LOGIN_CODE_ATTEMPT_KEY);
+			return Err(LoginCodeErrors::TooManyFailures);
+		}
+		return Err(LoginCodeErrors::InvalidCode);
+	}
+
+	// Single use, a concurrent login with the same code finds nothing
+	let login_code = LoginCode::find_one_and_delete(&db, doc! { "_id": login_code.id }, None)
+		.await?
+		.ok_or(LoginCodeErrors::NoPendingLogin)?;
+	throttle
+		.record_success(ThrottleAction::ValidateCode, &account, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	let mut user = User::find_by_id(&db, &login_code.user)
+		.await?
+		.ok_or(LoginCodeErrors::NoPendingLogin)?;
+	if !user.is_active() {
+		return Err(AuthErrors::from(UserErrors::AccountInactive(user.status.state)).into());
+	}
+
+	// Receiving the code proves the ownership of the email
+	if !user.email_scope.email_verified {
+		user.email_scope.email_verified = true;
+		user.save(&db, None).await?;
+	}
+
+	// Safe to unwrap since the user exists
+	let subject = user.id.clone().unwrap().to_hex();
+	session.remove(LOGIN_CODE_ATTEMPT_KEY);
+	start_session(&session, &subject).map_err(|e| AuthErrors::from(UserErrors::from(e)))?;
+	check_login_device(&db, &user, &req).await;
+
+	let login_request = match &login_code.login_challenge {
+		Some(login_challenge) => Some(fetch_login_request(login_challenge).await?),
+		None => None,
+	};
+	let client = login_request
+		.as_ref()
+		.map(|login_request| login_request.client.as_ref());
+	AuditEvent::new(AuditAction::LoginSucceeded, &req)
+		.user(&subject)
+		.client(client)
+		.factor("login-code")
+		.record(&db)
+		.await;
+
+	let redirect_to = match &login_request {
+		Some(login_request) => {
+			// Accept login request, unless the client does not allow the user
+			let completed_login_request = complete_login_request(&db, login_request, &subject, LOGIN_CODE_AMR).await?;
+			Some(completed_login_request.redirect_to)
+		}
+		None => None,
+	};
+
+	Ok(Json(PasswordlessLoginResponse {
+		user: user.into(),
+		redirect_to,
+	}))
+}
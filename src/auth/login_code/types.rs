@@ -0,0 +1,21 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Login code request input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct LoginCodeInput {
+	/// The account email.
+	#[validate(email)]
+	pub email: String,
+	/// The OAuth login challenge, when the code is asked during an OAuth login.
+	pub login_challenge: Option<String>,
+}
+
+/// Login code verification input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct LoginCodeLoginInput {
+	/// The code received by email.
+	pub code: String,
+}
@@ -0,0 +1,27 @@
+use log::{error, info};
+use ory_hydra_client::apis::admin_api;
+
+use crate::settings::ORY_HYDRA_CONFIGURATION;
+
+use super::LogoutErrors;
+
+/// Revokes the Hydra login sessions and consents of the subject, the tokens issued under them are revoked too
+pub async fn revoke_hydra_sessions(subject: &str) -> Result<(), LogoutErrors> {
+	info!("Revoking Hydra sessions");
+
+	admin_api::revoke_authentication_session(&ORY_HYDRA_CONFIGURATION, subject)
+		.await
+		.map_err(|e| {
+			error!("{:?}", e);
+			LogoutErrors::HydraError
+		})?;
+
+	admin_api::revoke_consent_sessions(&ORY_HYDRA_CONFIGURATION, subject, None, Some(true))
+		.await
+		.map_err(|e| {
+			error!("{:?}", e);
+			LogoutErrors::HydraError
+		})?;
+
+	Ok(())
+}
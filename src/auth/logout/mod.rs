@@ -1,7 +1,9 @@
+pub mod api;
 pub mod errors;
 pub mod routes;
 pub mod types;
 
+pub use api::*;
 pub use errors::*;
 pub use routes::*;
 pub use types::*;
@@ -1,12 +1,18 @@
-use crate::{auth::AcceptedRequest, settings::APP_SETTINGS, settings::ORY_HYDRA_CONFIGURATION};
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::AcceptedRequest,
+	settings::{APP_SETTINGS, ORY_HYDRA_CONFIGURATION},
+};
 
+use actix_web::HttpRequest;
 use log::{error, info};
 use ory_hydra_client::apis::admin_api;
 use paperclip::actix::{
 	api_v2_operation, get, post,
-	web::{HttpResponse, Json, Query},
+	web::{Data, HttpResponse, Json, Query},
 };
 use url::Url;
+use wither::mongodb::Database as MongoDatabase;
 
 use super::{LogoutErrors, OauthLogoutRequest};
 
@@ -44,9 +50,21 @@ pub async fn get_logout(oauth_request: Query<OauthLogoutRequest>) -> Result<Http
 /// Logs out the user, responds with a redirect to follow
 #[api_v2_operation]
 #[post("/logout")]
-pub async fn post_logout(oauth_request: Query<OauthLogoutRequest>) -> Result<Json<AcceptedRequest>, LogoutErrors> {
+pub async fn post_logout(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	oauth_request: Query<OauthLogoutRequest>,
+) -> Result<Json<AcceptedRequest>, LogoutErrors> {
 	info!("Handling accepted logout");
 
+	// Fetched for the audit log, the accepted request does not tell who logged out
+	let ask_logout_request = admin_api::get_logout_request(&ORY_HYDRA_CONFIGURATION, &oauth_request.logout_challenge)
+		.await
+		.map_err(|e| {
+			error!("{:?}", e);
+			LogoutErrors::HydraError
+		})?;
+
 	let accept_logout_request =
 		admin_api::accept_logout_request(&ORY_HYDRA_CONFIGURATION, &oauth_request.logout_challenge)
 			.await
@@ -55,5 +73,11 @@ pub async fn post_logout(oauth_request: Query<OauthLogoutRequest>) -> Result<Jso
 				LogoutErrors::HydraError
 			})?;
 
+	let mut event = AuditEvent::new(AuditAction::Logout, &req);
+	if let Some(subject) = &ask_logout_request.subject {
+		event = event.user(subject);
+	}
+	event.record(&db).await;
+
 	Ok(Json(accept_logout_request.into()))
 }
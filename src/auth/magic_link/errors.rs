@@ -0,0 +1,61 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use paperclip::actix::api_v2_errors;
+use serde::Serialize;
+use thiserror::Error;
+use wither::WitherError;
+
+use crate::auth::{AuthErrors, LoginErrors};
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "Wrong input, or the link is invalid, expired or already used",
+	code = 403,
+	description = "The link was opened in a different browser, or the account is not active",
+	code = 429,
+	description = "Too many attempts, retry after the seconds in the Retry-After header",
+	code = 500,
+	description = "Internal server error, could be a db connection error, email server error, Hydra error"
+)]
+#[derive(Error, Debug)]
+pub enum MagicLinkErrors {
+	#[error("Invalid or expired link")]
+	InvalidLink,
+	#[error("The link must be opened in the browser where it was asked for")]
+	DifferentBrowser,
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+	#[error("{0}")]
+	LoginError(#[from] LoginErrors),
+}
+
+impl ResponseError for MagicLinkErrors {
+	fn error_response(&self) -> HttpResponse {
+		match self {
+			Self::AuthError(e) => e.error_response(),
+			Self::LoginError(e) => e.error_response(),
+			_ => {
+				let error_response = ErrorResponse {
+					error: self.to_string(),
+				};
+				HttpResponse::build(self.status_code()).json(error_response)
+			}
+		}
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::InvalidLink => StatusCode::BAD_REQUEST,
+			Self::DifferentBrowser => StatusCode::FORBIDDEN,
+			Self::AuthError(e) => e.status_code(),
+			Self::LoginError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
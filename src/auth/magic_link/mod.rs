@@ -0,0 +1,9 @@
+pub mod errors;
+pub mod model;
+pub mod routes;
+pub mod types;
+
+pub use errors::*;
+pub use model::*;
+pub use routes::*;
+pub use types::*;
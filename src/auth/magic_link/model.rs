@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	prelude::*,
+	WitherError,
+};
+
+use crate::{session::unix_now, utils::hash_token};
+
+/// The session state key holding the nonce that binds the magic links to the browser that asked for them
+pub const MAGIC_LINK_BINDING_KEY: &str = "magic_link_binding";
+
+/// A pending passwordless login, deleted once used
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"token": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct MagicLink {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// SHA-256 of the token sent by email, the token itself is never stored
+	pub token: String,
+	pub user: ObjectId,
+	/// SHA-256 of the nonce kept in the session of the browser that asked for the link
+	pub binding: String,
+	/// The Hydra login request to accept once logged in
+	pub login_challenge: Option<String>,
+	pub expires_at: i64,
+}
+
+impl MagicLink {
+	/// Finds an unexpired link by the token sent by email
+	pub async fn find_valid(db: &Database, token: &str) -> Result<Option<Self>, WitherError> {
+		let filter = doc! {
+			"token": hash_token(token),
+			"expires_at": { "$gt": unix_now() },
+		};
+		MagicLink::find_one(db, filter, None).await
+	}
+
+	/// Checks if the nonce of the browser session is the one the link was bound to
+	pub fn is_bound_to(&self, binding: Option<&str>) -> bool {
+		binding.map_or(false, |binding| hash_token(binding) == self.binding)
+	}
+}
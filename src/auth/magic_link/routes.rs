@@ -0,0 +1,219 @@
+use actix_session::Session;
+use actix_web::HttpRequest;
+use paperclip::actix::{
+	api_v2_operation, post,
+	web::{Data, Json},
+};
+use serde::{Deserialize, Serialize};
+use url::Url;
+use validator::Validate;
+use wither::{bson::doc, mongodb::Database as MongoDatabase, Model};
+
+use crate::{
+	audit::{AuditAction, AuditEvent},
+	auth::{
+		check_login_device, complete_login_request, fetch_login_request, send_email_to_user, AuthErrors, EmailSentResponse,
+		PasswordlessLoginResponse,
+	},
+	session::{start_session, unix_now},
+	settings::{APP_SETTINGS, HANDLEBARS, MAGIC_LINK_TEMPLATE_NAME},
+	throttle::{Throttle, ThrottleAction},
+	user::{User, UserErrors},
+	utils::{client_ip, generate_token, hash_token},
+};
+
+use super::{MagicLink, MagicLinkErrors, MagicLinkInput, MagicLinkLoginInput, MAGIC_LINK_BINDING_KEY};
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct MagicLinkEMailData {
+	pub username: String,
+	pub link: String,
+	pub minutes: i64,
+}
+
+/// Creates the link, replacing the pending ones of the user, and sends it by email
+async fn send_magic_link(
+	db: &MongoDatabase,
+	user: &User,
+	binding: &str,
+	login_challenge: Option<String>,
+) -> Result<(), MagicLinkErrors> {
+	// Safe to unwrap, the user exists
+	let user_id = user.id.clone().unwrap();
+	let now = unix_now();
+	MagicLink::delete_many(
+		db,
+		doc! { "$or": [{ "user": user_id }, { "expires_at": { "$lte": now } }] },
+		None,
+	)
+	.await?;
+
+	let token = generate_token();
+	let mut magic_link = MagicLink {
+		id: None,
+		token: hash_token(&token),
+		user: user_id,
+		binding: hash_token(binding),
+		login_challenge,
+		expires_at: now + APP_SETTINGS.magiclink.lifetime,
+	};
+	magic_link.save(db, None).await?;
+
+	let mut link = Url::parse(&APP_SETTINGS.server.clienturi).map_err(AuthErrors::from)?;
+	link = link.join("magic-link").map_err(AuthErrors::from)?;
+	link.set_query(Some(&format!("token={}", token)));
+
+	let username = user.display_name();
+	let magic_link_data = MagicLinkEMailData {
+		username: username.clone(),
+		link: link.to_string(),
+		minutes: APP_SETTINGS.magiclink.lifetime / 60,
+	};
+	let html_mail = HANDLEBARS
+		.render(MAGIC_LINK_TEMPLATE_NAME, &magic_link_data)
+		.map_err(AuthErrors::from)?;
+	let email_title = "Your Odysseus login link";
+
+	send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail)?;
+
+	Ok(())
+}
+
+/// LOCAL Request magic link
+///
+/// Emails a single-use login link, the response is the same whether the account exists or not.
+/// The link works only in the browser that asked for it
+#[api_v2_operation]
+#[post("/magic-link")]
+pub async fn request_magic_link(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session: Session,
+	Json(magic_link_input): Json<MagicLinkInput>,
+) -> Result<Json<EmailSentResponse>, MagicLinkErrors> {
+	magic_link_input
+		.validate()
+		.map_err(|e| AuthErrors::from(UserErrors::ValidationError(e)))?;
+
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::MagicLink, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+	// Every request counts against the IP
+	throttle
+		.record_failure(ThrottleAction::MagicLink, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	// Bound before looking for the user, so unknown emails are not disclosed
+	let binding = generate_token();
+	session
+		.insert(MAGIC_LINK_BINDING_KEY, &binding)
+		.map_err(AuthErrors::from)?;
+
+	let MagicLinkInput { email, login_challenge } = magic_link_input;
+	if let Some(user) = User::find_by_email(&db, &email).await? {
+		if user.is_active() {
+			send_magic_link(&db, &user, &binding, login_challenge).await?;
+		}
+	}
+
+	Ok(Json(EmailSentResponse { email_sent: true }))
+}
+
+/// LOCAL Magic link login
+///
+/// Logs in with the token of a magic link, from the browser that asked for it.
+/// When the link was asked during an OAuth login the login request is accepted, and `redirectTo` must be followed
+#[api_v2_operation]
+#[post("/magic-link/login")]
+pub async fn magic_link_login(
+	req: HttpRequest,
+	db: Data<MongoDatabase>,
+	throttle: Data<Throttle>,
+	session: Session,
+	Json(magic_link_login_input): Json<MagicLinkLoginInput>,
+) -> Result<Json<PasswordlessLoginResponse>, MagicLinkErrors> {
+	let ip = client_ip(&req);
+	throttle
+		.check(ThrottleAction::ValidateCode, None, &ip)
+		.await
+		.map_err(AuthErrors::from)?;
+
+	let magic_link = match MagicLink::find_valid(&db, &magic_link_login_input.token).await? {
+		Some(magic_link) => magic_link,
+		None => {
+			throttle
+				.record_failure(ThrottleAction::ValidateCode, None, &ip)
+				.await
+				.map_err(AuthErrors::from)?;
+			return Err(MagicLinkErrors::InvalidLink);
+		}
+	};
+
+	// An intercepted link is useless in another browser, and stays valid for its owner
+	let binding: Option<String> = session.get(MAGIC_LINK_BINDING_KEY).map_err(AuthErrors::from)?;
+	if !magic_link.is_bound_to(binding.as_deref()) {
+		AuditEvent::new(AuditAction::LoginFailed, &req)
+			.subject(&magic_link.user.to_hex())
+			.factor("magic-link")
+			.detail("reason", MagicLinkErrors::DifferentBrowser)
+			.record(&db)
+			.await;
+		return Err(MagicLinkErrors::DifferentBrowser);
+	}
+
+	// Single use, a concurrent login with the same link finds nothing
+	let magic_link = MagicLink::find_one_and_delete(&db, doc! { "_id": magic_link.id }, None)
+		.await?
+		.ok_or(MagicLinkErrors::InvalidLink)?;
+
+	let mut user = User::find_by_id(&db, &magic_link.user)
+		.await?
+		.ok_or(MagicLinkErrors::InvalidLink)?;
+	if !user.is_active() {
+		return Err(AuthErrors::from(UserErrors::AccountInactive(user.status.state)).into());
+	}
+
+	// Following the link proves the ownership of the email
+	if !user.email_scope.email_verified {
+		user.email_scope.email_verified = true;
+		user.save(&db, None).await?;
+	}
+
+	// Safe to unwrap since the user exists
+	let subject = user.id.clone().unwrap().to_hex();
+	session.remove(MAGIC_LINK_BINDING_KEY);
+	start_session(&session, &subject).map_err(|e| AuthErrors::from(UserErrors::from(e)))?;
+	check_login_device(&db, &user, &req).await;
+
+	let login_request = match &magic_link.login_challenge {
+		Some(login_challenge) => Some(fetch_login_request(login_challenge).await?),
+		None => None,
+	};
+	let client = login_request
+		.as_ref()
+		.map(|login_request| login_request.client.as_ref());
+	AuditEvent::new(AuditAction::LoginSucceeded, &req)
+		.user(&subject)
+		.client(client)
+		.factor("magic-link")
+		.record(&db)
+		.await;
+
+	let redirect_to = match &login_request {
+		Some(login_request) => {
+			// Accept login request, unless the client does not allow the user
+			let completed_login_request = complete_login_request(&db, login_request, &subject, &[]).await?;
+			Some(completed_login_request.redirect_to)
+		}
+		None => None,
+	};
+
+	Ok(Json(PasswordlessLoginResponse {
+		user: user.into(),
+		redirect_to,
+	}))
+}
@@ -0,0 +1,21 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+/// Magic link request input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct MagicLinkInput {
+	/// The account email.
+	#[validate(email)]
+	pub email: String,
+	/// The OAuth login challenge, when the link is asked during an OAuth login.
+	pub login_challenge: Option<String>,
+}
+
+/// Magic link login input
+#[derive(Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct MagicLinkLoginInput {
+	/// The token of the link.
+	pub token: String,
+}
@@ -1,17 +1,27 @@
 pub mod consent;
+pub mod discovery;
 pub mod errors;
+pub mod federation;
 pub mod local;
 pub mod login;
+pub mod login_alert;
+pub mod login_code;
 pub mod logout;
+pub mod magic_link;
 pub mod oauth_client;
 pub mod routes;
 pub mod types;
 
 pub use consent::*;
+pub use discovery::*;
 pub use errors::*;
+pub use federation::*;
 pub use local::*;
 pub use login::*;
+pub use login_alert::*;
+pub use login_code::*;
 pub use logout::*;
+pub use magic_link::*;
 pub use oauth_client::*;
 pub use routes::*;
 pub use types::*;
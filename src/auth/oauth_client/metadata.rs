@@ -1,7 +1,20 @@
+use ory_hydra_client::models::OAuth2Client;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Default, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(default, rename_all = "camelCase")]
 pub struct Metadata {
 	pub is_trusted: bool,
+	/// When not empty, only members of one of these organizations, given by ID, can log in
+	pub organizations: Vec<String>,
+}
+
+impl Metadata {
+	/// Reads the metadata of the OAuth client, missing or malformed metadata is the default one
+	pub fn from_client(client: Option<&OAuth2Client>) -> Self {
+		client
+			.and_then(|client| client.metadata.clone())
+			.and_then(|metadata| serde_json::from_value(metadata).ok())
+			.unwrap_or_default()
+	}
 }
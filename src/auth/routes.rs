@@ -1,8 +1,11 @@
 use paperclip::actix::web::{scope, ServiceConfig};
 
 use super::{
-	get_consent, get_login, get_logout, local_login, post_consent, post_login, post_logout, signup, user_info,
-	validate_email,
+	change_password, change_username, confirm_pending_link, discover, federation_authorize, federation_callback,
+	federation_link, forgot_password, get_consent, get_login, get_logout, get_pending_link, list_identities,
+	list_providers, local_login, login_code_login, magic_link_login, not_me, post_consent, post_login, post_logout,
+	request_login_code, request_magic_link, reset_password, signup, signup_invitation, unlink_identity, user_info,
+	username_availability, validate_email,
 };
 
 /// Configures all the auth routes
@@ -11,11 +14,36 @@ pub fn init_routes(cfg: &mut ServiceConfig) {
 	cfg.service(
 		scope("/local")
 			.service(signup)
+			.service(signup_invitation)
+			.service(forgot_password)
+			.service(reset_password)
+			.service(change_password)
+			.service(change_username)
+			.service(username_availability)
 			.service(validate_email)
+			.service(discover)
 			.service(local_login)
+			.service(request_magic_link)
+			.service(magic_link_login)
+			.service(request_login_code)
+			.service(login_code_login)
+			.service(not_me)
 			.service(user_info),
 	);
 
+	// Upstream identity provider routes
+	cfg.service(
+		scope("/federation")
+			.service(list_providers)
+			.service(federation_authorize)
+			.service(federation_link)
+			.service(federation_callback)
+			.service(get_pending_link)
+			.service(confirm_pending_link)
+			.service(list_identities)
+			.service(unlink_identity),
+	);
+
 	// Oauth routes
 	cfg.service(
 		scope("/oauth")
@@ -2,6 +2,8 @@ use ory_hydra_client::models::CompletedRequest;
 use paperclip::actix::Apiv2Schema;
 use serde::{Deserialize, Serialize};
 
+use crate::user::UserInfo;
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
 pub struct AcceptedRequest {
 	/// RedirectURL is the URL which you should redirect the user to once the authentication process is completed.
@@ -22,3 +24,47 @@ pub struct LoggedOutResponse {
 	/// This should always be true
 	pub logged_out: bool,
 }
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+/// Response of the flows that must not disclose whether an account exists
+pub struct EmailSentResponse {
+	/// This should always be true, even when no email was actually sent
+	pub email_sent: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasswordChangedResponse {
+	/// This should always be true
+	pub password_changed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+/// The signup form fields given by an invitation
+pub struct SignupInvitationResponse {
+	/// The invited email
+	pub email: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+pub struct UsernameAvailabilityResponse {
+	/// The normalized username, the form used to log in
+	pub username: String,
+	pub available: bool,
+	/// Why the username cannot be taken
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+/// Response of the passwordless logins
+pub struct PasswordlessLoginResponse {
+	/// The logged in user
+	pub user: UserInfo,
+	/// The URL to follow when the login was started during an OAuth login
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub redirect_to: Option<String>,
+}
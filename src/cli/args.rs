@@ -0,0 +1,55 @@
+use std::{
+	collections::{HashMap, HashSet},
+	io,
+};
+
+/// Parsed command arguments: positional values, `--option value` pairs and `--flag`s
+#[derive(Debug, Default)]
+pub struct CommandArgs {
+	pub positional: Vec<String>,
+	options: HashMap<String, Vec<String>>,
+	flags: HashSet<String>,
+}
+
+impl CommandArgs {
+	/// Parses the arguments, `flags` are the option names that do not take a value
+	pub fn parse(args: &[String], flags: &[&str]) -> io::Result<Self> {
+		let mut parsed = Self::default();
+		let mut args = args.iter();
+
+		while let Some(arg) = args.next() {
+			match arg.strip_prefix("--") {
+				Some(name) if flags.contains(&name) => {
+					parsed.flags.insert(name.to_string());
+				}
+				Some(name) => {
+					let value = args
+						.next()
+						.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, format!("Missing value for --{}", name)))?;
+					parsed.options.entry(name.to_string()).or_default().push(value.clone());
+				}
+				None => parsed.positional.push(arg.clone()),
+			}
+		}
+
+		Ok(parsed)
+	}
+
+	/// Gets the last value of an option
+	pub fn option(&self, name: &str) -> Option<&str> {
+		self
+			.options
+			.get(name)
+			.and_then(|values| values.last())
+			.map(String::as_str)
+	}
+
+	/// Gets all the values of a repeatable option
+	pub fn options(&self, name: &str) -> &[String] {
+		self.options.get(name).map(Vec::as_slice).unwrap_or_default()
+	}
+
+	pub fn flag(&self, name: &str) -> bool {
+		self.flags.contains(name)
+	}
+}
@@ -0,0 +1,87 @@
+use std::{io, time::Instant};
+
+use argon2::{password_hash::SaltString, Argon2, PasswordHasher, Version};
+use rand::rngs::OsRng;
+
+use crate::{settings::APP_SETTINGS, utils::argon2_params};
+
+/// OWASP recommended minimum memory cost, in KiB
+const MIN_MEMORY: u32 = 19 * 1024;
+/// Memory cost upper bound, in KiB
+const MAX_MEMORY: u32 = 1024 * 1024;
+/// Iterations upper bound
+const MAX_ITERATIONS: u32 = 64;
+/// Default target latency of a single hash, in milliseconds
+const DEFAULT_TARGET_MS: u128 = 500;
+
+/// Measures how long a single hash takes with the given costs, in milliseconds
+fn measure(memory: u32, iterations: u32, parallelism: u32) -> io::Result<u128> {
+	let settings = &APP_SETTINGS.hasher;
+	let argon2 = Argon2::new(
+		settings.variant.into(),
+		Version::V0x13,
+		argon2_params(memory, iterations, parallelism),
+	);
+	let salt = SaltString::generate(&mut OsRng);
+
+	let start = Instant::now();
+	argon2
+		.hash_password(b"odysseus-calibration-password", &salt)
+		.map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+	Ok(start.elapsed().as_millis())
+}
+
+/// Finds the highest Argon2 costs whose hash time stays within the target latency.
+///
+/// Memory is raised first, then iterations, the configured variant and parallelism are kept.
+///
+/// Usage: `calibrate [target milliseconds]`
+pub fn calibrate(args: &[String]) -> io::Result<()> {
+	let target_ms = match args.first() {
+		Some(target_ms) => target_ms
+			.parse()
+			.map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "The target must be in milliseconds"))?,
+		None => DEFAULT_TARGET_MS,
+	};
+	let parallelism = APP_SETTINGS.hasher.parallelism;
+
+	println!("Calibrating Argon2 for a target of {}ms per hash...", target_ms);
+
+	let mut memory = MIN_MEMORY;
+	let mut iterations = 2;
+	let mut elapsed = measure(memory, iterations, parallelism)?;
+
+	while memory * 2 <= MAX_MEMORY {
+		let next_elapsed = measure(memory * 2, iterations, parallelism)?;
+		if next_elapsed > target_ms {
+			break;
+		}
+		memory *= 2;
+		elapsed = next_elapsed;
+	}
+
+	while iterations < MAX_ITERATIONS {
+		let next_elapsed = measure(memory, iterations + 1, parallelism)?;
+		if next_elapsed > target_ms {
+			break;
+		}
+		iterations += 1;
+		elapsed = next_elapsed;
+	}
+
+	if elapsed > target_ms {
+		println!(
+			"Warning: even the minimum recommended costs take {}ms, consider a higher target",
+			elapsed
+		);
+	}
+
+	println!("Measured {}ms per hash with the following settings:", elapsed);
+	println!("hasher:");
+	println!("  memory: {}", memory);
+	println!("  iterations: {}", iterations);
+	println!("  parallelism: {}", parallelism);
+
+	Ok(())
+}
@@ -0,0 +1,22 @@
+use std::io;
+
+use crate::utils::{connect_database, init_database};
+
+use super::{calibrate, export_users, import_users, migrate_emails, migrate_usernames};
+
+/// Runs a maintenance command instead of the server
+pub async fn run_command(args: &[String]) -> io::Result<()> {
+	match args.split_first() {
+		Some((command, args)) if command == "calibrate" => calibrate(args),
+		Some((command, args)) if command == "import" => import_users(&init_database().await, args).await,
+		Some((command, args)) if command == "export" => export_users(&init_database().await, args).await,
+		// The indexes cannot be synced while colliding emails exist
+		Some((command, args)) if command == "migrate-emails" => migrate_emails(&connect_database().await, args).await,
+		Some((command, args)) if command == "migrate-usernames" => migrate_usernames(&connect_database().await, args).await,
+		Some((command, _)) => Err(io::Error::new(
+			io::ErrorKind::InvalidInput,
+			format!("Unknown command: {}", command),
+		)),
+		None => Ok(()),
+	}
+}
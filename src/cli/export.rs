@@ -0,0 +1,57 @@
+use std::{
+	fs::File,
+	io::{self, BufWriter, Write},
+};
+
+use futures_util::StreamExt;
+use wither::{mongodb::Database, Model};
+
+use crate::user::User;
+
+use super::{CommandArgs, RecordFormat, UserRecord};
+
+fn to_io_error<E: ToString>(e: E) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Exports all the users, streaming them from the database.
+///
+/// Usage: `export <file> [--format jsonl|csv] [--with-hashes]`
+pub async fn export_users(db: &Database, args: &[String]) -> io::Result<()> {
+	let args = CommandArgs::parse(args, &["with-hashes"])?;
+	let path = args
+		.positional
+		.first()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Missing the output file"))?;
+	let format = RecordFormat::detect(args.option("format"), path)?;
+	let with_hashes = args.flag("with-hashes");
+
+	let mut cursor = User::find(db, None, None).await.map_err(to_io_error)?;
+	let mut exported = 0;
+
+	match format {
+		RecordFormat::JsonLines => {
+			let mut writer = BufWriter::new(File::create(path)?);
+			while let Some(user) = cursor.next().await {
+				let record = UserRecord::from_user(user.map_err(to_io_error)?, with_hashes);
+				serde_json::to_writer(&mut writer, &record)?;
+				writer.write_all(b"\n")?;
+				exported += 1;
+			}
+			writer.flush()?;
+		}
+		RecordFormat::Csv => {
+			let mut writer = csv::Writer::from_path(path)?;
+			while let Some(user) = cursor.next().await {
+				let record = UserRecord::from_user(user.map_err(to_io_error)?, with_hashes);
+				writer.serialize(&record).map_err(to_io_error)?;
+				exported += 1;
+			}
+			writer.flush()?;
+		}
+	}
+
+	println!("Exported {} users to {}", exported, path);
+
+	Ok(())
+}
@@ -0,0 +1,290 @@
+use std::{
+	collections::{HashMap, HashSet},
+	fs::File,
+	io::{self, BufRead, BufReader, Write},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+use validator::validate_email;
+use wither::{mongodb::Database, Model};
+
+use crate::{
+	auth::send_email_to_user,
+	password::{check_password_policy, PasswordPolicyErrors},
+	settings::{init_keyed_totp_long, HANDLEBARS, SIGNUP_TEMPLATE_NAME, WELCOME_TEMPLATE_NAME},
+	user::{check_username, email_key, normalize_email, EmailScope, PhoneScope, ProfileScope, User},
+	utils::{hash_password, hash_scheme},
+};
+
+use super::{CommandArgs, RecordFormat, UserRecord};
+
+/// A row that could not be imported
+#[derive(Debug, Serialize)]
+struct RowError {
+	row: usize,
+	email: String,
+	error: String,
+}
+
+#[derive(Debug, Serialize)]
+struct EMailData {
+	username: String,
+	code: String,
+}
+
+/// Streams the rows of the input file as column -> value maps
+fn read_rows(
+	path: &str,
+	format: RecordFormat,
+) -> io::Result<Box<dyn Iterator<Item = Result<HashMap<String, String>, String>>>> {
+	match format {
+		RecordFormat::JsonLines => {
+			let lines = BufReader::new(File::open(path)?).lines();
+			Ok(Box::new(
+				lines
+					.filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+					.map(|line| {
+						let line = line.map_err(|e| e.to_string())?;
+						match serde_json::from_str(&line).map_err(|e| e.to_string())? {
+							Value::Object(object) => Ok(
+								object
+									.into_iter()
+									.filter_map(|(key, value)| match value {
+										Value::Null => None,
+										Value::String(value) => Some((key, value)),
+										value => Some((key, value.to_string())),
+									})
+									.collect(),
+							),
+							_ => Err("Expected a JSON object".to_string()),
+						}
+					}),
+			))
+		}
+		RecordFormat::Csv => {
+			let reader = csv::Reader::from_path(path).map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+			Ok(Box::new(
+				reader
+					.into_deserialize::<HashMap<String, String>>()
+					.map(|row| row.map_err(|e| e.to_string())),
+			))
+		}
+	}
+}
+
+/// Validates the record and builds the user, the password is hashed only when the user is going to be saved
+async fn build_user(
+	db: &Database,
+	record: UserRecord,
+	dry_run: bool,
+	seen_emails: &mut HashSet<String>,
+	seen_usernames: &mut HashSet<String>,
+) -> Result<User, String> {
+	if !validate_email(record.email.as_str()) {
+		return Err(format!("Invalid email: {:?}", record.email));
+	}
+	if !seen_emails.insert(email_key(&record.email)) {
+		return Err("Duplicate email in the file".to_string());
+	}
+	if User::find_by_email(db, &record.email)
+		.await
+		.map_err(|e| e.to_string())?
+		.is_some()
+	{
+		return Err("Email already registered".to_string());
+	}
+
+	let username = match &record.preferred_username {
+		Some(username) => {
+			let username = check_username(username).map_err(|e| e.to_string())?;
+			if !seen_usernames.insert(username.clone()) {
+				return Err("Duplicate username in the file".to_string());
+			}
+			if User::find_by_username(db, &username)
+				.await
+				.map_err(|e| e.to_string())?
+				.is_some()
+			{
+				return Err("Username already taken".to_string());
+			}
+			Some(username)
+		}
+		None => None,
+	};
+
+	let password = match (record.password_hash, record.password) {
+		(Some(password_hash), _) => {
+			hash_scheme(&password_hash).ok_or_else(|| "Unsupported password hash format".to_string())?;
+			password_hash
+		}
+		(None, Some(password)) => {
+			let username = record.preferred_username.as_deref().unwrap_or_default();
+			check_password_policy(&password, &[record.email.as_str(), username]).map_err(|e| match e {
+				PasswordPolicyErrors::Violations(violations) => violations
+					.iter()
+					.map(ToString::to_string)
+					.collect::<Vec<_>>()
+					.join(", "),
+				e => e.to_string(),
+			})?;
+			if dry_run {
+				String::new()
+			} else {
+				hash_password(&password).await.map_err(|e| e.to_string())?
+			}
+		}
+		(None, None) => return Err("Missing password or password_hash".to_string()),
+	};
+
+	Ok(User {
+		id: None,
+		password,
+		username,
+		email_scope: EmailScope {
+			email: normalize_email(&record.email),
+			email_verified: record.email_verified,
+		},
+		profile_scope: ProfileScope {
+			preferred_username: record.preferred_username,
+			given_name: record.given_name,
+			middle_name: record.middle_name,
+			family_name: record.family_name,
+			nickname: record.nickname,
+			profile: record.profile,
+			picture: record.picture,
+			website: record.website,
+			birthdate: record.birthdate,
+			zoneinfo: record.zoneinfo,
+			locale: record.locale,
+			..Default::default()
+		},
+		phone_scope: PhoneScope {
+			phone_number: record.phone_number,
+			phone_number_verified: record.phone_number_verified,
+		},
+		..Default::default()
+	})
+}
+
+/// Sends the welcome email, with the verification code when the email is not verified yet
+fn send_import_email(user: &User) -> Result<(), String> {
+	let username = user.display_name();
+	// Safe to unwrap, the user has been saved
+	let code = if user.email_scope.email_verified {
+		String::new()
+	} else {
+		init_keyed_totp_long(&user.id.clone().unwrap().to_hex()).generate()
+	};
+	let email_data = EMailData {
+		username: username.clone(),
+		code,
+	};
+
+	let (template, email_title) = if user.email_scope.email_verified {
+		(WELCOME_TEMPLATE_NAME, "Welcome to Odysseus!")
+	} else {
+		(SIGNUP_TEMPLATE_NAME, "You signed up in Odysseus successfully!")
+	};
+
+	let html_mail = HANDLEBARS.render(template, &email_data).map_err(|e| e.to_string())?;
+	send_email_to_user(&user.email_scope.email, &username, email_title, &html_mail).map_err(|e| e.to_string())
+}
+
+/// Imports users, streaming them from the input file.
+///
+/// Usage: `import <file> [--format jsonl|csv] [--map <claim>=<column>]... [--dry-run] [--emails] [--report <file>]`
+///
+/// Rows provide either a plain text `password`, hashed and checked against the password policy,
+/// or a `password_hash` in any supported format. Rows that fail are written to the report (stderr by default).
+pub async fn import_users(db: &Database, args: &[String]) -> io::Result<()> {
+	let args = CommandArgs::parse(args, &["dry-run", "emails"])?;
+	let path = args
+		.positional
+		.first()
+		.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Missing the input file"))?;
+	let format = RecordFormat::detect(args.option("format"), path)?;
+	let dry_run = args.flag("dry-run");
+	let send_emails = args.flag("emails") && !dry_run;
+
+	let mut mapping = HashMap::new();
+	for map in args.options("map") {
+		let (claim, column) = map.split_once('=').ok_or_else(|| {
+			io::Error::new(
+				io::ErrorKind::InvalidInput,
+				format!("Invalid mapping {:?}, expected <claim>=<column>", map),
+			)
+		})?;
+		mapping.insert(claim.to_string(), column.to_string());
+	}
+
+	let mut report: Box<dyn Write> = match args.option("report") {
+		Some(report_path) => Box::new(File::create(report_path)?),
+		None => Box::new(io::stderr()),
+	};
+
+	let (mut seen_emails, mut seen_usernames) = (HashSet::new(), HashSet::new());
+	let (mut imported, mut failed) = (0, 0);
+
+	for (index, row) in read_rows(path, format)?.enumerate() {
+		let (email, result) = match row {
+			Ok(row) => {
+				let record = UserRecord::from_row(&row, &mapping);
+				let email = record.email.clone();
+				let result = build_user(db, record, dry_run, &mut seen_emails, &mut seen_usernames).await;
+				(email, result)
+			}
+			Err(error) => (String::new(), Err(error)),
+		};
+
+		let error = match result {
+			Ok(_) if dry_run => {
+				imported += 1;
+				None
+			}
+			Ok(mut user) => match user.save(db, None).await {
+				Ok(_) => {
+					imported += 1;
+					if send_emails {
+						// The user stays imported, the failure is only reported
+						send_import_email(&user)
+							.err()
+							.map(|e| format!("Imported, but the email could not be sent: {}", e))
+					} else {
+						None
+					}
+				}
+				Err(e) => {
+					failed += 1;
+					Some(e.to_string())
+				}
+			},
+			Err(e) => {
+				failed += 1;
+				Some(e)
+			}
+		};
+
+		if let Some(error) = error {
+			let row_error = RowError {
+				row: index + 1,
+				email,
+				error,
+			};
+			serde_json::to_writer(&mut report, &row_error)?;
+			report.write_all(b"\n")?;
+		}
+	}
+	report.flush()?;
+
+	if dry_run {
+		println!(
+			"Dry run: {} users would be imported, {} rows have errors",
+			imported, failed
+		);
+	} else {
+		println!("Imported {} users, {} rows have errors", imported, failed);
+	}
+
+	Ok(())
+}
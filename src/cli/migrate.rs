@@ -0,0 +1,209 @@
+use std::{
+	collections::{BTreeMap, HashSet},
+	fs::File,
+	io::{self, Write},
+};
+
+use futures_util::StreamExt;
+use serde::Serialize;
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	Model,
+};
+
+use crate::user::{check_username, email_key, normalize_email, User};
+
+use super::CommandArgs;
+
+/// Users whose emails normalize to the same address
+#[derive(Debug, Serialize)]
+struct EmailCollision {
+	email: String,
+	users: Vec<CollidingUser>,
+}
+
+#[derive(Debug, Serialize)]
+struct CollidingUser {
+	id: String,
+	email: String,
+}
+
+/// A `preferred_username` that cannot become the username of its account
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SkippedUsername {
+	id: String,
+	preferred_username: String,
+	reason: String,
+}
+
+fn to_io_error<E: ToString>(e: E) -> io::Error {
+	io::Error::new(io::ErrorKind::Other, e.to_string())
+}
+
+/// Normalizes the stored emails and reports the accounts colliding once normalized.
+///
+/// Usage: `migrate-emails [--apply] [--report <file>]`
+///
+/// Colliding accounts are never modified, they must be merged or renamed by hand:
+/// the case insensitive unique index cannot be built until they are resolved.
+pub async fn migrate_emails(db: &Database, args: &[String]) -> io::Result<()> {
+	let args = CommandArgs::parse(args, &["apply"])?;
+	let apply = args.flag("apply");
+
+	// Email key -> (user id, stored email, normalized email)
+	let mut accounts: BTreeMap<String, Vec<(ObjectId, String, String)>> = BTreeMap::new();
+	let mut cursor = User::find(db, None, None).await.map_err(to_io_error)?;
+	while let Some(user) = cursor.next().await {
+		let user = user.map_err(to_io_error)?;
+		let email = user.email_scope.email;
+		// Safe to unwrap, the user comes from the database
+		accounts
+			.entry(email_key(&email))
+			.or_default()
+			.push((user.id.unwrap(), email.clone(), normalize_email(&email)));
+	}
+
+	let mut report: Box<dyn Write> = match args.option("report") {
+		Some(report_path) => Box::new(File::create(report_path)?),
+		None => Box::new(io::stderr()),
+	};
+
+	let (mut normalized, mut collisions) = (0, 0);
+	for (key, users) in accounts {
+		if users.len() > 1 {
+			collisions += 1;
+			let collision = EmailCollision {
+				email: key,
+				users: users
+					.into_iter()
+					.map(|(id, email, _)| CollidingUser { id: id.to_hex(), email })
+					.collect(),
+			};
+			serde_json::to_writer(&mut report, &collision)?;
+			report.write_all(b"\n")?;
+			continue;
+		}
+
+		let (id, email, normalized_email) = &users[0];
+		if email != normalized_email {
+			normalized += 1;
+			if apply {
+				User::collection(db)
+					.update_one(
+						doc! { "_id": *id },
+						doc! { "$set": { "email": normalized_email.as_str() } },
+						None,
+					)
+					.await
+					.map_err(to_io_error)?;
+			}
+		}
+	}
+	report.flush()?;
+
+	if apply {
+		println!("Normalized {} emails", normalized);
+	} else {
+		println!("{} emails to normalize, run with --apply to update them", normalized);
+	}
+	if collisions > 0 {
+		println!(
+			"{} colliding emails reported, resolve them before starting the server",
+			collisions
+		);
+	}
+
+	Ok(())
+}
+
+/// Sets the missing usernames from the `preferred_username` of the accounts created before usernames were unique.
+///
+/// Usage: `migrate-usernames [--apply] [--report <file>]`
+///
+/// Names breaking the policy, already taken, or claimed by several accounts are reported and left unset,
+/// those users pick a username themselves.
+pub async fn migrate_usernames(db: &Database, args: &[String]) -> io::Result<()> {
+	let args = CommandArgs::parse(args, &["apply"])?;
+	let apply = args.flag("apply");
+
+	let mut taken = HashSet::new();
+	// Normalized username -> (user id, preferred username) of the accounts without a username
+	let mut candidates: BTreeMap<String, Vec<(ObjectId, String)>> = BTreeMap::new();
+	let mut skipped = Vec::new();
+	let mut cursor = User::find(db, None, None).await.map_err(to_io_error)?;
+	while let Some(user) = cursor.next().await {
+		let user = user.map_err(to_io_error)?;
+		// Safe to unwrap, the user comes from the database
+		let id = user.id.unwrap();
+		match (user.username, user.profile_scope.preferred_username) {
+			(Some(username), _) => {
+				taken.insert(username);
+			}
+			(None, Some(preferred_username)) => match check_username(&preferred_username) {
+				Ok(username) => candidates.entry(username).or_default().push((id, preferred_username)),
+				Err(e) => skipped.push(SkippedUsername {
+					id: id.to_hex(),
+					preferred_username,
+					reason: e.to_string(),
+				}),
+			},
+			(None, None) => {}
+		}
+	}
+
+	let mut migrated = 0;
+	for (username, users) in candidates {
+		if taken.contains(&username) || users.len() > 1 {
+			let reason = if taken.contains(&username) {
+				"taken by another account"
+			} else {
+				"claimed by several accounts"
+			};
+			skipped.extend(users.into_iter().map(|(id, preferred_username)| SkippedUsername {
+				id: id.to_hex(),
+				preferred_username,
+				reason: reason.to_string(),
+			}));
+			continue;
+		}
+
+		migrated += 1;
+		if apply {
+			// Not recorded in the history, the backfill must not start the change cooldown
+			User::collection(db)
+				.update_one(
+					doc! { "_id": users[0].0, "username": null },
+					doc! { "$set": { "username": username.as_str() } },
+					None,
+				)
+				.await
+				.map_err(to_io_error)?;
+		}
+	}
+
+	let mut report: Box<dyn Write> = match args.option("report") {
+		Some(report_path) => Box::new(File::create(report_path)?),
+		None => Box::new(io::stderr()),
+	};
+	for skipped_username in &skipped {
+		serde_json::to_writer(&mut report, skipped_username)?;
+		report.write_all(b"\n")?;
+	}
+	report.flush()?;
+
+	if apply {
+		println!("Set {} usernames", migrated);
+	} else {
+		println!("{} usernames to set, run with --apply to update them", migrated);
+	}
+	if !skipped.is_empty() {
+		println!(
+			"{} accounts reported, their users must choose a username",
+			skipped.len()
+		);
+	}
+
+	Ok(())
+}
@@ -0,0 +1,15 @@
+pub mod args;
+pub mod calibrate;
+pub mod command;
+pub mod export;
+pub mod import;
+pub mod migrate;
+pub mod record;
+
+pub use args::*;
+pub use calibrate::*;
+pub use command::*;
+pub use export::*;
+pub use import::*;
+pub use migrate::*;
+pub use record::*;
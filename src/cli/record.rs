@@ -0,0 +1,136 @@
+use std::{collections::HashMap, io, path::Path};
+
+use serde::Serialize;
+
+use crate::user::User;
+
+/// Bulk import/export file format
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RecordFormat {
+	/// One JSON object per line
+	JsonLines,
+	/// Comma separated values with a header row
+	Csv,
+}
+
+impl RecordFormat {
+	/// Gets the format from the `--format` option or from the file extension
+	pub fn detect(format: Option<&str>, path: &str) -> io::Result<Self> {
+		let format = match format {
+			Some(format) => format.to_string(),
+			None => Path::new(path)
+				.extension()
+				.map(|extension| extension.to_string_lossy().to_lowercase())
+				.unwrap_or_default(),
+		};
+
+		match format.as_str() {
+			"jsonl" | "ndjson" | "json" => Ok(Self::JsonLines),
+			"csv" => Ok(Self::Csv),
+			_ => Err(io::Error::new(
+				io::ErrorKind::InvalidInput,
+				"Unknown format, use --format jsonl or --format csv",
+			)),
+		}
+	}
+}
+
+/// A flat user representation, every claim is a column
+#[derive(Debug, Default, Serialize)]
+pub struct UserRecord {
+	pub id: Option<String>,
+	pub email: String,
+	pub email_verified: bool,
+	/// Plain text password, never exported
+	#[serde(skip_serializing)]
+	pub password: Option<String>,
+	/// Password hash in any supported format
+	pub password_hash: Option<String>,
+	pub preferred_username: Option<String>,
+	pub given_name: Option<String>,
+	pub middle_name: Option<String>,
+	pub family_name: Option<String>,
+	pub nickname: Option<String>,
+	pub profile: Option<String>,
+	pub picture: Option<String>,
+	pub website: Option<String>,
+	pub birthdate: Option<String>,
+	pub zoneinfo: Option<String>,
+	pub locale: Option<String>,
+	pub phone_number: Option<String>,
+	pub phone_number_verified: bool,
+}
+
+impl UserRecord {
+	/// Builds the record from an imported row.
+	///
+	/// `mapping` maps a claim to the name of the source column, unmapped claims are read from the column with the same name.
+	pub fn from_row(row: &HashMap<String, String>, mapping: &HashMap<String, String>) -> Self {
+		let field = |claim: &str| {
+			let column = mapping.get(claim).map(String::as_str).unwrap_or(claim);
+			row
+				.get(column)
+				.map(|value| value.trim().to_string())
+				.filter(|value| !value.is_empty())
+		};
+		let flag = |claim: &str| {
+			field(claim)
+				.map(|value| matches!(value.to_lowercase().as_str(), "true" | "1" | "yes"))
+				.unwrap_or(false)
+		};
+
+		Self {
+			id: None,
+			email: field("email").unwrap_or_default(),
+			email_verified: flag("email_verified"),
+			password: field("password"),
+			password_hash: field("password_hash"),
+			preferred_username: field("preferred_username"),
+			given_name: field("given_name"),
+			middle_name: field("middle_name"),
+			family_name: field("family_name"),
+			nickname: field("nickname"),
+			profile: field("profile"),
+			picture: field("picture"),
+			website: field("website"),
+			birthdate: field("birthdate"),
+			zoneinfo: field("zoneinfo"),
+			locale: field("locale"),
+			phone_number: field("phone_number"),
+			phone_number_verified: flag("phone_number_verified"),
+		}
+	}
+
+	/// Builds the record of an exported user
+	pub fn from_user(user: User, with_hash: bool) -> Self {
+		let User {
+			id,
+			password,
+			email_scope,
+			profile_scope,
+			phone_scope,
+			..
+		} = user;
+
+		Self {
+			id: id.map(|id| id.to_hex()),
+			email: email_scope.email,
+			email_verified: email_scope.email_verified,
+			password: None,
+			password_hash: Some(password).filter(|_| with_hash),
+			preferred_username: profile_scope.preferred_username,
+			given_name: profile_scope.given_name,
+			middle_name: profile_scope.middle_name,
+			family_name: profile_scope.family_name,
+			nickname: profile_scope.nickname,
+			profile: profile_scope.profile,
+			picture: profile_scope.picture,
+			website: profile_scope.website,
+			birthdate: profile_scope.birthdate,
+			zoneinfo: profile_scope.zoneinfo,
+			locale: profile_scope.locale,
+			phone_number: phone_scope.phone_number,
+			phone_number_verified: phone_scope.phone_number_verified,
+		}
+	}
+}
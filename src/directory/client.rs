@@ -0,0 +1,127 @@
+use std::time::Duration;
+
+use ldap3::{drive, ldap_escape, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+
+use crate::settings::{DirectorySettings, APP_SETTINGS};
+
+use super::DirectoryErrors;
+
+/// LDAP result code of a bind with wrong credentials
+const INVALID_CREDENTIALS: u32 = 49;
+
+/// The user entry, read through the directory attribute mapping
+#[derive(Debug)]
+pub struct DirectoryEntry {
+	pub dn: String,
+	pub email: Option<String>,
+	pub username: Option<String>,
+	pub given_name: Option<String>,
+	pub family_name: Option<String>,
+	pub groups: Vec<String>,
+}
+
+/// Finds the directory authenticating the emails of a domain
+pub fn find_directory(email: &str) -> Option<(&'static str, &'static DirectorySettings)> {
+	let domain = email.rsplit('@').next().unwrap_or_default().to_lowercase();
+	APP_SETTINGS
+		.ldap
+		.directories
+		.iter()
+		.find(|(_, directory)| directory.email_domains().contains(&domain))
+		.map(|(id, directory)| (id.as_str(), directory))
+}
+
+/// The search filter of the user entry, the email is escaped so it cannot change the filter
+fn user_filter(filter: &str, email: &str) -> String {
+	filter.replace("{email}", &ldap_escape(email))
+}
+
+/// Finds the user entry and binds as the user, `None` when the entry is unknown or the password is wrong
+pub async fn authenticate(
+	directory: &DirectorySettings,
+	email: &str,
+	password: &str,
+) -> Result<Option<DirectoryEntry>, DirectoryErrors> {
+	// An empty password would be an unauthenticated bind, which succeeds on most servers
+	if password.is_empty() {
+		return Ok(None);
+	}
+
+	let settings = LdapConnSettings::new()
+		.set_starttls(directory.starttls)
+		.set_conn_timeout(Duration::from_secs(directory.timeout));
+	let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &directory.url).await?;
+	drive!(conn);
+
+	if let Some(binddn) = &directory.binddn {
+		ldap
+			.simple_bind(binddn, directory.bindpassword.as_deref().unwrap_or_default())
+			.await?
+			.success()?;
+	}
+
+	let mapping = &directory.attributes;
+	let attributes = vec![
+		mapping.email.as_str(),
+		mapping.username.as_str(),
+		mapping.givenname.as_str(),
+		mapping.familyname.as_str(),
+		mapping.groups.as_str(),
+	];
+	let filter = user_filter(&directory.filter, email);
+	let (mut entries, _) = ldap
+		.search(&directory.base, Scope::Subtree, &filter, attributes)
+		.await?
+		.success()?;
+	// Ambiguous filters must not pick an entry at random
+	if entries.len() != 1 {
+		ldap.unbind().await?;
+		return Ok(None);
+	}
+	let entry = SearchEntry::construct(entries.remove(0));
+
+	let bind = ldap.simple_bind(&entry.dn, password).await?;
+	if bind.rc == INVALID_CREDENTIALS {
+		ldap.unbind().await?;
+		return Ok(None);
+	}
+	bind.success()?;
+	ldap.unbind().await?;
+
+	let first = |attribute: &str| {
+		entry
+			.attrs
+			.get(attribute)
+			.and_then(|values| values.first())
+			.filter(|value| !value.is_empty())
+			.cloned()
+	};
+
+	Ok(Some(DirectoryEntry {
+		email: first(&mapping.email),
+		username: first(&mapping.username),
+		given_name: first(&mapping.givenname),
+		family_name: first(&mapping.familyname),
+		groups: entry.attrs.get(&mapping.groups).cloned().unwrap_or_default(),
+		dn: entry.dn,
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn user_filter_inserts_the_email() {
+		assert_eq!(
+			user_filter("(&(objectClass=person)(mail={email}))", "john@example.org"),
+			"(&(objectClass=person)(mail=john@example.org))"
+		);
+	}
+
+	#[test]
+	fn user_filter_escapes_the_filter_syntax() {
+		assert_eq!(user_filter("(mail={email})", "*)(uid=*"), "(mail=\\2a\\29\\28uid=\\2a)");
+		assert_eq!(user_filter("(mail={email})", "a\\b\0c"), "(mail=a\\5cb\\00c)");
+	}
+}
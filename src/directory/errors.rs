@@ -0,0 +1,15 @@
+use ldap3::LdapError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+/// Possible directory errors
+pub enum DirectoryErrors {
+	#[error("The directory is unavailable")]
+	LdapError(#[from] LdapError),
+	#[error("The directory entry has no email")]
+	MissingEmail,
+	#[error("The email of the directory entry is not in a domain of the directory")]
+	ForeignEmail,
+	#[error("No account is linked to this directory account")]
+	NotProvisioned,
+}
@@ -0,0 +1,7 @@
+pub mod client;
+pub mod errors;
+pub mod sync;
+
+pub use client::*;
+pub use errors::*;
+pub use sync::*;
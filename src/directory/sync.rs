@@ -0,0 +1,107 @@
+use wither::{mongodb::Database, Model};
+
+use crate::{
+	settings::DirectorySettings,
+	user::{normalize_email, EmailScope, User, UserErrors},
+};
+
+use super::{authenticate, find_directory, DirectoryEntry, DirectoryErrors};
+
+/// Logs in through the directory of the email domain, the email of the account when logging in with the username.
+/// `None` when no directory handles the login
+pub async fn directory_login(db: &Database, login: &str, password: &str) -> Result<Option<User>, UserErrors> {
+	let email = if login.contains('@') {
+		normalize_email(login)
+	} else {
+		// The domain decides, accounts not synced yet must not keep using their local password
+		match User::find_by_username(db, login).await? {
+			Some(user) => user.email_scope.email,
+			None => return Ok(None),
+		}
+	};
+	let (id, directory) = match find_directory(&email) {
+		Some(directory) => directory,
+		None => return Ok(None),
+	};
+
+	let entry = authenticate(directory, &email, password)
+		.await?
+		.ok_or(UserErrors::InvalidCredentials)?;
+
+	sync_directory_user(db, id, directory, &email, entry).await.map(Some)
+}
+
+/// Creates or updates the account of a directory entry, the directory is authoritative for the mapped attributes
+/// and roles
+pub async fn sync_directory_user(
+	db: &Database,
+	id: &str,
+	directory: &DirectorySettings,
+	email: &str,
+	entry: DirectoryEntry,
+) -> Result<User, UserErrors> {
+	let email = entry
+		.email
+		.as_deref()
+		.map(normalize_email)
+		.unwrap_or_else(|| email.to_string());
+	// The directory only speaks for its own domains, its entries must not reach other accounts
+	let domain = email.rsplit('@').next().unwrap_or_default().to_lowercase();
+	if !directory.email_domains().contains(&domain) {
+		return Err(DirectoryErrors::ForeignEmail.into());
+	}
+
+	let mut user = match User::find_by_email(db, &email).await? {
+		Some(user) => user,
+		None if directory.provision => User {
+			id: None,
+			// Directory accounts never use a local password
+			password: String::new(),
+			email_scope: EmailScope {
+				email,
+				email_verified: true,
+			},
+			..Default::default()
+		},
+		None => return Err(DirectoryErrors::NotProvisioned.into()),
+	};
+
+	user.directory = Some(id.to_string());
+	// The directory bind proves the ownership of the email
+	user.email_scope.email_verified = true;
+	if entry.given_name.is_some() {
+		user.profile_scope.given_name = entry.given_name;
+	}
+	if entry.family_name.is_some() {
+		user.profile_scope.family_name = entry.family_name;
+	}
+	if let (None, Some(username)) = (&user.username, &entry.username) {
+		// Invalid or taken directory usernames are skipped, the user can pick another one
+		if let Err(e) = user.set_username(db, username, false).await {
+			if let UserErrors::DatabaseError(_) = e {
+				return Err(e);
+			}
+		}
+	}
+
+	// Only the roles listed in the mapping are managed by the directory, DNs are case insensitive
+	let groups: Vec<String> = entry.groups.iter().map(|group| group.to_lowercase()).collect();
+	let granted: Vec<&str> = directory
+		.roles
+		.iter()
+		.filter(|mapping| groups.contains(&mapping.group.to_lowercase()))
+		.map(|mapping| mapping.role.as_str())
+		.collect();
+	user
+		.roles
+		.retain(|role| granted.contains(&role.as_str()) || !directory.roles.iter().any(|mapping| &mapping.role == role));
+	for role in granted {
+		if !user.roles.iter().any(|r| r == role) {
+			user.roles.push(role.to_string());
+		}
+	}
+
+	user.save(db, None).await?;
+
+	Ok(user)
+}
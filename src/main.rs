@@ -1,22 +1,51 @@
+use std::env;
+
 use actix_cors::Cors;
-use actix_redis::RedisSession;
-use actix_web::{self, cookie, middleware, web::Data, App, HttpServer};
+use actix_web::{self, middleware, web::Data, App, HttpServer};
 use paperclip::{
 	actix::{web::scope, OpenApiExt},
 	v2::models::{Contact, DefaultApiRaw, Info, License},
 };
 
 use crate::{
+	admin::{
+		create_group, create_permission, create_role, create_signup_invitation, delete_group, delete_permission,
+		delete_role, delete_user, get_user, get_user_access, hasher_metrics, list_audit_events, list_groups,
+		list_permissions, list_roles, list_signup_invitations, list_users, revoke_signup_invitation, set_user_status,
+		trigger_password_reset, update_group, update_role, update_user, verify_user_email,
+	},
 	auth::{
-		get_consent, get_login, get_logout, local_login, post_consent, post_login, post_logout, signup, user_info,
-		validate_email,
+		change_password, change_username, confirm_pending_link, discover, federation_authorize, federation_callback,
+		federation_link, forgot_password, get_consent, get_login, get_logout, get_pending_link, list_identities,
+		list_providers, local_login, login_code_login, magic_link_login, not_me, post_consent, post_login, post_logout,
+		request_login_code, request_magic_link, reset_password, signup, signup_invitation, unlink_identity, user_info,
+		username_availability, validate_email,
 	},
+	cli::run_command,
+	organization::{
+		create_invitation, create_organization, delete_organization, get_organization, join_organization,
+		leave_organization, list_invitations, list_members, list_organizations, remove_member, revoke_invitation,
+		update_member,
+	},
+	session::{init_session_store, SessionMiddleware},
 	settings::APP_SETTINGS,
-	utils::{init_database, init_logger},
+	signup::init_disposable_domains,
+	throttle::init_throttle,
+	utils::{init_database, init_logger, RequestIdMiddleware},
 };
 
+mod admin;
+mod audit;
 mod auth;
+mod cli;
+mod directory;
+mod organization;
+mod password;
+mod role;
+mod session;
 mod settings;
+mod signup;
+mod throttle;
 mod user;
 mod utils;
 
@@ -29,9 +58,24 @@ const DESCRIPTION: &str = env!("CARGO_PKG_DESCRIPTION");
 async fn main() -> std::io::Result<()> {
 	init_logger();
 
+	// Run a maintenance command instead of the server when given
+	let args: Vec<String> = env::args().skip(1).collect();
+	if !args.is_empty() {
+		return run_command(&args).await;
+	}
+
 	// Connect & sync indexes.
 	let identity_database = init_database().await;
 
+	// Create the configured session store
+	let session_store = init_session_store().await;
+
+	// Create the brute-force protection
+	let throttle = init_throttle().await;
+
+	// Load the signup policy disposable email domains
+	init_disposable_domains();
+
 	HttpServer::new(move || {
 		let cors = Cors::default()
 			.allow_any_method()
@@ -44,18 +88,18 @@ async fn main() -> std::io::Result<()> {
 		let spec = create_base_spec();
 
 		App::new()
-			// enable logger
-			.wrap(middleware::Logger::default())
-			// cookie session middleware
-			.wrap(
-				RedisSession::new(&APP_SETTINGS.redis.uri, APP_SETTINGS.session.secret.as_bytes())
-					// Don't allow the cookie to be accessed from javascript
-					.cookie_http_only(true)
-					// allow the cookie only from the current domain or with safe methods
-					.cookie_same_site(cookie::SameSite::Lax),
-			)
+			// enable logger, with the request ID of the audit events
+			.wrap(middleware::Logger::new(
+				r#"%a "%r" %s %b "%{Referer}i" "%{User-Agent}i" %T %{x-request-id}i"#,
+			))
+			// session middleware, backed by the configured store
+			.wrap(SessionMiddleware::new(session_store.clone()))
 			.wrap(cors)
-			.app_data(Data::new(identity_database))
+			// outermost, so the logger and the handlers see the request ID
+			.wrap(RequestIdMiddleware)
+			.app_data(Data::new(identity_database.clone()))
+			.app_data(Data::new(session_store.clone()))
+			.app_data(Data::new(throttle.clone()))
 			// .data(identity_database.clone())
 			// Record services and routes from this line.
 			.wrap_api_with_spec(spec)
@@ -65,10 +109,75 @@ async fn main() -> std::io::Result<()> {
 						.service(
 							scope("/local")
 								.service(signup)
+								.service(signup_invitation)
+								.service(forgot_password)
+								.service(reset_password)
+								.service(change_password)
+								.service(change_username)
+								.service(username_availability)
 								.service(validate_email)
+								.service(discover)
 								.service(local_login)
+								.service(request_magic_link)
+								.service(magic_link_login)
+								.service(request_login_code)
+								.service(login_code_login)
+								.service(not_me)
 								.service(user_info),
 						)
+						.service(
+							scope("/admin")
+								.service(list_users)
+								.service(get_user)
+								.service(get_user_access)
+								.service(update_user)
+								.service(delete_user)
+								.service(verify_user_email)
+								.service(trigger_password_reset)
+								.service(set_user_status)
+								.service(hasher_metrics)
+								.service(list_audit_events)
+								.service(list_permissions)
+								.service(create_permission)
+								.service(delete_permission)
+								.service(list_roles)
+								.service(create_role)
+								.service(update_role)
+								.service(delete_role)
+								.service(list_groups)
+								.service(create_group)
+								.service(update_group)
+								.service(delete_group)
+								.service(create_signup_invitation)
+								.service(list_signup_invitations)
+								.service(revoke_signup_invitation),
+						)
+						.service(
+							scope("/orgs")
+								.service(create_organization)
+								.service(list_organizations)
+								.service(join_organization)
+								.service(get_organization)
+								.service(delete_organization)
+								.service(list_members)
+								.service(update_member)
+								.service(remove_member)
+								.service(leave_organization)
+								.service(create_invitation)
+								.service(list_invitations)
+								.service(revoke_invitation),
+						)
+						.service(
+							scope("/federation")
+								.service(list_providers)
+								.service(federation_authorize)
+								.service(federation_link)
+								.service(federation_callback)
+								.service(get_pending_link)
+								.service(confirm_pending_link)
+								.service(list_identities)
+								.service(unlink_identity),
+						)
 						.service(
 							scope("/oauth")
 								.service(get_consent)
@@ -0,0 +1,92 @@
+use actix_web::{http::StatusCode, HttpResponse, ResponseError};
+use handlebars::RenderError;
+use paperclip::actix::api_v2_errors;
+use serde::Serialize;
+use thiserror::Error;
+use validator::ValidationErrors;
+use wither::{bson::oid::Error as ObjectIdError, mongodb::error::Error as MongoError, WitherError};
+
+use crate::auth::AuthErrors;
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+	error: String,
+}
+
+#[api_v2_errors(
+	code = 400,
+	description = "Wrong input, or the change would leave the organization without owners",
+	code = 401,
+	description = "Not logged in or expired session",
+	code = 403,
+	description = "Not a member of the organization, or the organization role is not high enough",
+	code = 404,
+	description = "Organization, member or invitation not found",
+	code = 409,
+	description = "The name is already taken or the user is already a member",
+	code = 500,
+	description = "Internal server error, could be a db connection error, email server error"
+)]
+#[derive(Error, Debug)]
+pub enum OrganizationErrors {
+	#[error("Organization not found")]
+	OrganizationNotFound,
+	#[error("Not a member of the organization")]
+	NotAMember,
+	#[error("The organization role is not high enough")]
+	Forbidden,
+	#[error("Member not found")]
+	MemberNotFound,
+	#[error("Invitation not found or expired")]
+	InvitationNotFound,
+	#[error("The invitation was sent to a different email")]
+	InvitationEmailMismatch,
+	#[error("The name is already taken")]
+	NameTaken,
+	#[error("Already a member of the organization")]
+	AlreadyMember,
+	#[error("An organization needs at least one owner")]
+	LastOwner,
+	#[error("Invalid id: {0}")]
+	InvalidId(#[from] ObjectIdError),
+	#[error("{0}")]
+	ValidationError(#[from] ValidationErrors),
+	#[error("Internal server error")]
+	DatabaseError(#[from] WitherError),
+	#[error("Internal server error")]
+	MongoError(#[from] MongoError),
+	#[error("{0}")]
+	HandlebarsError(#[from] RenderError),
+	#[error("{0}")]
+	AuthError(#[from] AuthErrors),
+}
+
+impl ResponseError for OrganizationErrors {
+	fn error_response(&self) -> HttpResponse {
+		if let Self::AuthError(e) = self {
+			return e.error_response();
+		}
+		let error_response = ErrorResponse {
+			error: self.to_string(),
+		};
+		HttpResponse::build(self.status_code()).json(error_response)
+	}
+
+	fn status_code(&self) -> StatusCode {
+		match self {
+			Self::OrganizationNotFound => StatusCode::NOT_FOUND,
+			Self::NotAMember => StatusCode::FORBIDDEN,
+			Self::Forbidden => StatusCode::FORBIDDEN,
+			Self::MemberNotFound => StatusCode::NOT_FOUND,
+			Self::InvitationNotFound => StatusCode::NOT_FOUND,
+			Self::InvitationEmailMismatch => StatusCode::FORBIDDEN,
+			Self::NameTaken => StatusCode::CONFLICT,
+			Self::AlreadyMember => StatusCode::CONFLICT,
+			Self::LastOwner => StatusCode::BAD_REQUEST,
+			Self::InvalidId(_) => StatusCode::BAD_REQUEST,
+			Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+			Self::AuthError(e) => e.status_code(),
+			_ => StatusCode::INTERNAL_SERVER_ERROR,
+		}
+	}
+}
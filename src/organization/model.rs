@@ -0,0 +1,123 @@
+use futures_util::StreamExt;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	prelude::*,
+	WitherError,
+};
+
+use crate::utils::hash_token;
+
+/// Seconds an invitation can be accepted for
+pub const INVITATION_LIFETIME: i64 = 7 * 24 * 60 * 60;
+
+/// Role of a member inside an organization, ordered by privilege
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "lowercase")]
+pub enum OrgRole {
+	Member,
+	Admin,
+	Owner,
+}
+
+impl Default for OrgRole {
+	fn default() -> Self {
+		Self::Member
+	}
+}
+
+/// An organization, users join it through invitations
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"name": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Organization {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// The unique organization name, used in the URLs and in the clients metadata
+	pub name: String,
+	pub display_name: Option<String>,
+}
+
+/// A user membership in an organization
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"organization": 1, "user": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Membership {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	pub organization: ObjectId,
+	pub user: ObjectId,
+	pub role: OrgRole,
+}
+
+/// A pending invitation to join an organization
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"token": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Invitation {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	pub organization: ObjectId,
+	/// The invited email, lowercase
+	pub email: String,
+	/// The role given when the invitation is accepted
+	pub role: OrgRole,
+	/// SHA-256 of the token sent by email, the token itself is never stored
+	pub token: String,
+	pub invited_by: ObjectId,
+	pub expires_at: i64,
+}
+
+impl Organization {
+	pub async fn find_by_name(db: &Database, name: &str) -> Result<Option<Self>, WitherError> {
+		Organization::find_one(db, doc! { "name": name }, None).await
+	}
+}
+
+impl Membership {
+	pub async fn find_membership(
+		db: &Database,
+		organization: &ObjectId,
+		user: &ObjectId,
+	) -> Result<Option<Self>, WitherError> {
+		Membership::find_one(db, doc! { "organization": *organization, "user": *user }, None).await
+	}
+
+	pub async fn count_owners(db: &Database, organization: &ObjectId) -> Result<u64, WitherError> {
+		let owners = Membership::collection(db)
+			.count_documents(doc! { "organization": *organization, "role": "owner" }, None)
+			.await?;
+		Ok(owners)
+	}
+}
+
+impl Invitation {
+	/// Finds an invitation by the token sent by email
+	pub async fn find_by_token(db: &Database, token: &str) -> Result<Option<Self>, WitherError> {
+		Invitation::find_one(db, doc! { "token": hash_token(token) }, None).await
+	}
+}
+
+/// Lists the organizations of the user along with their role
+pub async fn user_memberships(db: &Database, user: &ObjectId) -> Result<Vec<(Organization, OrgRole)>, WitherError> {
+	let mut cursor = Membership::find(db, doc! { "user": *user }, None).await?;
+	let mut memberships = Vec::new();
+	while let Some(membership) = cursor.next().await {
+		let membership = membership?;
+		if let Some(organization) = Organization::find_one(db, doc! { "_id": membership.organization }, None).await? {
+			memberships.push((organization, membership.role));
+		}
+	}
+	Ok(memberships)
+}
+
+/// Checks if the user is a member of at least one of the organizations, given by ID.
+///
+/// Names are not trusted, a freed or unused name can be taken by any user
+pub async fn is_member_of_any(db: &Database, user: &ObjectId, organizations: &[String]) -> Result<bool, WitherError> {
+	let memberships = user_memberships(db, user).await?;
+	Ok(
+		memberships
+			.iter()
+			.any(|(organization, _)| organization.id.map_or(false, |id| organizations.contains(&id.to_hex()))),
+	)
+}
@@ -0,0 +1,436 @@
+use actix_session::Session;
+use futures_util::StreamExt;
+use log::info;
+use paperclip::actix::{
+	api_v2_operation, delete, get, patch, post,
+	web::{Data, Json, Path},
+};
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database as MongoDatabase,
+	Model,
+};
+
+use crate::{
+	admin::DeletedResponse,
+	auth::{send_email_to_user, AuthErrors},
+	session::unix_now,
+	settings::{HANDLEBARS, ORGANIZATION_INVITATION_TEMPLATE_NAME},
+	user::{email_key, User},
+	utils::{generate_token, hash_token},
+};
+
+use super::{
+	user_memberships, Invitation, InvitationInput, InvitationView, JoinOrganizationInput, LeftOrganizationResponse,
+	MemberView, Membership, MembershipView, NewOrganizationInput, OrgRole, Organization, OrganizationErrors,
+	OrganizationView, UpdateMemberInput, INVITATION_LIFETIME,
+};
+
+/// Loads the logged in user
+async fn session_user(db: &MongoDatabase, session: &Session) -> Result<User, OrganizationErrors> {
+	User::user_from_session(db, session)
+		.await
+		.map_err(|e| AuthErrors::from(e).into())
+}
+
+/// Loads the organization and the membership of the user, rejects users below the given role
+async fn find_membership(
+	db: &MongoDatabase,
+	name: &str,
+	user: &User,
+	required: OrgRole,
+) -> Result<(Organization, Membership), OrganizationErrors> {
+	let organization = Organization::find_by_name(db, name)
+		.await?
+		.ok_or(OrganizationErrors::OrganizationNotFound)?;
+	// Safe to unwrap, both come from the db
+	let membership = Membership::find_membership(db, organization.id.as_ref().unwrap(), user.id.as_ref().unwrap())
+		.await?
+		.ok_or(OrganizationErrors::NotAMember)?;
+
+	if membership.role < required {
+		return Err(OrganizationErrors::Forbidden);
+	}
+
+	Ok((organization, membership))
+}
+
+/// Rejects changes to an owner membership that would leave the organization without owners
+async fn ensure_other_owner(db: &MongoDatabase, membership: &Membership) -> Result<(), OrganizationErrors> {
+	if membership.role == OrgRole::Owner && Membership::count_owners(db, &membership.organization).await? <= 1 {
+		return Err(OrganizationErrors::LastOwner);
+	}
+	Ok(())
+}
+
+/// ORGANIZATION Create
+///
+/// Creates an organization, the logged in user becomes its owner
+#[api_v2_operation]
+#[post("")]
+pub async fn create_organization(
+	db: Data<MongoDatabase>,
+	session: Session,
+	Json(new_organization_input): Json<NewOrganizationInput>,
+) -> Result<Json<MembershipView>, OrganizationErrors> {
+	new_organization_input.validate()?;
+	let user = session_user(&db, &session).await?;
+
+	if Organization::find_by_name(&db, &new_organization_input.name)
+		.await?
+		.is_some()
+	{
+		return Err(OrganizationErrors::NameTaken);
+	}
+
+	let NewOrganizationInput { name, display_name } = new_organization_input;
+	let mut organization = Organization {
+		id: None,
+		name,
+		display_name,
+	};
+	organization.save(&db, None).await?;
+
+	let mut membership = Membership {
+		id: None,
+		organization: organization.id.clone().unwrap(),
+		user: user.id.clone().unwrap(),
+		role: OrgRole::Owner,
+	};
+	membership.save(&db, None).await?;
+
+	Ok(Json(MembershipView {
+		organization: organization.into(),
+		role: OrgRole::Owner,
+	}))
+}
+
+/// ORGANIZATION List mine
+///
+/// Lists the organizations of the logged in user
+#[api_v2_operation]
+#[get("")]
+pub async fn list_organizations(
+	db: Data<MongoDatabase>,
+	session: Session,
+) -> Result<Json<Vec<MembershipView>>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+
+	let memberships = user_memberships(&db, user.id.as_ref().unwrap()).await?;
+
+	Ok(Json(
+		memberships
+			.into_iter()
+			.map(|(organization, role)| MembershipView {
+				organization: organization.into(),
+				role,
+			})
+			.collect(),
+	))
+}
+
+/// ORGANIZATION Get
+#[api_v2_operation]
+#[get("/{name}")]
+pub async fn get_organization(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+) -> Result<Json<OrganizationView>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+	let (organization, _) = find_membership(&db, &name, &user, OrgRole::Member).await?;
+
+	Ok(Json(organization.into()))
+}
+
+/// ORGANIZATION Delete
+///
+/// Deletes the organization along with its memberships and invitations, owners only
+#[api_v2_operation]
+#[delete("/{name}")]
+pub async fn delete_organization(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+) -> Result<Json<DeletedResponse>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+	let (organization, _) = find_membership(&db, &name, &user, OrgRole::Owner).await?;
+
+	let id = organization.id.clone().unwrap();
+	Membership::collection(&db)
+		.delete_many(doc! { "organization": id }, None)
+		.await?;
+	Invitation::collection(&db)
+		.delete_many(doc! { "organization": id }, None)
+		.await?;
+	organization.delete(&db).await?;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ORGANIZATION List members
+#[api_v2_operation]
+#[get("/{name}/members")]
+pub async fn list_members(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+) -> Result<Json<Vec<MemberView>>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+	let (organization, _) = find_membership(&db, &name, &user, OrgRole::Member).await?;
+
+	let mut cursor = Membership::find(&db, doc! { "organization": organization.id.unwrap() }, None).await?;
+	let mut members = Vec::new();
+	while let Some(membership) = cursor.next().await {
+		let membership = membership?;
+		if let Some(member) = User::find_by_id(&db, &membership.user).await? {
+			members.push(MemberView {
+				user_id: member.id,
+				email: member.email_scope.email,
+				preferred_username: member.profile_scope.preferred_username,
+				role: membership.role,
+			});
+		}
+	}
+
+	Ok(Json(members))
+}
+
+/// ORGANIZATION Update member
+///
+/// Changes the role of a member, admins manage members and admins, owners manage everyone
+#[api_v2_operation]
+#[patch("/{name}/members/{user_id}")]
+pub async fn update_member(
+	db: Data<MongoDatabase>,
+	session: Session,
+	path: Path<(String, String)>,
+	Json(update_member_input): Json<UpdateMemberInput>,
+) -> Result<Json<MemberView>, OrganizationErrors> {
+	let (name, user_id) = path.into_inner();
+	let user = session_user(&db, &session).await?;
+	let (organization, own_membership) = find_membership(&db, &name, &user, OrgRole::Admin).await?;
+
+	let member_id = ObjectId::parse_str(&user_id)?;
+	let mut membership = Membership::find_membership(&db, organization.id.as_ref().unwrap(), &member_id)
+		.await?
+		.ok_or(OrganizationErrors::MemberNotFound)?;
+
+	// Only owners can touch owners
+	let role = update_member_input.role;
+	if (membership.role == OrgRole::Owner || role == OrgRole::Owner) && own_membership.role != OrgRole::Owner {
+		return Err(OrganizationErrors::Forbidden);
+	}
+	if role != OrgRole::Owner {
+		ensure_other_owner(&db, &membership).await?;
+	}
+
+	membership.role = role;
+	membership.save(&db, None).await?;
+
+	let member = User::find_by_id(&db, &member_id)
+		.await?
+		.ok_or(OrganizationErrors::MemberNotFound)?;
+
+	Ok(Json(MemberView {
+		user_id: member.id,
+		email: member.email_scope.email,
+		preferred_username: member.profile_scope.preferred_username,
+		role,
+	}))
+}
+
+/// ORGANIZATION Remove member
+///
+/// Removes a member, only owners can remove owners
+#[api_v2_operation]
+#[delete("/{name}/members/{user_id}")]
+pub async fn remove_member(
+	db: Data<MongoDatabase>,
+	session: Session,
+	path: Path<(String, String)>,
+) -> Result<Json<DeletedResponse>, OrganizationErrors> {
+	let (name, user_id) = path.into_inner();
+	let user = session_user(&db, &session).await?;
+	let (organization, own_membership) = find_membership(&db, &name, &user, OrgRole::Admin).await?;
+
+	let member_id = ObjectId::parse_str(&user_id)?;
+	let membership = Membership::find_membership(&db, organization.id.as_ref().unwrap(), &member_id)
+		.await?
+		.ok_or(OrganizationErrors::MemberNotFound)?;
+
+	if membership.role == OrgRole::Owner && own_membership.role != OrgRole::Owner {
+		return Err(OrganizationErrors::Forbidden);
+	}
+	ensure_other_owner(&db, &membership).await?;
+
+	membership.delete(&db).await?;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ORGANIZATION Leave
+///
+/// Removes the logged in user from the organization, the last owner cannot leave
+#[api_v2_operation]
+#[post("/{name}/leave")]
+pub async fn leave_organization(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+) -> Result<Json<LeftOrganizationResponse>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+	let (_, membership) = find_membership(&db, &name, &user, OrgRole::Member).await?;
+
+	ensure_other_owner(&db, &membership).await?;
+	membership.delete(&db).await?;
+
+	Ok(Json(LeftOrganizationResponse { left: true }))
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+struct InvitationEMailData {
+	pub organization: String,
+	pub inviter: String,
+	pub token: String,
+}
+
+/// ORGANIZATION Invite
+///
+/// Sends an invitation by email, only owners can invite owners
+#[api_v2_operation]
+#[post("/{name}/invitations")]
+pub async fn create_invitation(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+	Json(invitation_input): Json<InvitationInput>,
+) -> Result<Json<InvitationView>, OrganizationErrors> {
+	invitation_input.validate()?;
+	let user = session_user(&db, &session).await?;
+	let (organization, own_membership) = find_membership(&db, &name, &user, OrgRole::Admin).await?;
+
+	if invitation_input.role == OrgRole::Owner && own_membership.role != OrgRole::Owner {
+		return Err(OrganizationErrors::Forbidden);
+	}
+
+	let token = generate_token();
+	let mut invitation = Invitation {
+		id: None,
+		organization: organization.id.clone().unwrap(),
+		email: email_key(&invitation_input.email),
+		role: invitation_input.role,
+		token: hash_token(&token),
+		invited_by: user.id.clone().unwrap(),
+		expires_at: unix_now() + INVITATION_LIFETIME,
+	};
+	invitation.save(&db, None).await?;
+
+	let invitation_data = InvitationEMailData {
+		organization: organization.display_name.clone().unwrap_or(organization.name),
+		inviter: user.display_name(),
+		token,
+	};
+	let html_mail = HANDLEBARS.render(ORGANIZATION_INVITATION_TEMPLATE_NAME, &invitation_data)?;
+	let email_title = format!("You have been invited to join {}", invitation_data.organization);
+
+	send_email_to_user(&invitation.email, &invitation.email, &email_title, &html_mail)?;
+
+	Ok(Json(invitation.into()))
+}
+
+/// ORGANIZATION List invitations
+///
+/// Lists the pending invitations, admins only
+#[api_v2_operation]
+#[get("/{name}/invitations")]
+pub async fn list_invitations(
+	db: Data<MongoDatabase>,
+	session: Session,
+	name: Path<String>,
+) -> Result<Json<Vec<InvitationView>>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+	let (organization, _) = find_membership(&db, &name, &user, OrgRole::Admin).await?;
+
+	let filter = doc! { "organization": organization.id.unwrap(), "expires_at": { "$gt": unix_now() } };
+	let mut cursor = Invitation::find(&db, filter, None).await?;
+	let mut invitations = Vec::new();
+	while let Some(invitation) = cursor.next().await {
+		invitations.push(invitation?.into());
+	}
+
+	Ok(Json(invitations))
+}
+
+/// ORGANIZATION Revoke invitation
+#[api_v2_operation]
+#[delete("/{name}/invitations/{id}")]
+pub async fn revoke_invitation(
+	db: Data<MongoDatabase>,
+	session: Session,
+	path: Path<(String, String)>,
+) -> Result<Json<DeletedResponse>, OrganizationErrors> {
+	let (name, id) = path.into_inner();
+	let user = session_user(&db, &session).await?;
+	let (organization, _) = find_membership(&db, &name, &user, OrgRole::Admin).await?;
+
+	let id = ObjectId::parse_str(&id)?;
+	let invitation = Invitation::find_one(&db, doc! { "_id": id, "organization": organization.id.unwrap() }, None)
+		.await?
+		.ok_or(OrganizationErrors::InvitationNotFound)?;
+	invitation.delete(&db).await?;
+
+	Ok(Json(DeletedResponse { deleted: true }))
+}
+
+/// ORGANIZATION Join
+///
+/// Accepts an invitation, it must have been sent to the verified email of the logged in user
+#[api_v2_operation]
+#[post("/join")]
+pub async fn join_organization(
+	db: Data<MongoDatabase>,
+	session: Session,
+	Json(join_organization_input): Json<JoinOrganizationInput>,
+) -> Result<Json<MembershipView>, OrganizationErrors> {
+	let user = session_user(&db, &session).await?;
+
+	let invitation = Invitation::find_by_token(&db, &join_organization_input.token)
+		.await?
+		.filter(|invitation| invitation.expires_at > unix_now())
+		.ok_or(OrganizationErrors::InvitationNotFound)?;
+
+	if !user.email_scope.email_verified || email_key(&user.email_scope.email) != invitation.email {
+		return Err(OrganizationErrors::InvitationEmailMismatch);
+	}
+
+	let organization = Organization::find_one(&db, doc! { "_id": invitation.organization }, None)
+		.await?
+		.ok_or(OrganizationErrors::OrganizationNotFound)?;
+	let user_id = user.id.clone().unwrap();
+	if Membership::find_membership(&db, &invitation.organization, &user_id)
+		.await?
+		.is_some()
+	{
+		invitation.delete(&db).await?;
+		return Err(OrganizationErrors::AlreadyMember);
+	}
+
+	let mut membership = Membership {
+		id: None,
+		organization: invitation.organization,
+		user: user_id,
+		role: invitation.role,
+	};
+	membership.save(&db, None).await?;
+	invitation.delete(&db).await?;
+
+	info!("User {:?} joined organization {:?}", user.id, organization.name);
+
+	Ok(Json(MembershipView {
+		organization: organization.into(),
+		role: membership.role,
+	}))
+}
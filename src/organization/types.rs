@@ -0,0 +1,117 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+use wither::bson::oid::ObjectId;
+
+use crate::utils::serialize_object_id;
+
+use super::{Invitation, OrgRole, Organization};
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct NewOrganizationInput {
+	/// The unique organization name, lowercase letters, digits and dashes
+	#[validate(length(min = 2, max = 64), custom = "validate_organization_name")]
+	pub name: String,
+	pub display_name: Option<String>,
+}
+
+fn validate_organization_name(name: &str) -> Result<(), validator::ValidationError> {
+	if name
+		.chars()
+		.all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+	{
+		Ok(())
+	} else {
+		Err(validator::ValidationError::new("organization_name"))
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationView {
+	/// The ID to list in the `organizations` metadata of the clients
+	pub id: String,
+	pub name: String,
+	pub display_name: Option<String>,
+}
+
+impl From<Organization> for OrganizationView {
+	fn from(organization: Organization) -> Self {
+		Self {
+			// Safe to unwrap, the organization comes from the db
+			id: organization.id.unwrap().to_hex(),
+			name: organization.name,
+			display_name: organization.display_name,
+		}
+	}
+}
+
+/// An organization of the logged in user
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct MembershipView {
+	pub organization: OrganizationView,
+	pub role: OrgRole,
+}
+
+/// A member of an organization
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct MemberView {
+	#[serde(serialize_with = "serialize_object_id")]
+	pub user_id: Option<ObjectId>,
+	pub email: String,
+	pub preferred_username: Option<String>,
+	pub role: OrgRole,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateMemberInput {
+	pub role: OrgRole,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema, Validate)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationInput {
+	#[validate(email)]
+	pub email: String,
+	#[serde(default)]
+	pub role: OrgRole,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct InvitationView {
+	#[serde(serialize_with = "serialize_object_id")]
+	pub id: Option<ObjectId>,
+	pub email: String,
+	pub role: OrgRole,
+	/// Expiration unix timestamp
+	pub expires_at: i64,
+}
+
+impl From<Invitation> for InvitationView {
+	fn from(invitation: Invitation) -> Self {
+		Self {
+			id: invitation.id,
+			email: invitation.email,
+			role: invitation.role,
+			expires_at: invitation.expires_at,
+		}
+	}
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+pub struct JoinOrganizationInput {
+	/// The token received by email
+	pub token: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct LeftOrganizationResponse {
+	/// This should always be true
+	pub left: bool,
+}
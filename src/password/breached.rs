@@ -0,0 +1,33 @@
+use std::{fs, io::ErrorKind, path::Path};
+
+use sha1::{Digest, Sha1};
+
+use super::PasswordPolicyErrors;
+
+/// Checks the password against a local breached passwords corpus.
+///
+/// The corpus is a directory of HIBP range files: `<first 5 hex chars of the SHA-1>.txt`,
+/// each line being `<remaining 35 hex chars>:<count>`.
+pub fn is_breached(corpus_path: &str, password: &str) -> Result<bool, PasswordPolicyErrors> {
+	let hash: String = Sha1::digest(password.as_bytes())
+		.iter()
+		.map(|byte| format!("{:02X}", byte))
+		.collect();
+	let (prefix, suffix) = hash.split_at(5);
+
+	let range_file = Path::new(corpus_path).join(format!("{}.txt", prefix));
+	let range = match fs::read_to_string(range_file) {
+		Ok(range) => range,
+		// No range file, no breached password with this prefix
+		Err(e) if e.kind() == ErrorKind::NotFound => return Ok(false),
+		Err(e) => return Err(e.into()),
+	};
+
+	Ok(range.lines().any(|line| {
+		line
+			.split(':')
+			.next()
+			.map(|line_suffix| line_suffix.trim().eq_ignore_ascii_case(suffix))
+			.unwrap_or(false)
+	}))
+}
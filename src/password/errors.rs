@@ -0,0 +1,48 @@
+use std::{fmt, io::Error as IOError};
+
+use serde::Serialize;
+use thiserror::Error;
+
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "rule", rename_all = "camelCase")]
+/// A violated password policy rule
+pub enum PasswordViolation {
+	TooShort { min: usize },
+	TooLong { max: usize },
+	MissingLowercase,
+	MissingUppercase,
+	MissingDigit,
+	MissingSymbol,
+	TooWeak { score: u8, min_score: u8 },
+	ContainsPersonalInfo,
+	Breached,
+}
+
+impl fmt::Display for PasswordViolation {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::TooShort { min } => write!(f, "The password must be at least {} characters long", min),
+			Self::TooLong { max } => write!(f, "The password must be at most {} characters long", max),
+			Self::MissingLowercase => write!(f, "The password must contain a lowercase letter"),
+			Self::MissingUppercase => write!(f, "The password must contain an uppercase letter"),
+			Self::MissingDigit => write!(f, "The password must contain a digit"),
+			Self::MissingSymbol => write!(f, "The password must contain a symbol"),
+			Self::TooWeak { score, min_score } => write!(
+				f,
+				"The password is too easy to guess (strength {} out of 4, at least {} required)",
+				score, min_score
+			),
+			Self::ContainsPersonalInfo => write!(f, "The password must not contain your email or username"),
+			Self::Breached => write!(f, "The password appeared in a data breach, choose a different one"),
+		}
+	}
+}
+
+#[derive(Error, Debug)]
+/// Possible password policy errors
+pub enum PasswordPolicyErrors {
+	#[error("The password does not satisfy the password policy")]
+	Violations(Vec<PasswordViolation>),
+	#[error("Could not read the breached passwords: {0}")]
+	BreachedCorpusError(#[from] IOError),
+}
@@ -0,0 +1,7 @@
+pub mod breached;
+pub mod errors;
+pub mod policy;
+
+pub use breached::*;
+pub use errors::*;
+pub use policy::*;
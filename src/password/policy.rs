@@ -0,0 +1,163 @@
+use log::error;
+use zxcvbn::zxcvbn;
+
+use crate::settings::{PasswordSettings, APP_SETTINGS};
+
+use super::{is_breached, PasswordPolicyErrors, PasswordViolation};
+
+/// Personal info shorter than this is too generic to be rejected
+const MIN_PERSONAL_INFO_LENGTH: usize = 3;
+
+/// Checks the password against the configured policy, reporting every violated rule.
+///
+/// `personal_info` are the user's email, username and similar values the password must not contain.
+pub fn check_password_policy(password: &str, personal_info: &[&str]) -> Result<(), PasswordPolicyErrors> {
+	check_password_policy_with(&APP_SETTINGS.password, password, personal_info)
+}
+
+fn check_password_policy_with(
+	settings: &PasswordSettings,
+	password: &str,
+	personal_info: &[&str],
+) -> Result<(), PasswordPolicyErrors> {
+	let mut violations = Vec::new();
+
+	let length = password.chars().count();
+	if length < settings.min {
+		violations.push(PasswordViolation::TooShort { min: settings.min });
+	}
+	if length > settings.max {
+		// Scoring costs grow with the length, an oversized password is rejected before anything else runs
+		return Err(PasswordPolicyErrors::Violations(vec![PasswordViolation::TooLong {
+			max: settings.max,
+		}]));
+	}
+	if settings.lowercase && !password.chars().any(char::is_lowercase) {
+		violations.push(PasswordViolation::MissingLowercase);
+	}
+	if settings.uppercase && !password.chars().any(char::is_uppercase) {
+		violations.push(PasswordViolation::MissingUppercase);
+	}
+	if settings.digit && !password.chars().any(|c| c.is_ascii_digit()) {
+		violations.push(PasswordViolation::MissingDigit);
+	}
+	if settings.symbol && !password.chars().any(|c| !c.is_alphanumeric() && !c.is_whitespace()) {
+		violations.push(PasswordViolation::MissingSymbol);
+	}
+
+	// The email local part is checked on its own too
+	let mut user_inputs: Vec<&str> = Vec::new();
+	for info in personal_info {
+		user_inputs.push(info);
+		if let Some((local_part, _)) = info.split_once('@') {
+			user_inputs.push(local_part);
+		}
+	}
+
+	let lowercase_password = password.to_lowercase();
+	if user_inputs
+		.iter()
+		.filter(|info| info.chars().count() >= MIN_PERSONAL_INFO_LENGTH)
+		.any(|info| lowercase_password.contains(&info.to_lowercase()))
+	{
+		violations.push(PasswordViolation::ContainsPersonalInfo);
+	}
+
+	// Blank passwords can't be scored, they are already rejected by the length rule
+	if let Ok(entropy) = zxcvbn(password, &user_inputs) {
+		if entropy.score() < settings.score {
+			violations.push(PasswordViolation::TooWeak {
+				score: entropy.score(),
+				min_score: settings.score,
+			});
+		}
+	}
+
+	if let Some(corpus_path) = &settings.breached {
+		match is_breached(corpus_path, password) {
+			Ok(true) => violations.push(PasswordViolation::Breached),
+			Ok(false) => {}
+			Err(e) => {
+				error!("{:?}", e);
+				return Err(e);
+			}
+		}
+	}
+
+	if violations.is_empty() {
+		Ok(())
+	} else {
+		Err(PasswordPolicyErrors::Violations(violations))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn password_settings() -> PasswordSettings {
+		PasswordSettings {
+			min: 8,
+			max: 64,
+			lowercase: true,
+			uppercase: true,
+			digit: true,
+			symbol: true,
+			score: 3,
+			breached: None,
+		}
+	}
+
+	fn violations(password: &str, personal_info: &[&str]) -> Vec<PasswordViolation> {
+		match check_password_policy_with(&password_settings(), password, personal_info) {
+			Ok(()) => Vec::new(),
+			Err(PasswordPolicyErrors::Violations(violations)) => violations,
+			Err(e) => panic!("Unexpected error: {:?}", e),
+		}
+	}
+
+	#[test]
+	fn accepts_a_strong_password() {
+		assert!(violations("Correct-Horse-Battery-9", &[]).is_empty());
+	}
+
+	#[test]
+	fn reports_every_violated_rule() {
+		let violations = violations("abc", &[]);
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, PasswordViolation::TooShort { min: 8 })));
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, PasswordViolation::MissingUppercase)));
+		assert!(violations.iter().any(|v| matches!(v, PasswordViolation::MissingDigit)));
+		assert!(violations.iter().any(|v| matches!(v, PasswordViolation::MissingSymbol)));
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, PasswordViolation::TooWeak { .. })));
+	}
+
+	#[test]
+	fn rejects_oversized_passwords_alone() {
+		let password = "Aa1!".repeat(1000);
+		let violations = violations(&password, &[]);
+		assert_eq!(violations.len(), 1);
+		assert!(matches!(violations[0], PasswordViolation::TooLong { max: 64 }));
+	}
+
+	#[test]
+	fn rejects_personal_info() {
+		let violations = violations("Xq7!JohnDoe-Trombone", &["johndoe@example.com"]);
+		assert!(violations
+			.iter()
+			.any(|v| matches!(v, PasswordViolation::ContainsPersonalInfo)));
+	}
+
+	#[test]
+	fn ignores_short_personal_info() {
+		let violations = violations("Correct-Horse-Battery-9", &["co@example.com"]);
+		assert!(!violations
+			.iter()
+			.any(|v| matches!(v, PasswordViolation::ContainsPersonalInfo)));
+	}
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeSet;
+
+use futures_util::StreamExt;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use wither::{bson::doc, mongodb::Database, Model, WitherError};
+
+use crate::user::User;
+
+use super::{Group, Role, ADMIN_ROLE};
+
+/// The effective roles and permissions of a user
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+pub struct UserAccess {
+	/// The roles given directly and through groups
+	pub roles: Vec<String>,
+	/// The permissions granted by the roles
+	pub permissions: Vec<String>,
+}
+
+impl UserAccess {
+	/// Resolves the user roles, directly assigned and inherited from groups, and their permissions
+	pub async fn resolve(db: &Database, user: &User) -> Result<Self, WitherError> {
+		let mut roles: BTreeSet<String> = user.roles.iter().cloned().collect();
+		if !user.groups.is_empty() {
+			let mut cursor = Group::find(db, doc! { "name": { "$in": user.groups.clone() } }, None).await?;
+			while let Some(group) = cursor.next().await {
+				roles.extend(group?.roles);
+			}
+		}
+
+		let mut permissions = BTreeSet::new();
+		if !roles.is_empty() {
+			let names: Vec<String> = roles.iter().cloned().collect();
+			let mut cursor = Role::find(db, doc! { "name": { "$in": names } }, None).await?;
+			while let Some(role) = cursor.next().await {
+				permissions.extend(role?.permissions);
+			}
+		}
+
+		Ok(Self {
+			roles: roles.into_iter().collect(),
+			permissions: permissions.into_iter().collect(),
+		})
+	}
+
+	pub fn is_admin(&self) -> bool {
+		self.roles.iter().any(|role| role == ADMIN_ROLE)
+	}
+
+	/// Admins have every permission
+	pub fn has_permission(&self, permission: &str) -> bool {
+		self.is_admin() || self.permissions.iter().any(|granted| granted == permission)
+	}
+}
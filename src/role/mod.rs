@@ -0,0 +1,5 @@
+pub mod access;
+pub mod model;
+
+pub use access::*;
+pub use model::*;
@@ -0,0 +1,115 @@
+use std::collections::HashSet;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::{options::FindOptions, Database},
+	prelude::*,
+	WitherError,
+};
+
+/// Built-in role granting every permission, it needs no document
+pub const ADMIN_ROLE: &str = "admin";
+
+/// Permissions checked by the admin API
+pub const USERS_READ_PERMISSION: &str = "odysseus:users:read";
+pub const USERS_WRITE_PERMISSION: &str = "odysseus:users:write";
+pub const ROLES_READ_PERMISSION: &str = "odysseus:roles:read";
+pub const ROLES_WRITE_PERMISSION: &str = "odysseus:roles:write";
+pub const METRICS_READ_PERMISSION: &str = "odysseus:metrics:read";
+pub const AUDIT_READ_PERMISSION: &str = "odysseus:audit:read";
+
+/// Models referenced by their unique name
+pub trait Named {
+	fn name(&self) -> &str;
+}
+
+/// A permission, granted to users through roles
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"name": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Permission {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// The unique permission name, e.g. `odysseus:users:read`
+	pub name: String,
+	pub description: Option<String>,
+}
+
+/// A named set of permissions
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"name": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Role {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// The unique role name
+	pub name: String,
+	pub description: Option<String>,
+	/// The names of the granted permissions
+	#[serde(default)]
+	pub permissions: Vec<String>,
+}
+
+/// A group of users, its members get all its roles
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"name": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct Group {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// The unique group name
+	pub name: String,
+	pub description: Option<String>,
+	/// The names of the roles given to the members
+	#[serde(default)]
+	pub roles: Vec<String>,
+}
+
+impl Named for Permission {
+	fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+impl Named for Role {
+	fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+impl Named for Group {
+	fn name(&self) -> &str {
+		&self.name
+	}
+}
+
+/// Finds a document by its unique name
+pub async fn find_by_name<M: Model>(db: &Database, name: &str) -> Result<Option<M>, WitherError> {
+	M::find_one(db, doc! { "name": name }, None).await
+}
+
+/// Lists all the documents, sorted by name
+pub async fn find_all<M: Model>(db: &Database) -> Result<Vec<M>, WitherError> {
+	let options = FindOptions::builder().sort(doc! { "name": 1 }).build();
+	let mut cursor = M::find(db, None, options).await?;
+
+	let mut models = Vec::new();
+	while let Some(model) = cursor.next().await {
+		models.push(model?);
+	}
+	Ok(models)
+}
+
+/// Returns the given names without a matching document
+pub async fn find_missing<M: Model + Named>(db: &Database, names: &[String]) -> Result<Vec<String>, WitherError> {
+	if names.is_empty() {
+		return Ok(Vec::new());
+	}
+
+	let mut cursor = M::find(db, doc! { "name": { "$in": names.to_vec() } }, None).await?;
+	let mut found = HashSet::new();
+	while let Some(model) = cursor.next().await {
+		found.insert(model?.name().to_string());
+	}
+
+	Ok(names.iter().filter(|name| !found.contains(*name)).cloned().collect())
+}
@@ -0,0 +1,77 @@
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use super::{unix_now, SessionState, SessionStore, SessionStoreErrors};
+
+/// Name used only to drive the private cookie jar encryption
+const PAYLOAD_NAME: &str = "session";
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CookiePayload {
+	state: SessionState,
+	expires_at: i64,
+}
+
+/// Client side session store, the whole state is encrypted and signed into the session cookie.
+///
+/// Nothing is kept on the server, so sessions can be neither listed nor revoked, and the state must fit in a cookie.
+pub struct CookieSessionStore {
+	key: Key,
+}
+
+impl CookieSessionStore {
+	pub fn new(secret: &[u8]) -> Self {
+		Self {
+			key: Key::derive_from(secret),
+		}
+	}
+}
+
+#[async_trait]
+impl SessionStore for CookieSessionStore {
+	async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionStoreErrors> {
+		let mut jar = CookieJar::new();
+		jar.add_original(Cookie::new(PAYLOAD_NAME, key.to_string()));
+
+		// Tampered or undecryptable cookies are treated as missing sessions
+		let payload = match jar.private(&self.key).get(PAYLOAD_NAME) {
+			Some(cookie) => serde_json::from_str::<CookiePayload>(cookie.value())?,
+			None => return Ok(None),
+		};
+
+		if payload.expires_at > unix_now() {
+			Ok(Some(payload.state))
+		} else {
+			Ok(None)
+		}
+	}
+
+	async fn save(&self, _key: Option<&str>, state: SessionState, ttl: i64) -> Result<String, SessionStoreErrors> {
+		let payload = CookiePayload {
+			state,
+			expires_at: unix_now() + ttl,
+		};
+
+		let mut jar = CookieJar::new();
+		jar
+			.private_mut(&self.key)
+			.add(Cookie::new(PAYLOAD_NAME, serde_json::to_string(&payload)?));
+
+		// Safe to unwrap, it has just been added
+		Ok(jar.get(PAYLOAD_NAME).unwrap().value().to_string())
+	}
+
+	async fn delete(&self, _key: &str) -> Result<(), SessionStoreErrors> {
+		// The cookie removal is handled by the middleware
+		Ok(())
+	}
+
+	async fn user_sessions(&self, _user_id: &str) -> Result<Vec<String>, SessionStoreErrors> {
+		Err(SessionStoreErrors::Unsupported)
+	}
+
+	async fn delete_user_sessions(&self, _user_id: &str) -> Result<(), SessionStoreErrors> {
+		Err(SessionStoreErrors::Unsupported)
+	}
+}
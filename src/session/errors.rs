@@ -0,0 +1,23 @@
+use actix_web::Error as ActixError;
+use redis::RedisError;
+use serde_json::Error as JSONError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SessionErrors {
+	#[error("{0}")]
+	ActixError(#[from] ActixError),
+	#[error("Session expired")]
+	SessionExpired,
+}
+
+#[derive(Error, Debug)]
+/// Possible session store errors
+pub enum SessionStoreErrors {
+	#[error("Redis error: {0}")]
+	RedisError(#[from] RedisError),
+	#[error("Session state is malformed: {0}")]
+	SerializationError(#[from] JSONError),
+	#[error("The configured session store does not support this operation")]
+	Unsupported,
+}
@@ -0,0 +1,66 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+
+use super::{generate_session_key, session_owner, unix_now, SessionState, SessionStore, SessionStoreErrors};
+
+/// In-process session store, sessions are lost on restart and are not shared between instances.
+///
+/// Meant for local development, tests and single instance deployments.
+#[derive(Default)]
+pub struct MemorySessionStore {
+	/// Session key -> (state, expiration timestamp)
+	sessions: RwLock<HashMap<String, (SessionState, i64)>>,
+}
+
+impl MemorySessionStore {
+	/// Drops all the expired sessions
+	fn evict_expired(sessions: &mut HashMap<String, (SessionState, i64)>) {
+		let now = unix_now();
+		sessions.retain(|_, (_, expires_at)| *expires_at > now);
+	}
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+	async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionStoreErrors> {
+		let sessions = self.sessions.read().expect("Session store lock poisoned");
+		let now = unix_now();
+
+		Ok(
+			sessions
+				.get(key)
+				.filter(|(_, expires_at)| *expires_at > now)
+				.map(|(state, _)| state.clone()),
+		)
+	}
+
+	async fn save(&self, key: Option<&str>, state: SessionState, ttl: i64) -> Result<String, SessionStoreErrors> {
+		let mut sessions = self.sessions.write().expect("Session store lock poisoned");
+		Self::evict_expired(&mut sessions);
+
+		let key = key.map(String::from).unwrap_or_else(generate_session_key);
+		sessions.insert(key.clone(), (state, unix_now() + ttl));
+
+		Ok(key)
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), SessionStoreErrors> {
+		let mut sessions = self.sessions.write().expect("Session store lock poisoned");
+		sessions.remove(key);
+		Ok(())
+	}
+
+	async fn user_sessions(&self, user_id: &str) -> Result<Vec<String>, SessionStoreErrors> {
+		let sessions = self.sessions.read().expect("Session store lock poisoned");
+		let now = unix_now();
+
+		Ok(
+			sessions
+				.iter()
+				.filter(|(_, (state, expires_at))| *expires_at > now && session_owner(state).as_deref() == Some(user_id))
+				.map(|(key, _)| key.clone())
+				.collect(),
+		)
+	}
+}
@@ -0,0 +1,149 @@
+use std::rc::Rc;
+
+use actix_session::{Session, SessionStatus};
+use actix_web::{
+	cookie::{time::Duration, Cookie},
+	dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+	error::ErrorInternalServerError,
+	Error,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+use log::error;
+
+use crate::settings::APP_SETTINGS;
+
+use super::SharedSessionStore;
+
+/// Session middleware, loads and persists the `actix_session::Session` state through the configured store
+pub struct SessionMiddleware {
+	store: SharedSessionStore,
+}
+
+impl SessionMiddleware {
+	pub fn new(store: SharedSessionStore) -> Self {
+		Self { store }
+	}
+}
+
+impl<S, B> Transform<S, ServiceRequest> for SessionMiddleware
+where
+	S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+	B: 'static,
+{
+	type Response = ServiceResponse<B>;
+	type Error = Error;
+	type Transform = InnerSessionMiddleware<S>;
+	type InitError = ();
+	type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+	fn new_transform(&self, service: S) -> Self::Future {
+		ready(Ok(InnerSessionMiddleware {
+			service: Rc::new(service),
+			store: self.store.clone(),
+		}))
+	}
+}
+
+pub struct InnerSessionMiddleware<S> {
+	service: Rc<S>,
+	store: SharedSessionStore,
+}
+
+impl<S, B> Service<ServiceRequest> for InnerSessionMiddleware<S>
+where
+	S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+	B: 'static,
+{
+	type Response = ServiceResponse<B>;
+	type Error = Error;
+	type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+	forward_ready!(service);
+
+	fn call(&self, mut req: ServiceRequest) -> Self::Future {
+		let service = Rc::clone(&self.service);
+		let store = self.store.clone();
+
+		Box::pin(async move {
+			let session_settings = &APP_SETTINGS.session;
+
+			// Load the session state from the store
+			let mut key = req
+				.cookie(&session_settings.cookie.name)
+				.map(|cookie| cookie.value().to_string());
+			let state = match &key {
+				Some(key) => store.load(key).await.map_err(|e| {
+					error!("{:?}", e);
+					ErrorInternalServerError(e)
+				})?,
+				None => None,
+			};
+			// Unknown or expired keys are never reused
+			if state.is_none() {
+				key = None;
+			}
+			Session::set_session(&mut req, state.unwrap_or_default());
+
+			let mut res = service.call(req).await?;
+
+			let (status, state) = Session::get_changes(&mut res);
+			let ttl = session_settings.timeout.absolute;
+			let new_key = match status {
+				SessionStatus::Unchanged => None,
+				SessionStatus::Changed => Some(store.save(key.as_deref(), state.collect(), ttl).await),
+				SessionStatus::Renewed => {
+					if let Some(key) = &key {
+						store.delete(key).await.map_err(ErrorInternalServerError)?;
+					}
+					Some(store.save(None, state.collect(), ttl).await)
+				}
+				SessionStatus::Purged => {
+					if let Some(key) = &key {
+						store.delete(key).await.map_err(ErrorInternalServerError)?;
+					}
+					res
+						.response_mut()
+						.add_removal_cookie(&session_cookie(String::new()))
+						.map_err(ErrorInternalServerError)?;
+					None
+				}
+			};
+
+			if let Some(new_key) = new_key {
+				let new_key = new_key.map_err(|e| {
+					error!("{:?}", e);
+					ErrorInternalServerError(e)
+				})?;
+				res
+					.response_mut()
+					.add_cookie(&session_cookie(new_key))
+					.map_err(ErrorInternalServerError)?;
+			}
+
+			Ok(res)
+		})
+	}
+}
+
+/// Builds the session cookie according to the session settings
+fn session_cookie(value: String) -> Cookie<'static> {
+	let cookie_settings = &APP_SETTINGS.session.cookie;
+
+	let mut cookie = Cookie::build(cookie_settings.name.clone(), value)
+		.path(cookie_settings.path.clone())
+		// Send the cookie only over HTTPS when configured
+		.secure(cookie_settings.secure)
+		// Don't allow the cookie to be accessed from javascript
+		.http_only(true)
+		// allow the cookie only from the current domain or with safe methods
+		.same_site(cookie_settings.samesite.into())
+		// The session cannot outlive its absolute lifetime
+		.max_age(Duration::seconds(APP_SETTINGS.session.timeout.absolute))
+		.finish();
+
+	if let Some(domain) = &cookie_settings.domain {
+		cookie.set_domain(domain.clone());
+	}
+
+	cookie
+}
@@ -0,0 +1,15 @@
+pub mod cookie_store;
+pub mod errors;
+pub mod memory_store;
+pub mod middleware;
+pub mod redis_store;
+pub mod state;
+pub mod store;
+
+pub use cookie_store::*;
+pub use errors::*;
+pub use memory_store::*;
+pub use middleware::*;
+pub use redis_store::*;
+pub use state::*;
+pub use store::*;
@@ -0,0 +1,86 @@
+use async_trait::async_trait;
+use redis::{aio::ConnectionManager, AsyncCommands, Client};
+
+use super::{generate_session_key, session_owner, SessionState, SessionStore, SessionStoreErrors};
+
+/// Redis backed session store, sessions are shared between instances
+pub struct RedisSessionStore {
+	connection: ConnectionManager,
+}
+
+impl RedisSessionStore {
+	pub async fn new(uri: &str) -> Result<Self, SessionStoreErrors> {
+		let client = Client::open(format!("redis://{}", uri.trim_start_matches("redis://")))?;
+		let connection = ConnectionManager::new(client).await?;
+		Ok(Self { connection })
+	}
+
+	fn session_key(key: &str) -> String {
+		format!("session:{}", key)
+	}
+
+	fn user_sessions_key(user_id: &str) -> String {
+		format!("user-sessions:{}", user_id)
+	}
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+	async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionStoreErrors> {
+		let mut connection = self.connection.clone();
+		let value: Option<String> = connection.get(Self::session_key(key)).await?;
+
+		match value {
+			Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+			None => Ok(None),
+		}
+	}
+
+	async fn save(&self, key: Option<&str>, state: SessionState, ttl: i64) -> Result<String, SessionStoreErrors> {
+		let mut connection = self.connection.clone();
+		let key = key.map(String::from).unwrap_or_else(generate_session_key);
+		let value = serde_json::to_string(&state)?;
+
+		connection.set_ex(Self::session_key(&key), value, ttl as usize).await?;
+
+		// Keep an index of the user's sessions for listing and revocation
+		if let Some(user_id) = session_owner(&state) {
+			let user_sessions_key = Self::user_sessions_key(&user_id);
+			connection.sadd(&user_sessions_key, &key).await?;
+			connection.expire(&user_sessions_key, ttl as usize).await?;
+		}
+
+		Ok(key)
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), SessionStoreErrors> {
+		if let Some(user_id) = self.load(key).await?.as_ref().and_then(session_owner) {
+			let mut connection = self.connection.clone();
+			connection.srem(Self::user_sessions_key(&user_id), key).await?;
+		}
+
+		let mut connection = self.connection.clone();
+		connection.del(Self::session_key(key)).await?;
+
+		Ok(())
+	}
+
+	async fn user_sessions(&self, user_id: &str) -> Result<Vec<String>, SessionStoreErrors> {
+		let mut connection = self.connection.clone();
+		let user_sessions_key = Self::user_sessions_key(user_id);
+		let keys: Vec<String> = connection.smembers(&user_sessions_key).await?;
+
+		let mut live_keys = Vec::with_capacity(keys.len());
+		for key in keys {
+			let exists: bool = connection.exists(Self::session_key(&key)).await?;
+			if exists {
+				live_keys.push(key);
+			} else {
+				// The session expired, clean up the index
+				connection.srem(&user_sessions_key, &key).await?;
+			}
+		}
+
+		Ok(live_keys)
+	}
+}
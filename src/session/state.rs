@@ -0,0 +1,150 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_session::Session;
+use log::info;
+
+use crate::settings::APP_SETTINGS;
+
+use super::SessionErrors;
+
+/// The session state key holding the authenticated user id
+pub const USER_ID_KEY: &str = "user_id";
+const CREATED_AT_KEY: &str = "created_at";
+const LAST_SEEN_KEY: &str = "last_seen";
+
+/// Current unix timestamp in seconds
+pub fn unix_now() -> i64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|duration| duration.as_secs() as i64)
+		.unwrap_or_default()
+}
+
+/// Starts an authenticated session for the user.
+///
+/// The session key is renewed so that a pre-login session id can never be reused (session fixation).
+pub fn start_session(session: &Session, user_id: &str) -> Result<(), SessionErrors> {
+	let now = unix_now();
+
+	session.insert(USER_ID_KEY, user_id)?;
+	session.insert(CREATED_AT_KEY, now)?;
+	session.insert(LAST_SEEN_KEY, now)?;
+	// Inserting marks the session as changed again, the renewal must come last
+	session.renew();
+
+	Ok(())
+}
+
+/// Renews the session key keeping its state, must be called on every privilege change.
+///
+/// It must be the last session write of the request, a later insert would save the state under the old key.
+pub fn renew_session(session: &Session) {
+	session.renew();
+}
+
+/// Gets the authenticated user id from the session, enforcing the idle and absolute timeouts.
+///
+/// Expired sessions are purged.
+pub fn session_user_id(session: &Session) -> Result<Option<String>, SessionErrors> {
+	let user_id: Option<String> = session.get(USER_ID_KEY)?;
+
+	if user_id.is_none() {
+		return Ok(None);
+	}
+
+	let now = unix_now();
+	let timeout = &APP_SETTINGS.session.timeout;
+	let created_at: i64 = session.get(CREATED_AT_KEY)?.unwrap_or_default();
+	let last_seen: i64 = session.get(LAST_SEEN_KEY)?.unwrap_or_default();
+
+	if now - created_at > timeout.absolute || now - last_seen > timeout.idle {
+		info!("Session expired, purging");
+		session.purge();
+		return Err(SessionErrors::SessionExpired);
+	}
+
+	session.insert(LAST_SEEN_KEY, now)?;
+
+	Ok(user_id)
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use actix_web::{cookie::Cookie, test, web, App, HttpResponse};
+
+	use crate::{
+		session::{MemorySessionStore, SessionMiddleware, SessionStore, SharedSessionStore},
+		settings::APP_SETTINGS,
+	};
+
+	use super::*;
+
+	async fn visit(session: Session) -> HttpResponse {
+		session.insert("visited", true).unwrap();
+		HttpResponse::Ok().finish()
+	}
+
+	async fn login(session: Session) -> HttpResponse {
+		start_session(&session, "user").unwrap();
+		HttpResponse::Ok().finish()
+	}
+
+	async fn change_password(session: Session) -> HttpResponse {
+		session_user_id(&session).unwrap();
+		renew_session(&session);
+		HttpResponse::Ok().finish()
+	}
+
+	fn session_cookie(res: &actix_web::dev::ServiceResponse) -> Cookie<'static> {
+		res
+			.response()
+			.cookies()
+			.find(|cookie| cookie.name() == APP_SETTINGS.session.cookie.name)
+			.map(Cookie::into_owned)
+			.expect("Missing session cookie")
+	}
+
+	#[actix_web::test]
+	async fn login_and_privilege_changes_renew_the_session_key() {
+		let store: SharedSessionStore = Arc::new(MemorySessionStore::default());
+		let app = test::init_service(
+			App::new()
+				.wrap(SessionMiddleware::new(store.clone()))
+				.route("/visit", web::get().to(visit))
+				.route("/login", web::get().to(login))
+				.route("/change-password", web::get().to(change_password)),
+		)
+		.await;
+
+		let res = test::call_service(&app, test::TestRequest::get().uri("/visit").to_request()).await;
+		let anonymous = session_cookie(&res);
+
+		let res = test::call_service(
+			&app,
+			test::TestRequest::get()
+				.uri("/login")
+				.cookie(anonymous.clone())
+				.to_request(),
+		)
+		.await;
+		let authenticated = session_cookie(&res);
+		assert_ne!(authenticated.value(), anonymous.value());
+		// The pre-login key cannot be used anymore
+		assert!(store.load(anonymous.value()).await.unwrap().is_none());
+		assert!(store.load(authenticated.value()).await.unwrap().is_some());
+
+		let res = test::call_service(
+			&app,
+			test::TestRequest::get()
+				.uri("/change-password")
+				.cookie(authenticated.clone())
+				.to_request(),
+		)
+		.await;
+		let renewed = session_cookie(&res);
+		assert_ne!(renewed.value(), authenticated.value());
+		assert!(store.load(authenticated.value()).await.unwrap().is_none());
+	}
+}
@@ -0,0 +1,78 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_trait::async_trait;
+use log::info;
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+
+use crate::settings::{SessionStoreKind, APP_SETTINGS};
+
+use super::{CookieSessionStore, MemorySessionStore, RedisSessionStore, SessionStoreErrors, USER_ID_KEY};
+
+/// The session state, as handled by `actix_session`: keys with JSON serialized values
+pub type SessionState = HashMap<String, String>;
+
+/// The session store shared between workers and handlers
+pub type SharedSessionStore = Arc<dyn SessionStore>;
+
+/// A session storage backend
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+	/// Loads the session state identified by the cookie value
+	async fn load(&self, key: &str) -> Result<Option<SessionState>, SessionStoreErrors>;
+
+	/// Persists the session state, returns the value to set in the session cookie.
+	///
+	/// When `key` is `None` a new session is created.
+	async fn save(&self, key: Option<&str>, state: SessionState, ttl: i64) -> Result<String, SessionStoreErrors>;
+
+	/// Deletes the session identified by the cookie value
+	async fn delete(&self, key: &str) -> Result<(), SessionStoreErrors>;
+
+	/// Lists the keys of all the live sessions of a user
+	async fn user_sessions(&self, user_id: &str) -> Result<Vec<String>, SessionStoreErrors>;
+
+	/// Deletes all the sessions of a user
+	async fn delete_user_sessions(&self, user_id: &str) -> Result<(), SessionStoreErrors> {
+		for key in self.user_sessions(user_id).await? {
+			self.delete(&key).await?;
+		}
+		Ok(())
+	}
+}
+
+/// Generates a new random session key
+pub fn generate_session_key() -> String {
+	OsRng.sample_iter(&Alphanumeric).take(64).map(char::from).collect()
+}
+
+/// Gets the authenticated user id owning the session state, if any
+pub fn session_owner(state: &SessionState) -> Option<String> {
+	state
+		.get(USER_ID_KEY)
+		.and_then(|user_id| serde_json::from_str(user_id).ok())
+}
+
+/// Creates the session store selected in the settings
+pub async fn init_session_store() -> SharedSessionStore {
+	let store: SharedSessionStore = match APP_SETTINGS.session.store {
+		SessionStoreKind::Redis => Arc::new(
+			RedisSessionStore::new(&APP_SETTINGS.redis.uri)
+				.await
+				.expect("Cannot connect to redis"),
+		),
+		SessionStoreKind::Memory => Arc::new(MemorySessionStore::default()),
+		SessionStoreKind::Cookie => Arc::new(CookieSessionStore::new(APP_SETTINGS.session.secret.as_bytes())),
+	};
+
+	info!("Session store initialised: {:?}", APP_SETTINGS.session.store);
+
+	store
+}
+
+/// Deletes all the sessions of a user, stores that cannot revoke sessions are ignored
+pub async fn revoke_user_sessions(store: &SharedSessionStore, user_id: &str) -> Result<(), SessionStoreErrors> {
+	match store.delete_user_sessions(user_id).await {
+		Ok(_) | Err(SessionStoreErrors::Unsupported) => Ok(()),
+		Err(e) => Err(e),
+	}
+}
@@ -12,7 +12,11 @@ use once_cell::sync::Lazy;
 use ory_hydra_client::apis::configuration::Configuration as OryConfiguration;
 use serde::{Deserialize, Serialize};
 
-use super::{HydraSettings, LoggerSettings, MongoSettings, SMTPSettings, ServerSettings};
+use super::{
+	AuditSettings, EmailSettings, FederationSettings, HasherSettings, HydraSettings, LdapSettings, LoggerSettings,
+	LoginAlertSettings, LoginCodeSettings, MagicLinkSettings, MongoSettings, PasswordSettings, RealmSettings,
+	SMTPSettings, ServerSettings, SessionSettings, SignupSettings, ThrottleSettings, UsernameSettings,
+};
 
 pub static APP_SETTINGS: Lazy<Settings> = Lazy::new(Settings::init_config);
 pub static ORY_HYDRA_CONFIGURATION: Lazy<OryConfiguration> = Lazy::new(init_ory_config);
@@ -22,6 +26,15 @@ pub static SIMPLE_TOTP_LONG_GENERATOR: Lazy<TOTP> = Lazy::new(init_simple_totp_l
 
 pub const SIGNUP_TEMPLATE_NAME: &str = "signup";
 pub const EMAIL_VERIFIED_TEMPLATE_NAME: &str = "email-verified";
+pub const ACCOUNT_LOCKED_TEMPLATE_NAME: &str = "account-locked";
+pub const SIGNUP_EXISTING_TEMPLATE_NAME: &str = "signup-existing";
+pub const RESET_PASSWORD_TEMPLATE_NAME: &str = "reset-password";
+pub const WELCOME_TEMPLATE_NAME: &str = "welcome";
+pub const ORGANIZATION_INVITATION_TEMPLATE_NAME: &str = "organization-invitation";
+pub const SIGNUP_INVITATION_TEMPLATE_NAME: &str = "signup-invitation";
+pub const MAGIC_LINK_TEMPLATE_NAME: &str = "magic-link";
+pub const LOGIN_CODE_TEMPLATE_NAME: &str = "login-code";
+pub const LOGIN_ALERT_TEMPLATE_NAME: &str = "login-alert";
 
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -30,13 +43,6 @@ pub struct RedisSettings {
 	pub uri: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub struct SessionSettings {
-	/// Encryption secret
-	pub secret: String,
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TemplateSettings {
 	/// The base path to the template directory
@@ -54,24 +60,63 @@ pub struct TOTPSettings {
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Settings {
+	/// Security audit log configuration
+	#[serde(default)]
+	pub audit: AuditSettings,
+	/// Email normalization configuration
+	#[serde(default)]
+	pub email: EmailSettings,
+	/// Upstream identity providers configuration
+	#[serde(default)]
+	pub federation: FederationSettings,
+	/// LDAP authentication backends configuration
+	#[serde(default)]
+	pub ldap: LdapSettings,
 	/// Logger configuration
 	pub logger: LoggerSettings,
+	/// New device login notifications configuration
+	#[serde(default)]
+	pub loginalert: LoginAlertSettings,
+	/// Email one-time code login configuration
+	#[serde(default)]
+	pub logincode: LoginCodeSettings,
+	/// Magic link login configuration
+	#[serde(default)]
+	pub magiclink: MagicLinkSettings,
+	/// Password hashing configuration
+	#[serde(default)]
+	pub hasher: HasherSettings,
 	/// ORY Hydra client configuration
 	pub hydra: HydraSettings,
 	/// Mongo database configuration
 	pub mongo: MongoSettings,
+	/// Password policy configuration
+	#[serde(default)]
+	pub password: PasswordSettings,
+	/// Home realm discovery configuration
+	#[serde(default)]
+	pub realm: RealmSettings,
 	/// Redis configuration
 	pub redis: RedisSettings,
 	/// HTTP server and app configuration
 	pub server: ServerSettings,
 	/// Session configuration
 	pub session: SessionSettings,
+	/// Signup policy configuration
+	#[serde(default)]
+	pub signup: SignupSettings,
 	/// Template configuration
 	pub template: TemplateSettings,
 	/// SMTP configuration
 	pub smtp: SMTPSettings,
+	/// Brute-force protection configuration
+	#[serde(default)]
+	pub throttle: ThrottleSettings,
 	/// Time-based one time token password configuration
 	pub totp: TOTPSettings,
+	/// Username policy configuration
+	#[serde(default)]
+	pub username: UsernameSettings,
 }
 
 impl Settings {
@@ -118,6 +163,7 @@ impl Settings {
 
 		// Deserialize configuration
 		let settings: Settings = config.try_deserialize().expect("Configuration error");
+		settings.audit.validate().expect("Configuration error");
 
 		info!("APP CONFIGURATION: {:?}", settings);
 
@@ -183,6 +229,54 @@ fn init_handlebars() -> Handlebars<'static> {
 		.register_template_file(EMAIL_VERIFIED_TEMPLATE_NAME, base_path.join("email-verified.hbs"))
 		.expect("Could not register `email-verified` template!");
 
+	// Register account locked template
+	handlebars
+		.register_template_file(ACCOUNT_LOCKED_TEMPLATE_NAME, base_path.join("account-locked.hbs"))
+		.expect("Could not register `account-locked` template!");
+
+	// Register signup with an existing email template
+	handlebars
+		.register_template_file(SIGNUP_EXISTING_TEMPLATE_NAME, base_path.join("signup-existing.hbs"))
+		.expect("Could not register `signup-existing` template!");
+
+	// Register reset password template
+	handlebars
+		.register_template_file(RESET_PASSWORD_TEMPLATE_NAME, base_path.join("reset-password.hbs"))
+		.expect("Could not register `reset-password` template!");
+
+	// Register welcome template
+	handlebars
+		.register_template_file(WELCOME_TEMPLATE_NAME, base_path.join("welcome.hbs"))
+		.expect("Could not register `welcome` template!");
+
+	// Register organization invitation template
+	handlebars
+		.register_template_file(
+			ORGANIZATION_INVITATION_TEMPLATE_NAME,
+			base_path.join("organization-invitation.hbs"),
+		)
+		.expect("Could not register `organization-invitation` template!");
+
+	// Register signup invitation template
+	handlebars
+		.register_template_file(SIGNUP_INVITATION_TEMPLATE_NAME, base_path.join("signup-invitation.hbs"))
+		.expect("Could not register `signup-invitation` template!");
+
+	// Register magic link template
+	handlebars
+		.register_template_file(MAGIC_LINK_TEMPLATE_NAME, base_path.join("magic-link.hbs"))
+		.expect("Could not register `magic-link` template!");
+
+	// Register login code template
+	handlebars
+		.register_template_file(LOGIN_CODE_TEMPLATE_NAME, base_path.join("login-code.hbs"))
+		.expect("Could not register `login-code` template!");
+
+	// Register login alert template
+	handlebars
+		.register_template_file(LOGIN_ALERT_TEMPLATE_NAME, base_path.join("login-alert.hbs"))
+		.expect("Could not register `login-alert` template!");
+
 	info!("Successfully Registered all templates!");
 
 	handlebars
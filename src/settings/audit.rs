@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+
+fn default_retention() -> u64 {
+	365
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Security audit log configuration
+pub struct AuditSettings {
+	/// Days the audit events are kept, at least one
+	#[serde(default = "default_retention")]
+	pub retention: u64,
+}
+
+impl Default for AuditSettings {
+	fn default() -> Self {
+		Self {
+			retention: default_retention(),
+		}
+	}
+}
+
+impl AuditSettings {
+	/// The retention in seconds, `None` when too large to be counted
+	pub fn retention_seconds(&self) -> Option<u64> {
+		self
+			.retention
+			.checked_mul(24 * 60 * 60)
+			.filter(|seconds| *seconds <= i64::MAX as u64)
+	}
+
+	/// Rejects a retention of zero days, which would expire the events at once, or too large to be counted
+	pub fn validate(&self) -> Result<(), String> {
+		if self.retention < 1 {
+			return Err("audit.retention must be at least one day".to_string());
+		}
+		if self.retention_seconds().is_none() {
+			return Err("audit.retention is too large".to_string());
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+fn default_lowercase() -> bool {
+	true
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Email normalization configuration, the domain is always lowercased
+pub struct EmailSettings {
+	/// Lowercase the local part too, it is case sensitive by the RFC but almost never in practice
+	#[serde(default = "default_lowercase")]
+	pub lowercase: bool,
+	/// Drop the `+tag` subaddress from the local part
+	#[serde(default)]
+	pub subaddress: bool,
+	/// Comma separated domains where dots in the local part are ignored (e.g. gmail.com)
+	#[serde(default)]
+	pub dots: String,
+}
+
+impl Default for EmailSettings {
+	fn default() -> Self {
+		Self {
+			lowercase: default_lowercase(),
+			subaddress: false,
+			dots: String::new(),
+		}
+	}
+}
+
+impl EmailSettings {
+	/// The domains ignoring dots, lowercase
+	pub fn dots_domains(&self) -> Vec<String> {
+		self
+			.dots
+			.split(',')
+			.map(|domain| domain.trim().to_lowercase())
+			.filter(|domain| !domain.is_empty())
+			.collect()
+	}
+}
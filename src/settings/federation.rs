@@ -0,0 +1,114 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+fn default_scopes() -> String {
+	"openid email profile".to_string()
+}
+
+fn default_subject_claim() -> String {
+	"sub".to_string()
+}
+
+fn default_email_claim() -> String {
+	"email".to_string()
+}
+
+fn default_verified_claim() -> String {
+	"email_verified".to_string()
+}
+
+fn default_username_claim() -> String {
+	"preferred_username".to_string()
+}
+
+fn default_given_name_claim() -> String {
+	"given_name".to_string()
+}
+
+fn default_family_name_claim() -> String {
+	"family_name".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Names of the upstream claims read into the account
+pub struct ClaimMapping {
+	/// The stable identifier of the upstream account
+	#[serde(default = "default_subject_claim")]
+	pub subject: String,
+	#[serde(default = "default_email_claim")]
+	pub email: String,
+	/// Missing verification claims count as unverified
+	#[serde(default = "default_verified_claim")]
+	pub verified: String,
+	#[serde(default = "default_username_claim")]
+	pub username: String,
+	#[serde(default = "default_given_name_claim")]
+	pub givenname: String,
+	#[serde(default = "default_family_name_claim")]
+	pub familyname: String,
+}
+
+impl Default for ClaimMapping {
+	fn default() -> Self {
+		Self {
+			subject: default_subject_claim(),
+			email: default_email_claim(),
+			verified: default_verified_claim(),
+			username: default_username_claim(),
+			givenname: default_given_name_claim(),
+			familyname: default_family_name_claim(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// An upstream identity provider
+pub struct ProviderSettings {
+	/// The name shown on the login button
+	pub name: String,
+	/// OpenID Connect issuer, the endpoints are discovered from it
+	pub issuer: Option<String>,
+	/// Authorization endpoint, for plain OAuth2 providers without discovery
+	pub authorization: Option<String>,
+	/// Token endpoint, for plain OAuth2 providers without discovery
+	pub token: Option<String>,
+	/// Userinfo endpoint, overrides the discovered one
+	pub userinfo: Option<String>,
+	/// Verified emails endpoint, for plain OAuth2 providers without a verification claim (e.g. GitHub
+	/// `https://api.github.com/user/emails`), its primary verified email replaces the profile one
+	pub emails: Option<String>,
+	pub clientid: String,
+	pub clientsecret: String,
+	/// Space separated scopes
+	#[serde(default = "default_scopes")]
+	pub scopes: String,
+	#[serde(default)]
+	pub claims: ClaimMapping,
+	/// Creates the account at the first login, otherwise only linked accounts can log in
+	#[serde(default)]
+	pub provision: bool,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+/// Upstream identity providers configuration
+pub struct FederationSettings {
+	/// Public base URL of the federation routes, the callback of each provider is `{callback}/{provider}/callback`
+	#[serde(default)]
+	pub callback: String,
+	/// The providers by their identifier, used in the routes
+	#[serde(default)]
+	pub providers: HashMap<String, ProviderSettings>,
+}
+
+impl FederationSettings {
+	/// The redirect URI registered at the provider
+	pub fn redirect_uri(&self, provider: &str) -> String {
+		format!("{}/{}/callback", self.callback.trim_end_matches('/'), provider)
+	}
+
+	/// The URI starting a login with the provider
+	pub fn authorize_uri(&self, provider: &str) -> String {
+		format!("{}/{}/authorize", self.callback.trim_end_matches('/'), provider)
+	}
+}
@@ -0,0 +1,86 @@
+use argon2::Algorithm;
+use serde::{Deserialize, Serialize};
+
+fn default_memory() -> u32 {
+	19456
+}
+
+fn default_iterations() -> u32 {
+	2
+}
+
+fn default_parallelism() -> u32 {
+	1
+}
+
+fn default_workers() -> usize {
+	4
+}
+
+fn default_queue() -> usize {
+	64
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Argon2 variant
+pub enum Argon2Variant {
+	Argon2d,
+	Argon2i,
+	Argon2id,
+}
+
+impl Default for Argon2Variant {
+	fn default() -> Self {
+		Self::Argon2id
+	}
+}
+
+impl From<Argon2Variant> for Algorithm {
+	fn from(variant: Argon2Variant) -> Self {
+		match variant {
+			Argon2Variant::Argon2d => Algorithm::Argon2d,
+			Argon2Variant::Argon2i => Algorithm::Argon2i,
+			Argon2Variant::Argon2id => Algorithm::Argon2id,
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Password hashing configuration
+pub struct HasherSettings {
+	/// Argon2 variant
+	#[serde(default)]
+	pub variant: Argon2Variant,
+	/// Memory cost, in KiB
+	#[serde(default = "default_memory")]
+	pub memory: u32,
+	/// Number of iterations
+	#[serde(default = "default_iterations")]
+	pub iterations: u32,
+	/// Degree of parallelism
+	#[serde(default = "default_parallelism")]
+	pub parallelism: u32,
+	/// Maximum hashes running at the same time on the blocking thread pool
+	#[serde(default = "default_workers")]
+	pub workers: usize,
+	/// Maximum hashes waiting for a free worker, further requests are rejected
+	#[serde(default = "default_queue")]
+	pub queue: usize,
+	/// Server-side secret mixed into every hash, changing it invalidates all the passwords
+	pub pepper: Option<String>,
+}
+
+impl Default for HasherSettings {
+	fn default() -> Self {
+		Self {
+			variant: Argon2Variant::default(),
+			memory: default_memory(),
+			iterations: default_iterations(),
+			parallelism: default_parallelism(),
+			workers: default_workers(),
+			queue: default_queue(),
+			pepper: None,
+		}
+	}
+}
@@ -0,0 +1,118 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+fn default_filter() -> String {
+	"(mail={email})".to_string()
+}
+
+fn default_timeout() -> u64 {
+	5
+}
+
+fn default_email_attribute() -> String {
+	"mail".to_string()
+}
+
+fn default_username_attribute() -> String {
+	"uid".to_string()
+}
+
+fn default_given_name_attribute() -> String {
+	"givenName".to_string()
+}
+
+fn default_family_name_attribute() -> String {
+	"sn".to_string()
+}
+
+fn default_groups_attribute() -> String {
+	"memberOf".to_string()
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Names of the directory attributes read into the account
+pub struct AttributeMapping {
+	#[serde(default = "default_email_attribute")]
+	pub email: String,
+	#[serde(default = "default_username_attribute")]
+	pub username: String,
+	#[serde(default = "default_given_name_attribute")]
+	pub givenname: String,
+	#[serde(default = "default_family_name_attribute")]
+	pub familyname: String,
+	/// The group DNs of the entry
+	#[serde(default = "default_groups_attribute")]
+	pub groups: String,
+}
+
+impl Default for AttributeMapping {
+	fn default() -> Self {
+		Self {
+			email: default_email_attribute(),
+			username: default_username_attribute(),
+			givenname: default_given_name_attribute(),
+			familyname: default_family_name_attribute(),
+			groups: default_groups_attribute(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Grants a role to the members of a directory group
+pub struct GroupRole {
+	/// The group DN
+	pub group: String,
+	pub role: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// An LDAP or Active Directory server
+pub struct DirectorySettings {
+	/// Comma separated email domains authenticated by this directory
+	pub domains: String,
+	/// Server URL, e.g. `ldap://localhost:389` or `ldaps://ldap.example.com`
+	pub url: String,
+	/// Upgrade `ldap://` connections with StartTLS
+	#[serde(default)]
+	pub starttls: bool,
+	/// Connection timeout, in seconds
+	#[serde(default = "default_timeout")]
+	pub timeout: u64,
+	/// Service account used to find the user entry, anonymous when missing
+	pub binddn: Option<String>,
+	pub bindpassword: Option<String>,
+	/// Search base of the user entries
+	pub base: String,
+	/// Search filter of the user entry, `{email}` is replaced by the escaped email
+	#[serde(default = "default_filter")]
+	pub filter: String,
+	#[serde(default)]
+	pub attributes: AttributeMapping,
+	/// Roles granted by group membership, the listed roles are synced at each login
+	#[serde(default)]
+	pub roles: Vec<GroupRole>,
+	/// Creates the account at the first login, otherwise only existing accounts can log in
+	#[serde(default)]
+	pub provision: bool,
+}
+
+impl DirectorySettings {
+	/// The email domains authenticated by this directory, lowercase
+	pub fn email_domains(&self) -> Vec<String> {
+		self
+			.domains
+			.split(',')
+			.map(|domain| domain.trim().to_lowercase())
+			.filter(|domain| !domain.is_empty())
+			.collect()
+	}
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+/// LDAP authentication backends configuration
+pub struct LdapSettings {
+	/// The directories by their identifier
+	#[serde(default)]
+	pub directories: HashMap<String, DirectorySettings>,
+}
@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+fn default_enabled() -> bool {
+	true
+}
+
+fn default_lifetime() -> i64 {
+	7 * 24 * 60 * 60
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// New device login notifications configuration
+pub struct LoginAlertSettings {
+	/// Email the user on logins from an unseen device or network
+	#[serde(default = "default_enabled")]
+	pub enabled: bool,
+	/// GeoLite2/GeoIP2 City database file, the location is left out of the emails when missing
+	pub geoip: Option<String>,
+	/// Lifetime of the "this wasn't me" links, in seconds
+	#[serde(default = "default_lifetime")]
+	pub lifetime: i64,
+}
+
+impl Default for LoginAlertSettings {
+	fn default() -> Self {
+		Self {
+			enabled: default_enabled(),
+			geoip: None,
+			lifetime: default_lifetime(),
+		}
+	}
+}
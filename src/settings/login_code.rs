@@ -0,0 +1,44 @@
+use serde::{Deserialize, Serialize};
+
+fn default_length() -> u32 {
+	6
+}
+
+fn default_lifetime() -> i64 {
+	10 * 60
+}
+
+fn default_attempts() -> i64 {
+	5
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Email one-time code login configuration
+pub struct LoginCodeSettings {
+	/// Number of digits of the codes, from 6 to 8
+	#[serde(default = "default_length")]
+	pub length: u32,
+	/// Code lifetime, in seconds
+	#[serde(default = "default_lifetime")]
+	pub lifetime: i64,
+	/// Wrong codes allowed before the pending login is dropped
+	#[serde(default = "default_attempts")]
+	pub attempts: i64,
+}
+
+impl Default for LoginCodeSettings {
+	fn default() -> Self {
+		Self {
+			length: default_length(),
+			lifetime: default_lifetime(),
+			attempts: default_attempts(),
+		}
+	}
+}
+
+impl LoginCodeSettings {
+	/// The number of digits, kept between 6 and 8
+	pub fn digits(&self) -> u32 {
+		self.length.clamp(6, 8)
+	}
+}
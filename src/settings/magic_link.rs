@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+fn default_lifetime() -> i64 {
+	15 * 60
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Magic link login configuration
+pub struct MagicLinkSettings {
+	/// Link lifetime, in seconds
+	#[serde(default = "default_lifetime")]
+	pub lifetime: i64,
+}
+
+impl Default for MagicLinkSettings {
+	fn default() -> Self {
+		Self {
+			lifetime: default_lifetime(),
+		}
+	}
+}
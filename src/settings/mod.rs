@@ -1,13 +1,41 @@
 pub mod app_settings;
+pub mod audit;
+pub mod email;
+pub mod federation;
+pub mod hasher;
 pub mod hydra;
+pub mod ldap;
 pub mod logger;
+pub mod login_alert;
+pub mod login_code;
+pub mod magic_link;
 pub mod mongo;
+pub mod password;
+pub mod realm;
 pub mod server;
+pub mod session;
+pub mod signup;
 pub mod smtp;
+pub mod throttle;
+pub mod username;
 
 pub use app_settings::*;
+pub use audit::*;
+pub use email::*;
+pub use federation::*;
+pub use hasher::*;
 pub use hydra::*;
+pub use ldap::*;
 pub use logger::*;
+pub use login_alert::*;
+pub use login_code::*;
+pub use magic_link::*;
 pub use mongo::*;
+pub use password::*;
+pub use realm::*;
 pub use server::*;
+pub use session::*;
+pub use signup::*;
 pub use smtp::*;
+pub use throttle::*;
+pub use username::*;
@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+fn default_min() -> usize {
+	10
+}
+
+fn default_max() -> usize {
+	128
+}
+
+fn default_lowercase() -> bool {
+	true
+}
+
+fn default_score() -> u8 {
+	3
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Password policy configuration
+pub struct PasswordSettings {
+	/// Minimum length, in characters
+	#[serde(default = "default_min")]
+	pub min: usize,
+	/// Maximum length, in characters
+	#[serde(default = "default_max")]
+	pub max: usize,
+	/// Require at least a lowercase letter
+	#[serde(default = "default_lowercase")]
+	pub lowercase: bool,
+	/// Require at least an uppercase letter
+	#[serde(default)]
+	pub uppercase: bool,
+	/// Require at least a digit
+	#[serde(default)]
+	pub digit: bool,
+	/// Require at least a symbol
+	#[serde(default)]
+	pub symbol: bool,
+	/// Minimum strength score, from 0 (too guessable) to 4 (very unguessable)
+	#[serde(default = "default_score")]
+	pub score: u8,
+	/// Directory of the breached passwords SHA-1 range files (HIBP format), the check is disabled when missing
+	pub breached: Option<String>,
+}
+
+impl Default for PasswordSettings {
+	fn default() -> Self {
+		Self {
+			min: default_min(),
+			max: default_max(),
+			lowercase: default_lowercase(),
+			uppercase: false,
+			digit: false,
+			symbol: false,
+			score: default_score(),
+			breached: None,
+		}
+	}
+}
@@ -0,0 +1,30 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Sends the users of some email domains to an upstream provider
+pub struct RealmRule {
+	/// Comma separated email domains
+	pub domains: String,
+	/// The upstream provider identifier
+	pub provider: String,
+}
+
+impl RealmRule {
+	/// The email domains of the rule, lowercase
+	pub fn email_domains(&self) -> Vec<String> {
+		self
+			.domains
+			.split(',')
+			.map(|domain| domain.trim().to_lowercase())
+			.filter(|domain| !domain.is_empty())
+			.collect()
+	}
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+/// Home realm discovery configuration
+pub struct RealmSettings {
+	/// Domain rules, the first matching one wins
+	#[serde(default)]
+	pub rules: Vec<RealmRule>,
+}
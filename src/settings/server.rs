@@ -1,3 +1,5 @@
+use std::net::IpAddr;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -8,4 +10,18 @@ pub struct ServerSettings {
 	pub clienturi: String,
 	/// Server's port
 	pub port: u16,
+	/// Comma separated addresses of the proxies whose `X-Forwarded-For` header is trusted
+	#[serde(default)]
+	pub trustedproxies: String,
+}
+
+impl ServerSettings {
+	/// The trusted proxy addresses, the invalid ones are ignored
+	pub fn trusted_proxies(&self) -> Vec<IpAddr> {
+		self
+			.trustedproxies
+			.split(',')
+			.filter_map(|proxy| proxy.trim().parse().ok())
+			.collect()
+	}
 }
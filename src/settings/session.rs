@@ -0,0 +1,70 @@
+use actix_web::cookie::SameSite;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Cookie SameSite policy
+pub enum SameSitePolicy {
+	Strict,
+	Lax,
+	None,
+}
+
+impl From<SameSitePolicy> for SameSite {
+	fn from(policy: SameSitePolicy) -> Self {
+		match policy {
+			SameSitePolicy::Strict => SameSite::Strict,
+			SameSitePolicy::Lax => SameSite::Lax,
+			SameSitePolicy::None => SameSite::None,
+		}
+	}
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Session storage backend
+pub enum SessionStoreKind {
+	/// Sessions stored in redis, shared between instances
+	Redis,
+	/// Sessions stored in the process memory
+	Memory,
+	/// Sessions encrypted and signed into the cookie itself
+	Cookie,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Session cookie configuration
+pub struct SessionCookieSettings {
+	/// The cookie name
+	pub name: String,
+	/// The cookie domain, defaults to the current host
+	pub domain: Option<String>,
+	/// The cookie path
+	pub path: String,
+	/// Send the cookie only over HTTPS
+	pub secure: bool,
+	/// The cookie SameSite policy
+	pub samesite: SameSitePolicy,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Session timeouts, in seconds
+pub struct SessionTimeoutSettings {
+	/// Maximum inactivity before the session is discarded
+	pub idle: i64,
+	/// Maximum session lifetime, regardless of activity
+	pub absolute: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Session configuration
+pub struct SessionSettings {
+	/// Encryption secret
+	pub secret: String,
+	/// Session storage backend
+	pub store: SessionStoreKind,
+	/// Session cookie configuration
+	pub cookie: SessionCookieSettings,
+	/// Session timeouts configuration
+	pub timeout: SessionTimeoutSettings,
+}
@@ -0,0 +1,64 @@
+use serde::{Deserialize, Serialize};
+
+fn default_invitation() -> i64 {
+	7 * 24 * 60 * 60
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Who can sign up
+pub enum SignupMode {
+	/// Anyone
+	Open,
+	/// Only the holders of an invitation, issued by an admin or an organization
+	Invite,
+	/// Only the emails of the allowed domains, or the holders of an invitation
+	Domains,
+	/// Nobody, accounts are created by admins or imported
+	Closed,
+}
+
+impl Default for SignupMode {
+	fn default() -> Self {
+		Self::Open
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Signup policy configuration
+pub struct SignupSettings {
+	/// Who can sign up
+	#[serde(default)]
+	pub mode: SignupMode,
+	/// Comma separated allowed email domains, used by the `domains` mode
+	#[serde(default)]
+	pub domains: String,
+	/// File of the disposable email domains, one per line, the check is disabled when missing
+	pub blocklist: Option<String>,
+	/// Admin invitations lifetime, in seconds
+	#[serde(default = "default_invitation")]
+	pub invitation: i64,
+}
+
+impl Default for SignupSettings {
+	fn default() -> Self {
+		Self {
+			mode: SignupMode::default(),
+			domains: String::new(),
+			blocklist: None,
+			invitation: default_invitation(),
+		}
+	}
+}
+
+impl SignupSettings {
+	/// The allowed email domains, lowercase
+	pub fn allowed_domains(&self) -> Vec<String> {
+		self
+			.domains
+			.split(',')
+			.map(|domain| domain.trim().to_lowercase())
+			.filter(|domain| !domain.is_empty())
+			.collect()
+	}
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+
+fn default_window() -> i64 {
+	15 * 60
+}
+
+fn default_account() -> i64 {
+	10
+}
+
+fn default_ip() -> i64 {
+	50
+}
+
+fn default_pair() -> i64 {
+	5
+}
+
+fn default_backoff_base() -> i64 {
+	1
+}
+
+fn default_backoff_max() -> i64 {
+	5 * 60
+}
+
+fn default_lockout() -> i64 {
+	15 * 60
+}
+
+fn default_lookups() -> i64 {
+	100
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+/// Failed attempts storage backend
+pub enum ThrottleStoreKind {
+	/// Counters stored in redis, shared between instances, falls back to memory when redis is unreachable
+	Redis,
+	/// Counters stored in the process memory
+	Memory,
+}
+
+impl Default for ThrottleStoreKind {
+	fn default() -> Self {
+		Self::Redis
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Exponential back-off configuration, in seconds
+pub struct BackoffSettings {
+	/// Delay after the first failure, doubled on every following failure
+	#[serde(default = "default_backoff_base")]
+	pub base: i64,
+	/// Maximum delay
+	#[serde(default = "default_backoff_max")]
+	pub max: i64,
+}
+
+impl Default for BackoffSettings {
+	fn default() -> Self {
+		Self {
+			base: default_backoff_base(),
+			max: default_backoff_max(),
+		}
+	}
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Brute-force protection configuration
+pub struct ThrottleSettings {
+	/// Failed attempts storage backend
+	#[serde(default)]
+	pub store: ThrottleStoreKind,
+	/// Failed attempts are counted over this window, in seconds
+	#[serde(default = "default_window")]
+	pub window: i64,
+	/// Failed attempts on a single account before locking it
+	#[serde(default = "default_account")]
+	pub account: i64,
+	/// Failed attempts from a single IP before locking it
+	#[serde(default = "default_ip")]
+	pub ip: i64,
+	/// Failed attempts on a single account from a single IP before locking the pair
+	#[serde(default = "default_pair")]
+	pub pair: i64,
+	/// Exponential back-off between failed attempts
+	#[serde(default)]
+	pub backoff: BackoffSettings,
+	/// Lockout duration, in seconds
+	#[serde(default = "default_lockout")]
+	pub lockout: i64,
+	/// Username availability lookups from a single IP over the window
+	#[serde(default = "default_lookups")]
+	pub lookups: i64,
+}
+
+impl Default for ThrottleSettings {
+	fn default() -> Self {
+		Self {
+			store: ThrottleStoreKind::default(),
+			window: default_window(),
+			account: default_account(),
+			ip: default_ip(),
+			pair: default_pair(),
+			backoff: BackoffSettings::default(),
+			lockout: default_lockout(),
+			lookups: default_lookups(),
+		}
+	}
+}
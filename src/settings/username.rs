@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+
+fn default_min() -> usize {
+	3
+}
+
+fn default_max() -> usize {
+	32
+}
+
+fn default_cooldown() -> i64 {
+	30 * 24 * 60 * 60
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+/// Username policy configuration
+pub struct UsernameSettings {
+	/// Minimum username length
+	#[serde(default = "default_min")]
+	pub min: usize,
+	/// Maximum username length
+	#[serde(default = "default_max")]
+	pub max: usize,
+	/// Comma separated reserved usernames, on top of the built-in ones
+	#[serde(default)]
+	pub reserved: String,
+	/// Seconds before a user can change the username again
+	#[serde(default = "default_cooldown")]
+	pub cooldown: i64,
+}
+
+impl Default for UsernameSettings {
+	fn default() -> Self {
+		Self {
+			min: default_min(),
+			max: default_max(),
+			reserved: String::new(),
+			cooldown: default_cooldown(),
+		}
+	}
+}
+
+impl UsernameSettings {
+	/// The configured reserved usernames, lowercase
+	pub fn reserved_usernames(&self) -> Vec<String> {
+		self
+			.reserved
+			.split(',')
+			.map(|username| username.trim().to_lowercase())
+			.filter(|username| !username.is_empty())
+			.collect()
+	}
+}
@@ -0,0 +1,46 @@
+use std::{collections::HashSet, fs, io};
+
+use log::info;
+use once_cell::sync::Lazy;
+
+use crate::settings::APP_SETTINGS;
+
+static DISPOSABLE_DOMAINS: Lazy<HashSet<String>> = Lazy::new(|| match &APP_SETTINGS.signup.blocklist {
+	Some(path) => load_blocklist(path).expect("Could not read the disposable email domains"),
+	None => HashSet::new(),
+});
+
+/// Reads a domain per line, empty lines and `#` comments are skipped
+fn load_blocklist(path: &str) -> io::Result<HashSet<String>> {
+	let content = fs::read_to_string(path)?;
+
+	Ok(
+		content
+			.lines()
+			.map(|line| line.trim())
+			.filter(|line| !line.is_empty() && !line.starts_with('#'))
+			.map(|line| line.to_lowercase())
+			.collect(),
+	)
+}
+
+/// Loads the disposable email domains, so that a missing file is reported on startup
+pub fn init_disposable_domains() {
+	info!("Loaded {} disposable email domains", DISPOSABLE_DOMAINS.len());
+}
+
+/// Checks the domain, and its parent domains, against the disposable email domains
+pub fn is_disposable(domain: &str) -> bool {
+	let domain = domain.to_lowercase();
+	let mut candidate = domain.as_str();
+
+	loop {
+		if DISPOSABLE_DOMAINS.contains(candidate) {
+			return true;
+		}
+		match candidate.find('.') {
+			Some(index) => candidate = &candidate[index + 1..],
+			None => return false,
+		}
+	}
+}
@@ -0,0 +1,21 @@
+use thiserror::Error;
+use wither::WitherError;
+
+#[derive(Error, Debug)]
+/// Possible signup policy errors
+pub enum SignupErrors {
+	#[error("Signup is closed")]
+	Closed,
+	#[error("An invitation is required to sign up")]
+	InvitationRequired,
+	#[error("Invitation not found or expired")]
+	InvalidInvitation,
+	#[error("The invitation was sent to a different email")]
+	InvitationEmailMismatch,
+	#[error("Signup is not allowed for this email domain")]
+	DomainNotAllowed,
+	#[error("Disposable email addresses are not allowed")]
+	DisposableEmail,
+	#[error("{0}")]
+	DatabaseError(#[from] WitherError),
+}
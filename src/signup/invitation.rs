@@ -0,0 +1,77 @@
+use wither::{mongodb::Database, Model, WitherError};
+
+use crate::{
+	organization::{Invitation, Membership},
+	session::unix_now,
+	user::User,
+};
+
+use super::SignupInvitation;
+
+/// An unexpired invitation, either to sign up or to join an organization
+pub enum ValidInvitation {
+	Signup(SignupInvitation),
+	Organization(Invitation),
+}
+
+impl ValidInvitation {
+	/// Finds an unexpired invitation by the token sent by email
+	pub async fn find_by_token(db: &Database, token: &str) -> Result<Option<Self>, WitherError> {
+		let now = unix_now();
+
+		if let Some(invitation) = SignupInvitation::find_by_token(db, token).await? {
+			return Ok(Some(Self::Signup(invitation)).filter(|invitation| invitation.expires_at() > now));
+		}
+		if let Some(invitation) = Invitation::find_by_token(db, token).await? {
+			return Ok(Some(Self::Organization(invitation)).filter(|invitation| invitation.expires_at() > now));
+		}
+
+		Ok(None)
+	}
+
+	/// The invited email, lowercase
+	pub fn email(&self) -> &str {
+		match self {
+			Self::Signup(invitation) => &invitation.email,
+			Self::Organization(invitation) => &invitation.email,
+		}
+	}
+
+	fn expires_at(&self) -> i64 {
+		match self {
+			Self::Signup(invitation) => invitation.expires_at,
+			Self::Organization(invitation) => invitation.expires_at,
+		}
+	}
+
+	/// Gives the invitation roles or membership to the new user, then deletes the invitation
+	pub async fn redeem(self, db: &Database, user: &mut User) -> Result<(), WitherError> {
+		// The invitation was received at this email
+		user.email_scope.email_verified = true;
+
+		match self {
+			Self::Signup(invitation) => {
+				for role in &invitation.roles {
+					if !user.roles.contains(role) {
+						user.roles.push(role.clone());
+					}
+				}
+				user.save(db, None).await?;
+				invitation.delete(db).await?;
+			}
+			Self::Organization(invitation) => {
+				user.save(db, None).await?;
+				let mut membership = Membership {
+					id: None,
+					organization: invitation.organization,
+					user: user.id.clone().unwrap(),
+					role: invitation.role,
+				};
+				membership.save(db, None).await?;
+				invitation.delete(db).await?;
+			}
+		}
+
+		Ok(())
+	}
+}
@@ -0,0 +1,11 @@
+pub mod blocklist;
+pub mod errors;
+pub mod invitation;
+pub mod model;
+pub mod policy;
+
+pub use blocklist::*;
+pub use errors::*;
+pub use invitation::*;
+pub use model::*;
+pub use policy::*;
@@ -0,0 +1,33 @@
+use serde::{Deserialize, Serialize};
+use wither::{
+	bson::{doc, oid::ObjectId},
+	mongodb::Database,
+	prelude::*,
+	WitherError,
+};
+
+use crate::utils::hash_token;
+
+/// A single-use invitation to sign up, issued by an admin
+#[derive(Debug, Default, Model, Serialize, Deserialize)]
+#[model(index(keys = r#"doc!{"token": 1}"#, options = r#"doc!{"unique": true}"#))]
+pub struct SignupInvitation {
+	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
+	pub id: Option<ObjectId>,
+	/// The invited email, lowercase
+	pub email: String,
+	/// The roles given to the new user
+	#[serde(default)]
+	pub roles: Vec<String>,
+	/// SHA-256 of the token sent by email, the token itself is never stored
+	pub token: String,
+	pub invited_by: ObjectId,
+	pub expires_at: i64,
+}
+
+impl SignupInvitation {
+	/// Finds an invitation by the token sent by email
+	pub async fn find_by_token(db: &Database, token: &str) -> Result<Option<Self>, WitherError> {
+		SignupInvitation::find_one(db, doc! { "token": hash_token(token) }, None).await
+	}
+}
@@ -0,0 +1,24 @@
+use crate::settings::{SignupMode, APP_SETTINGS};
+
+use super::{is_disposable, SignupErrors};
+
+/// Checks the email against the signup mode and the disposable domains, invited users skip the domain allowlist
+pub fn check_signup_email(email: &str, invited: bool) -> Result<(), SignupErrors> {
+	let settings = &APP_SETTINGS.signup;
+	let domain = email.rsplit('@').next().unwrap_or_default().to_lowercase();
+
+	match settings.mode {
+		SignupMode::Closed => return Err(SignupErrors::Closed),
+		SignupMode::Invite if !invited => return Err(SignupErrors::InvitationRequired),
+		SignupMode::Domains if !invited && !settings.allowed_domains().contains(&domain) => {
+			return Err(SignupErrors::DomainNotAllowed)
+		}
+		_ => {}
+	}
+
+	if is_disposable(&domain) {
+		return Err(SignupErrors::DisposableEmail);
+	}
+
+	Ok(())
+}
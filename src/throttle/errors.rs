@@ -0,0 +1,11 @@
+use redis::RedisError;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+/// Possible brute-force protection errors
+pub enum ThrottleErrors {
+	#[error("Too many attempts, retry in {0} seconds")]
+	TooManyAttempts(i64),
+	#[error("Redis error: {0}")]
+	RedisError(#[from] RedisError),
+}
@@ -0,0 +1,206 @@
+use std::{cmp, sync::Arc};
+
+use log::{error, info, warn};
+
+use crate::settings::{ThrottleStoreKind, APP_SETTINGS};
+
+use super::{MemoryThrottleStore, RedisThrottleStore, ThrottleErrors, ThrottleStore};
+
+/// The throttled operations, each one has its own counters
+#[derive(Clone, Copy, Debug)]
+pub enum ThrottleAction {
+	Login,
+	Signup,
+	ValidateCode,
+	PasswordReset,
+	MagicLink,
+	LoginCode,
+	Discovery,
+	UsernameLookup,
+}
+
+impl ThrottleAction {
+	fn as_str(&self) -> &'static str {
+		match self {
+			Self::Login => "login",
+			Self::Signup => "signup",
+			Self::ValidateCode => "validate-code",
+			Self::PasswordReset => "password-reset",
+			Self::MagicLink => "magic-link",
+			Self::LoginCode => "login-code",
+			Self::Discovery => "discovery",
+			Self::UsernameLookup => "username-lookup",
+		}
+	}
+}
+
+/// Brute-force protection.
+///
+/// Failed attempts are counted per account, per IP and per account+IP pair, every failure imposes an exponentially
+/// growing delay before the next attempt and crossing a threshold locks the offender out.
+#[derive(Clone)]
+pub struct Throttle {
+	store: Arc<dyn ThrottleStore>,
+}
+
+impl Throttle {
+	pub fn new(store: Arc<dyn ThrottleStore>) -> Self {
+		Self { store }
+	}
+
+	fn key(action: ThrottleAction, kind: &str, subject: &str) -> String {
+		format!("throttle:{}:{}:{}", action.as_str(), kind, subject)
+	}
+
+	fn pair(account: &str, ip: &str) -> String {
+		format!("{}|{}", account, ip)
+	}
+
+	/// Fails with `ThrottleErrors::TooManyAttempts` when the attempt must be rejected
+	pub async fn check(&self, action: ThrottleAction, account: Option<&str>, ip: &str) -> Result<(), ThrottleErrors> {
+		let mut keys = vec![Self::key(action, "lock:ip", ip)];
+		match account {
+			Some(account) => {
+				let pair = Self::pair(account, ip);
+				keys.push(Self::key(action, "lock:account", account));
+				keys.push(Self::key(action, "lock:pair", &pair));
+				keys.push(Self::key(action, "wait", &pair));
+			}
+			None => keys.push(Self::key(action, "wait", ip)),
+		}
+
+		let mut retry_after = 0;
+		for key in keys {
+			if let Some(ttl) = self.store.ttl(&key).await? {
+				retry_after = cmp::max(retry_after, ttl);
+			}
+		}
+
+		if retry_after > 0 {
+			info!("Throttled {:?} attempt from {:?}", action, ip);
+			Err(ThrottleErrors::TooManyAttempts(retry_after))
+		} else {
+			Ok(())
+		}
+	}
+
+	/// Records a failed attempt, returns `true` when the account has just been locked
+	pub async fn record_failure(
+		&self,
+		action: ThrottleAction,
+		account: Option<&str>,
+		ip: &str,
+	) -> Result<bool, ThrottleErrors> {
+		let settings = &APP_SETTINGS.throttle;
+		let mut account_locked = false;
+
+		let ip_failures = self
+			.store
+			.increment(&Self::key(action, "failures:ip", ip), settings.window)
+			.await?;
+		if ip_failures >= settings.ip {
+			warn!("Locking IP {:?} for {:?}", ip, action);
+			self.lock(action, "ip", ip).await?;
+		}
+
+		let (wait_subject, failures) = match account {
+			Some(account) => {
+				let pair = Self::pair(account, ip);
+
+				let pair_failures = self
+					.store
+					.increment(&Self::key(action, "failures:pair", &pair), settings.window)
+					.await?;
+				if pair_failures >= settings.pair {
+					self.lock(action, "pair", &pair).await?;
+				}
+
+				let account_failures = self
+					.store
+					.increment(&Self::key(action, "failures:account", account), settings.window)
+					.await?;
+				if account_failures >= settings.account {
+					warn!("Locking account {:?} for {:?}", account, action);
+					self.lock(action, "account", account).await?;
+					account_locked = true;
+				}
+
+				(pair, pair_failures)
+			}
+			None => (ip.to_string(), ip_failures),
+		};
+
+		// Exponential back-off: base, 2 * base, 4 * base... up to max
+		let exponent = cmp::min(failures - 1, 30) as u32;
+		let delay = cmp::min(
+			settings.backoff.base.saturating_mul(2_i64.pow(exponent)),
+			settings.backoff.max,
+		);
+		if delay > 0 {
+			self
+				.store
+				.set_flag(&Self::key(action, "wait", &wait_subject), delay)
+				.await?;
+		}
+
+		Ok(account_locked)
+	}
+
+	/// Flat rate limit, fails with `ThrottleErrors::TooManyAttempts` past `limit` requests from the IP in the window.
+	///
+	/// Unlike failures, the requests impose no back-off and no lockout
+	pub async fn rate_limit(&self, action: ThrottleAction, ip: &str, limit: i64) -> Result<(), ThrottleErrors> {
+		let window = APP_SETTINGS.throttle.window;
+		let key = Self::key(action, "requests:ip", ip);
+
+		let requests = self.store.increment(&key, window).await?;
+		if requests > limit {
+			info!("Rate limited {:?} request from {:?}", action, ip);
+			let retry_after = self.store.ttl(&key).await?.unwrap_or(window);
+			return Err(ThrottleErrors::TooManyAttempts(retry_after));
+		}
+
+		Ok(())
+	}
+
+	/// Records a successful attempt, clearing the account counters
+	pub async fn record_success(&self, action: ThrottleAction, account: &str, ip: &str) -> Result<(), ThrottleErrors> {
+		let pair = Self::pair(account, ip);
+		self.store.delete(&Self::key(action, "failures:pair", &pair)).await?;
+		self
+			.store
+			.delete(&Self::key(action, "failures:account", account))
+			.await?;
+		Ok(())
+	}
+
+	/// Locks the subject out and restarts its failures count
+	async fn lock(&self, action: ThrottleAction, kind: &str, subject: &str) -> Result<(), ThrottleErrors> {
+		let lock_kind = format!("lock:{}", kind);
+		let failures_kind = format!("failures:{}", kind);
+		self
+			.store
+			.set_flag(&Self::key(action, &lock_kind, subject), APP_SETTINGS.throttle.lockout)
+			.await?;
+		self.store.delete(&Self::key(action, &failures_kind, subject)).await
+	}
+}
+
+/// Creates the brute-force protection with the configured store
+pub async fn init_throttle() -> Throttle {
+	let store: Arc<dyn ThrottleStore> = match APP_SETTINGS.throttle.store {
+		ThrottleStoreKind::Redis => match RedisThrottleStore::new(&APP_SETTINGS.redis.uri).await {
+			Ok(store) => Arc::new(store),
+			Err(e) => {
+				error!("{:?}", e);
+				warn!("Redis unreachable, using the in-memory throttle store");
+				Arc::new(MemoryThrottleStore::default())
+			}
+		},
+		ThrottleStoreKind::Memory => Arc::new(MemoryThrottleStore::default()),
+	};
+
+	info!("Throttle initialised: {:?}", APP_SETTINGS.throttle.store);
+
+	Throttle::new(store)
+}
@@ -0,0 +1,7 @@
+pub mod errors;
+pub mod limiter;
+pub mod store;
+
+pub use errors::*;
+pub use limiter::*;
+pub use store::*;
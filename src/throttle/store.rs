@@ -0,0 +1,199 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use async_trait::async_trait;
+use log::{error, warn};
+use redis::{aio::ConnectionManager, cmd, AsyncCommands, Client};
+
+use crate::session::unix_now;
+
+use super::ThrottleErrors;
+
+/// Increments the counter and sets its expiration on creation in one step, a counter cannot be left without one
+const INCREMENT_SCRIPT: &str = r#"
+local value = redis.call('INCR', KEYS[1])
+if value == 1 then
+	redis.call('EXPIRE', KEYS[1], ARGV[1])
+end
+return value
+"#;
+
+/// Failed attempts counters storage
+#[async_trait]
+pub trait ThrottleStore: Send + Sync {
+	/// Increments a counter expiring `window` seconds after its creation, returns the new value
+	async fn increment(&self, key: &str, window: i64) -> Result<i64, ThrottleErrors>;
+
+	/// Sets a flag expiring after `ttl` seconds
+	async fn set_flag(&self, key: &str, ttl: i64) -> Result<(), ThrottleErrors>;
+
+	/// Gets the remaining seconds of a counter or flag, if it exists
+	async fn ttl(&self, key: &str) -> Result<Option<i64>, ThrottleErrors>;
+
+	/// Deletes a counter or flag
+	async fn delete(&self, key: &str) -> Result<(), ThrottleErrors>;
+}
+
+/// In-process counters, not shared between instances
+#[derive(Default)]
+pub struct MemoryThrottleStore {
+	/// Key -> (value, expiration timestamp)
+	entries: Mutex<HashMap<String, (i64, i64)>>,
+}
+
+#[async_trait]
+impl ThrottleStore for MemoryThrottleStore {
+	async fn increment(&self, key: &str, window: i64) -> Result<i64, ThrottleErrors> {
+		let mut entries = self.entries.lock().expect("Throttle store lock poisoned");
+		let now = unix_now();
+		entries.retain(|_, (_, expires_at)| *expires_at > now);
+
+		let entry = entries.entry(key.to_string()).or_insert((0, now + window));
+		entry.0 += 1;
+
+		Ok(entry.0)
+	}
+
+	async fn set_flag(&self, key: &str, ttl: i64) -> Result<(), ThrottleErrors> {
+		let mut entries = self.entries.lock().expect("Throttle store lock poisoned");
+		entries.insert(key.to_string(), (1, unix_now() + ttl));
+		Ok(())
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<i64>, ThrottleErrors> {
+		let entries = self.entries.lock().expect("Throttle store lock poisoned");
+		let now = unix_now();
+
+		Ok(
+			entries
+				.get(key)
+				.map(|(_, expires_at)| expires_at - now)
+				.filter(|ttl| *ttl > 0),
+		)
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), ThrottleErrors> {
+		let mut entries = self.entries.lock().expect("Throttle store lock poisoned");
+		entries.remove(key);
+		Ok(())
+	}
+}
+
+/// Redis backed counters, shared between instances.
+///
+/// When redis cannot be reached the in-memory fallback is used, so protection degrades instead of failing open.
+pub struct RedisThrottleStore {
+	connection: ConnectionManager,
+	fallback: MemoryThrottleStore,
+}
+
+impl RedisThrottleStore {
+	pub async fn new(uri: &str) -> Result<Self, ThrottleErrors> {
+		let client = Client::open(format!("redis://{}", uri.trim_start_matches("redis://")))?;
+		let connection = ConnectionManager::new(client).await?;
+		Ok(Self {
+			connection,
+			fallback: MemoryThrottleStore::default(),
+		})
+	}
+
+	async fn redis_increment(&self, key: &str, window: i64) -> Result<i64, ThrottleErrors> {
+		let mut connection = self.connection.clone();
+		let value: i64 = cmd("EVAL")
+			.arg(INCREMENT_SCRIPT)
+			.arg(1)
+			.arg(key)
+			.arg(window)
+			.query_async(&mut connection)
+			.await?;
+		Ok(value)
+	}
+
+	async fn redis_ttl(&self, key: &str) -> Result<Option<i64>, ThrottleErrors> {
+		let mut connection = self.connection.clone();
+		// -2 when the key does not exist, -1 when it has no expiration
+		let ttl: i64 = connection.ttl(key).await?;
+		Ok(Some(ttl).filter(|ttl| *ttl > 0))
+	}
+}
+
+#[async_trait]
+impl ThrottleStore for RedisThrottleStore {
+	async fn increment(&self, key: &str, window: i64) -> Result<i64, ThrottleErrors> {
+		match self.redis_increment(key, window).await {
+			Ok(value) => Ok(value),
+			Err(e) => {
+				error!("{:?}", e);
+				warn!("Redis unreachable, using the in-memory throttle store");
+				self.fallback.increment(key, window).await
+			}
+		}
+	}
+
+	async fn set_flag(&self, key: &str, ttl: i64) -> Result<(), ThrottleErrors> {
+		let mut connection = self.connection.clone();
+		let result: Result<(), _> = connection.set_ex(key, 1, ttl as usize).await;
+		if let Err(e) = result {
+			error!("{:?}", e);
+			warn!("Redis unreachable, using the in-memory throttle store");
+			self.fallback.set_flag(key, ttl).await?;
+		}
+		Ok(())
+	}
+
+	async fn ttl(&self, key: &str) -> Result<Option<i64>, ThrottleErrors> {
+		match self.redis_ttl(key).await {
+			// Flags set while redis was unreachable are still honoured
+			Ok(ttl) => Ok(ttl.or(self.fallback.ttl(key).await?)),
+			Err(e) => {
+				error!("{:?}", e);
+				self.fallback.ttl(key).await
+			}
+		}
+	}
+
+	async fn delete(&self, key: &str) -> Result<(), ThrottleErrors> {
+		let mut connection = self.connection.clone();
+		let result: Result<(), _> = connection.del(key).await;
+		if let Err(e) = result {
+			error!("{:?}", e);
+		}
+		self.fallback.delete(key).await
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[actix_web::test]
+	async fn memory_store_counts_within_the_window() {
+		let store = MemoryThrottleStore::default();
+		assert_eq!(store.increment("login:ip", 60).await.unwrap(), 1);
+		assert_eq!(store.increment("login:ip", 60).await.unwrap(), 2);
+		assert_eq!(store.increment("login:other", 60).await.unwrap(), 1);
+
+		let ttl = store.ttl("login:ip").await.unwrap().unwrap();
+		assert!(ttl > 0 && ttl <= 60);
+	}
+
+	#[actix_web::test]
+	async fn memory_store_restarts_expired_counters() {
+		let store = MemoryThrottleStore::default();
+		assert_eq!(store.increment("login:ip", 0).await.unwrap(), 1);
+		assert_eq!(store.increment("login:ip", 0).await.unwrap(), 1);
+		assert_eq!(store.ttl("login:ip").await.unwrap(), None);
+	}
+
+	#[actix_web::test]
+	async fn memory_store_sets_and_deletes_flags() {
+		let store = MemoryThrottleStore::default();
+		assert_eq!(store.ttl("lock:account").await.unwrap(), None);
+
+		store.set_flag("lock:account", 900).await.unwrap();
+		let ttl = store.ttl("lock:account").await.unwrap().unwrap();
+		assert!(ttl > 0 && ttl <= 900);
+
+		store.delete("lock:account").await.unwrap();
+		assert_eq!(store.ttl("lock:account").await.unwrap(), None);
+	}
+}
@@ -0,0 +1,117 @@
+use unicode_normalization::UnicodeNormalization;
+use wither::mongodb::options::{Collation, CollationStrength};
+
+use crate::settings::{EmailSettings, APP_SETTINGS};
+
+/// Normalizes an email before storing or looking it up: Unicode NFC, lowercase domain and the configured local part rules
+pub fn normalize_email(email: &str) -> String {
+	normalize_email_with(&APP_SETTINGS.email, email)
+}
+
+fn normalize_email_with(settings: &EmailSettings, email: &str) -> String {
+	let email: String = email.trim().nfc().collect();
+	let (local, domain) = match email.rsplit_once('@') {
+		Some(parts) => parts,
+		// Not an email, validation will reject it
+		None => return email,
+	};
+
+	let domain = domain.to_lowercase();
+	let mut local = if settings.lowercase {
+		local.to_lowercase()
+	} else {
+		local.to_string()
+	};
+	if settings.subaddress {
+		if let Some((base, _tag)) = local.split_once('+') {
+			local = base.to_string();
+		}
+	}
+	if settings.dots_domains().contains(&domain) {
+		local = local.replace('.', "");
+	}
+
+	format!("{}@{}", local, domain)
+}
+
+/// The case insensitive identity of an email, two emails with the same key belong to the same account
+pub fn email_key(email: &str) -> String {
+	email_key_with(&APP_SETTINGS.email, email)
+}
+
+fn email_key_with(settings: &EmailSettings, email: &str) -> String {
+	normalize_email_with(settings, email).to_lowercase()
+}
+
+/// Collation of the email unique index, the email lookups must use it to match case insensitively
+pub fn email_collation() -> Collation {
+	Collation::builder()
+		.locale("en")
+		.strength(CollationStrength::Secondary)
+		.build()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn email_settings(lowercase: bool, subaddress: bool, dots: &str) -> EmailSettings {
+		EmailSettings {
+			lowercase,
+			subaddress,
+			dots: dots.to_string(),
+		}
+	}
+
+	#[test]
+	fn normalize_email_lowercases_the_domain_only_by_default() {
+		let settings = email_settings(false, false, "");
+		assert_eq!(
+			normalize_email_with(&settings, " John.Doe@Example.COM "),
+			"John.Doe@example.com"
+		);
+	}
+
+	#[test]
+	fn normalize_email_applies_the_local_part_rules() {
+		let settings = email_settings(true, true, "gmail.com");
+		assert_eq!(
+			normalize_email_with(&settings, "John.Doe+news@Gmail.com"),
+			"johndoe@gmail.com"
+		);
+		// Dots are only ignored for the listed domains
+		assert_eq!(
+			normalize_email_with(&settings, "John.Doe+news@example.com"),
+			"john.doe@example.com"
+		);
+	}
+
+	#[test]
+	fn normalize_email_composes_unicode() {
+		let settings = email_settings(false, false, "");
+		// `e` followed by a combining acute accent
+		assert_eq!(
+			normalize_email_with(&settings, "Jose\u{301}@example.com"),
+			"Jos\u{e9}@example.com"
+		);
+	}
+
+	#[test]
+	fn normalize_email_keeps_invalid_emails() {
+		let settings = email_settings(true, true, "");
+		assert_eq!(normalize_email_with(&settings, " not-an-email "), "not-an-email");
+	}
+
+	#[test]
+	fn email_key_matches_case_insensitively() {
+		let settings = email_settings(false, false, "");
+		assert_eq!(
+			email_key_with(&settings, "John.Doe@Example.com"),
+			email_key_with(&settings, "john.doe@EXAMPLE.com")
+		);
+		assert_ne!(
+			email_key_with(&settings, "john.doe@example.com"),
+			email_key_with(&settings, "johndoe@example.com")
+		);
+	}
+}
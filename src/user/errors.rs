@@ -3,7 +3,9 @@ use thiserror::Error;
 use validator::ValidationErrors;
 use wither::{bson::oid::Error as ObjectIdError, WitherError};
 
-use crate::utils::PasswordErrors;
+use crate::{directory::DirectoryErrors, session::SessionErrors, utils::PasswordErrors};
+
+use super::AccountState;
 
 #[derive(Error, Debug)]
 /// Possible user errors
@@ -14,14 +16,28 @@ pub enum UserErrors {
 	SessionError(#[from] ActixError),
 	#[error("{0}")]
 	HashError(#[from] PasswordErrors),
+	#[error("{0}")]
+	SessionStateError(#[from] SessionErrors),
 	#[error("User not found")]
 	UserNotFound,
-	#[error("User with email {0} was not found")]
-	UserWithEmailNotFound(String),
+	#[error("Invalid credentials")]
+	InvalidCredentials,
 	#[error("Invalid code")]
 	InvalidCode,
+	#[error("The account is {0}")]
+	AccountInactive(AccountState),
+	#[error("Invalid username: {0}")]
+	InvalidUsername(String),
+	#[error("This username is reserved")]
+	ReservedUsername,
+	#[error("This username is taken")]
+	UsernameTaken,
+	#[error("The username can be changed again in {0} seconds")]
+	UsernameCooldown(i64),
 	#[error("{0}")]
 	ValidationError(#[from] ValidationErrors),
 	#[error("{0}")]
 	ObjectIdError(#[from] ObjectIdError),
+	#[error("{0}")]
+	DirectoryError(#[from] DirectoryErrors),
 }
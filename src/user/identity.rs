@@ -0,0 +1,17 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+/// An account of an upstream identity provider linked to the user
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkedIdentity {
+	/// The provider identifier, as configured
+	pub provider: String,
+	/// The stable identifier of the account at the provider
+	pub subject: String,
+	/// Link unix timestamp
+	pub linked_at: i64,
+	/// Last login unix timestamp, missing until the first login
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub last_used_at: Option<i64>,
+}
@@ -1,7 +1,15 @@
+pub mod email;
 pub mod errors;
+pub mod identity;
 pub mod model;
+pub mod status;
 pub mod types;
+pub mod username;
 
+pub use email::*;
 pub use errors::*;
+pub use identity::*;
 pub use model::*;
+pub use status::*;
 pub use types::*;
+pub use username::*;
@@ -1,29 +1,60 @@
 use actix_session::Session;
+use log::{error, info};
 use serde::{Deserialize, Serialize};
 use wither::{
 	bson::{doc, oid::ObjectId},
-	mongodb::Database,
+	mongodb::{options::FindOneOptions, Database},
 	prelude::*,
 	WitherError,
 };
 
 use crate::{
 	auth::NewUserInput,
-	settings::init_keyed_totp_long,
-	utils::{hash_password, verify_password},
+	directory::directory_login,
+	session::{session_user_id, start_session, unix_now},
+	settings::{init_keyed_totp_long, APP_SETTINGS},
+	utils::{hash_password, hash_scheme, needs_rehash, verify_dummy_password, verify_password, PasswordErrors},
 };
 
-use super::{AddressScope, EmailScope, PhoneScope, ProfileScope, UserErrors};
+use super::{
+	check_username, email_collation, normalize_email, normalize_username, AccountStatus, AddressScope, EmailScope,
+	LinkedIdentity, PhoneScope, ProfileScope, UserErrors, UsernameChange,
+};
 
 /// User representation
 #[derive(Debug, Default, Model, Serialize, Deserialize)]
-#[model(index(keys = r#"doc!{"email": 1}"#, options = r#"doc!{"unique": true}"#))]
+#[model(
+	index(
+		keys = r#"doc!{"email": 1}"#,
+		options = r#"doc!{"unique": true, "collation": {"locale": "en", "strength": 2}}"#
+	),
+	index(
+		keys = r#"doc!{"username": 1}"#,
+		options = r#"doc!{"unique": true, "partialFilterExpression": {"username": {"$type": "string"}}}"#
+	),
+	index(
+		keys = r#"doc!{"identities.provider": 1, "identities.subject": 1}"#,
+		options = r#"doc!{"unique": true, "partialFilterExpression": {"identities.subject": {"$exists": true}}}"#
+	)
+)]
 pub struct User {
 	/// The ID of the model and the Subject: Identifier for the End-User at the Issuer.
 	#[serde(rename = "_id", skip_serializing_if = "Option::is_none")]
 	pub id: Option<ObjectId>,
-	/// The user's hashed password.
+	/// The user's hashed password, empty for accounts created by an upstream provider.
 	pub password: String,
+	/// The normalized unique username, used to log in. `preferred_username` keeps the form chosen by the user
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub username: Option<String>,
+	/// The username changes, the latest one starts the change cooldown
+	#[serde(default)]
+	pub username_history: Vec<UsernameChange>,
+	/// The upstream provider accounts that can log in as this user
+	#[serde(default)]
+	pub identities: Vec<LinkedIdentity>,
+	/// The LDAP directory authenticating the account, its profile and mapped roles are synced at each login
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub directory: Option<String>,
 	/// OpenID Connect Email scope
 	#[serde(flatten)]
 	pub email_scope: EmailScope,
@@ -35,6 +66,15 @@ pub struct User {
 	pub phone_scope: PhoneScope,
 	/// OpenID Connect address scope
 	pub address: Option<AddressScope>,
+	/// The user's roles
+	#[serde(default)]
+	pub roles: Vec<String>,
+	/// The names of the user's groups, their roles are given to the user
+	#[serde(default)]
+	pub groups: Vec<String>,
+	/// Only active accounts can log in
+	#[serde(default)]
+	pub status: AccountStatus,
 }
 
 impl User {
@@ -44,18 +84,31 @@ impl User {
 			username,
 			password,
 			email,
+			..
 		} = input;
 
+		// Usernames are optional, but unique when given
+		let normalized_username = match &username {
+			Some(username) => {
+				let normalized = check_username(username)?;
+				if Self::find_by_username(db, &normalized).await?.is_some() {
+					return Err(UserErrors::UsernameTaken);
+				}
+				Some(normalized)
+			}
+			None => None,
+		};
+
 		// Hash the password
-		let password = hash_password(&password)?;
+		let password = hash_password(&password).await?;
 
 		let email_scope = EmailScope {
-			email,
+			email: normalize_email(&email),
 			..Default::default()
 		};
 
 		let profile_scope = ProfileScope {
-			preferred_username: username,
+			preferred_username: username.map(|username| username.trim().to_string()),
 			..Default::default()
 		};
 
@@ -63,6 +116,7 @@ impl User {
 			id: None,
 			profile_scope,
 			password,
+			username: normalized_username,
 			email_scope,
 			..Default::default()
 		};
@@ -72,12 +126,51 @@ impl User {
 		Ok(user)
 	}
 
-	pub async fn login(db: &Database, email: &str, password: &str) -> Result<Self, UserErrors> {
+	/// Logs in with the email or the username
+	pub async fn login(db: &Database, login: &str, password: &str) -> Result<Self, UserErrors> {
+		// Accounts of the directory email domains are authenticated by their directory
+		if let Some(user) = directory_login(db, login, password).await? {
+			if !user.is_active() {
+				return Err(UserErrors::AccountInactive(user.status.state));
+			}
+			return Ok(user);
+		}
+
 		// Find the user
-		let user = Self::find_by_email(db, email).await?.ok_or(UserErrors::UserNotFound)?;
+		let mut user = match Self::find_by_login(db, login).await? {
+			Some(user) => user,
+			None => {
+				// Unknown users must not answer faster than wrong passwords
+				verify_dummy_password(password).await?;
+				return Err(UserErrors::InvalidCredentials);
+			}
+		};
+		// Accounts created by an upstream provider have no password until they set one
+		if !user.has_password() {
+			verify_dummy_password(password).await?;
+			return Err(UserErrors::InvalidCredentials);
+		}
+
+		// Verify the password, a wrong password is indistinguishable from an unknown user
+		verify_password(&user.password, password).await.map_err(|e| match e {
+			PasswordErrors::InvalidPassword => UserErrors::InvalidCredentials,
+			e => e.into(),
+		})?;
 
-		// Verify the password
-		verify_password(&user.password, password)?;
+		// Checked only after the password, so the account state is disclosed to its owner only
+		if !user.is_active() {
+			return Err(UserErrors::AccountInactive(user.status.state));
+		}
+
+		// The plain password is known only now, upgrade hashes created with outdated parameters
+		if needs_rehash(&user.password) {
+			info!("Upgrading outdated password hash: {:?}", hash_scheme(&user.password));
+			user.password = hash_password(password).await?;
+			if let Err(e) = user.save(db, None).await {
+				// The login is still valid, the upgrade is retried next time
+				error!("{:?}", e);
+			}
+		}
 
 		Ok(user)
 	}
@@ -85,27 +178,45 @@ impl User {
 	pub async fn login_with_session(
 		db: &Database,
 		session: &Session,
-		email: &str,
+		login: &str,
 		password: &str,
 	) -> Result<Self, UserErrors> {
-		// Find the user
-		let user = Self::find_by_email(db, email)
-			.await?
-			.ok_or_else(|| UserErrors::UserWithEmailNotFound(email.to_string()))?;
-
-		// Verify the password
-		verify_password(&user.password, password)?;
+		let user = Self::login(db, login, password).await?;
 
-		session.insert("user_id", user.id.clone().unwrap().to_hex())?;
+		// Renews the session key and persists the user id
+		start_session(session, &user.id.clone().unwrap().to_hex())?;
 
 		Ok(user)
 	}
 
 	pub async fn user_from_session(db: &Database, session: &Session) -> Result<Self, UserErrors> {
-		let user_id: String = session.get("user_id")?.ok_or(UserErrors::UserNotFound)?;
+		let user_id = session_user_id(session)?.ok_or(UserErrors::UserNotFound)?;
 		// let id = ObjectId::with_string(&user_id)?;
 		let id = ObjectId::parse_str(&user_id)?;
-		Ok(Self::find_by_id(db, &id).await?.ok_or(UserErrors::UserNotFound)?)
+		let user = Self::find_by_id(db, &id).await?.ok_or(UserErrors::UserNotFound)?;
+
+		// The account may have been suspended after the login
+		if !user.is_active() {
+			session.purge();
+			return Err(UserErrors::AccountInactive(user.status.state));
+		}
+
+		Ok(user)
+	}
+
+	/// Checks if the account can log in with a password
+	pub fn has_password(&self) -> bool {
+		!self.password.is_empty()
+	}
+
+	/// Counts the ways to log in to the account, the password or the directory, and the linked identities
+	pub fn login_methods(&self) -> usize {
+		usize::from(self.has_password() || self.directory.is_some()) + self.identities.len()
+	}
+
+	/// Checks if the account can log in, suspensions and locks end when they expire
+	pub fn is_active(&self) -> bool {
+		!self.status.is_blocked(unix_now())
 	}
 
 	pub async fn validate_email(&mut self, db: &Database, code: &str) -> Result<(), UserErrors> {
@@ -122,15 +233,118 @@ impl User {
 		}
 	}
 
+	/// Generates the password reset code, it is invalidated as soon as the password changes
+	pub fn password_reset_code(&self) -> String {
+		init_keyed_totp_long(&self.password_reset_key()).generate()
+	}
+
+	/// Replaces the password if the reset code is valid
+	pub async fn reset_password(&mut self, db: &Database, code: &str, password: &str) -> Result<(), UserErrors> {
+		let generator = init_keyed_totp_long(&self.password_reset_key());
+		if !generator.is_valid(code) {
+			return Err(UserErrors::InvalidCode);
+		}
+
+		self.password = hash_password(password).await?;
+		self.save(db, None).await?;
+
+		Ok(())
+	}
+
+	/// Replaces the password after verifying the current one
+	pub async fn change_password(
+		&mut self,
+		db: &Database,
+		current_password: &str,
+		new_password: &str,
+	) -> Result<(), UserErrors> {
+		// Accounts without a password set one with a password reset
+		if !self.has_password() {
+			return Err(UserErrors::InvalidCredentials);
+		}
+		verify_password(&self.password, current_password).await.map_err(|e| match e {
+			PasswordErrors::InvalidPassword => UserErrors::InvalidCredentials,
+			e => e.into(),
+		})?;
+
+		self.password = hash_password(new_password).await?;
+		self.save(db, None).await?;
+
+		Ok(())
+	}
+
+	/// Sets a new username without saving the user, the cooldown is skipped for admins.
+	/// Changing only the case is always allowed
+	pub async fn set_username(&mut self, db: &Database, username: &str, cooldown: bool) -> Result<(), UserErrors> {
+		let normalized = check_username(username)?;
+
+		if self.username.as_deref() != Some(normalized.as_str()) {
+			let now = unix_now();
+			if let Some(last_change) = self.username_history.last() {
+				let remaining = last_change.changed_at + APP_SETTINGS.username.cooldown - now;
+				if cooldown && remaining > 0 {
+					return Err(UserErrors::UsernameCooldown(remaining));
+				}
+			}
+			if Self::find_by_username(db, &normalized).await?.is_some() {
+				return Err(UserErrors::UsernameTaken);
+			}
+
+			self.username_history.push(UsernameChange {
+				previous: self.username.take(),
+				username: normalized.clone(),
+				changed_at: now,
+			});
+			self.username = Some(normalized);
+		}
+
+		self.profile_scope.preferred_username = Some(username.trim().to_string());
+
+		Ok(())
+	}
+
+	fn password_reset_key(&self) -> String {
+		// The current hash is part of the key, so a code can be used only once
+		format!("reset_{}_{}", self.id.clone().unwrap().to_hex(), self.password)
+	}
+
+	/// The name used to greet the user in emails
+	pub fn display_name(&self) -> String {
+		self
+			.profile_scope
+			.preferred_username
+			.clone()
+			.unwrap_or_else(|| "Anonymous".to_string())
+	}
+
 	pub async fn find_by_id(db: &Database, id: &ObjectId) -> Result<Option<Self>, WitherError> {
 		User::find_one(db, doc! { "_id": id }, None).await
 	}
 
 	pub async fn find_by_username(db: &Database, username: &str) -> Result<Option<Self>, WitherError> {
-		User::find_one(db, doc! { "username": username }, None).await
+		User::find_one(db, doc! { "username": normalize_username(username) }, None).await
 	}
 
+	/// Finds a user by email, ignoring the case like the unique index does
 	pub async fn find_by_email(db: &Database, email: &str) -> Result<Option<Self>, WitherError> {
-		User::find_one(db, doc! { "email": email }, None).await
+		let options = FindOneOptions::builder().collation(email_collation()).build();
+		User::find_one(db, doc! { "email": normalize_email(email) }, options).await
+	}
+
+	/// Finds the user linked to an upstream provider account
+	pub async fn find_by_identity(db: &Database, provider: &str, subject: &str) -> Result<Option<Self>, WitherError> {
+		let filter = doc! {
+			"identities": { "$elemMatch": { "provider": provider, "subject": subject } },
+		};
+		User::find_one(db, filter, None).await
+	}
+
+	/// Finds a user by email or by username, usernames cannot contain `@`
+	pub async fn find_by_login(db: &Database, login: &str) -> Result<Option<Self>, WitherError> {
+		if login.contains('@') {
+			Self::find_by_email(db, login).await
+		} else {
+			Self::find_by_username(db, login).await
+		}
 	}
 }
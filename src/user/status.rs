@@ -0,0 +1,61 @@
+use std::fmt;
+
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+
+/// The state of an account, only active accounts can log in
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "kebab-case")]
+pub enum AccountState {
+	Active,
+	Suspended,
+	Locked,
+	PendingDeletion,
+}
+
+impl Default for AccountState {
+	fn default() -> Self {
+		Self::Active
+	}
+}
+
+impl AccountState {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			Self::Active => "active",
+			Self::Suspended => "suspended",
+			Self::Locked => "locked",
+			Self::PendingDeletion => "pending-deletion",
+		}
+	}
+}
+
+impl fmt::Display for AccountState {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.write_str(self.as_str())
+	}
+}
+
+/// The account state, why it was set and until when
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountStatus {
+	pub state: AccountState,
+	/// Why the state was set, shown to admins only
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub reason: Option<String>,
+	/// Unix timestamp after which a suspended or locked account is active again, never when missing
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expires_at: Option<i64>,
+}
+
+impl AccountStatus {
+	/// Checks if the account cannot log in at the given time, pending deletions never expire
+	pub fn is_blocked(&self, now: i64) -> bool {
+		match self.state {
+			AccountState::Active => false,
+			AccountState::PendingDeletion => true,
+			AccountState::Suspended | AccountState::Locked => self.expires_at.map_or(true, |expires_at| expires_at > now),
+		}
+	}
+}
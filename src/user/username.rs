@@ -0,0 +1,95 @@
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use unicode_normalization::UnicodeNormalization;
+
+use crate::settings::APP_SETTINGS;
+
+use super::UserErrors;
+
+/// Names that could be mistaken for the service or its staff
+const RESERVED_USERNAMES: &[&str] = &[
+	"abuse",
+	"admin",
+	"administrator",
+	"api",
+	"help",
+	"hostmaster",
+	"info",
+	"mail",
+	"me",
+	"moderator",
+	"noreply",
+	"null",
+	"oauth",
+	"odysseus",
+	"postmaster",
+	"root",
+	"security",
+	"staff",
+	"support",
+	"system",
+	"undefined",
+	"webmaster",
+	"www",
+];
+
+/// A past username change
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct UsernameChange {
+	/// The previous normalized username, missing when it was the first one
+	pub previous: Option<String>,
+	/// The new normalized username
+	pub username: String,
+	/// Change unix timestamp
+	pub changed_at: i64,
+}
+
+/// Normalizes a username: Unicode NFKC and lowercase, so that look-alike forms map to the same name
+pub fn normalize_username(username: &str) -> String {
+	username.trim().nfkc().collect::<String>().to_lowercase()
+}
+
+/// Checks the built-in and the configured reserved usernames
+pub fn is_reserved_username(normalized: &str) -> bool {
+	RESERVED_USERNAMES.contains(&normalized)
+		|| APP_SETTINGS
+			.username
+			.reserved_usernames()
+			.iter()
+			.any(|r| r == normalized)
+}
+
+/// Checks the username against the policy, returns its normalized form.
+///
+/// Usernames cannot contain `@`, so a login is never ambiguous with an email.
+pub fn check_username(username: &str) -> Result<String, UserErrors> {
+	let settings = &APP_SETTINGS.username;
+	let normalized = normalize_username(username);
+
+	let length = normalized.chars().count();
+	if length < settings.min || length > settings.max {
+		return Err(UserErrors::InvalidUsername(format!(
+			"must be between {} and {} characters long",
+			settings.min, settings.max
+		)));
+	}
+	if !normalized
+		.chars()
+		.all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-')
+	{
+		return Err(UserErrors::InvalidUsername(
+			"only letters, digits, dots, dashes and underscores are allowed".to_string(),
+		));
+	}
+	if !normalized.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+		return Err(UserErrors::InvalidUsername(
+			"must start with a letter or a digit".to_string(),
+		));
+	}
+	if is_reserved_username(&normalized) {
+		return Err(UserErrors::ReservedUsername);
+	}
+
+	Ok(normalized)
+}
@@ -1,9 +1,20 @@
-use argon2::{password_hash::SaltString, Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use std::convert::TryFrom;
+
+use argon2::{
+	password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher, PasswordVerifier, Version,
+};
 use once_cell::sync::Lazy;
 use rand::rngs::OsRng;
 use thiserror::Error;
 
-static ARGON_2: Lazy<Argon2> = Lazy::new(Argon2::default);
+use crate::settings::{HasherSettings, APP_SETTINGS};
+
+use super::{hash_scheme, run_hasher, verify_legacy_password, HashScheme};
+
+static ARGON_2: Lazy<Argon2<'static>> = Lazy::new(|| init_argon2(&APP_SETTINGS.hasher));
+/// Hash verified when the user does not exist, so that unknown users take as long as known ones
+static DUMMY_HASH: Lazy<String> =
+	Lazy::new(|| hash_password_blocking("odysseus-dummy-password").expect("Could not create dummy hash"));
 
 #[derive(Error, Debug)]
 pub enum PasswordErrors {
@@ -11,9 +22,51 @@ pub enum PasswordErrors {
 	HashError,
 	#[error("Invalid password")]
 	InvalidPassword,
+	#[error("Too many concurrent requests, retry later")]
+	Overloaded,
+}
+
+/// Creates the Argon2 context from the hasher settings
+pub fn init_argon2(settings: &'static HasherSettings) -> Argon2<'static> {
+	let params = argon2_params(settings.memory, settings.iterations, settings.parallelism);
+
+	match &settings.pepper {
+		Some(pepper) => Argon2::new_with_secret(pepper.as_bytes(), settings.variant.into(), Version::V0x13, params)
+			.expect("Invalid Argon2 pepper"),
+		None => Argon2::new(settings.variant.into(), Version::V0x13, params),
+	}
+}
+
+/// Creates the Argon2 cost parameters
+pub fn argon2_params(memory: u32, iterations: u32, parallelism: u32) -> Params {
+	Params::new(memory, iterations, parallelism, None).expect("Invalid Argon2 parameters")
+}
+
+/// Hashes the password on the hashing pool
+pub async fn hash_password(password: &str) -> Result<String, PasswordErrors> {
+	let password = password.to_string();
+	run_hasher(move || hash_password_blocking(&password)).await
 }
 
-pub fn hash_password(password: &str) -> Result<String, PasswordErrors> {
+/// Verifies the password on the hashing pool
+pub async fn verify_password(password_hash: &str, password: &str) -> Result<(), PasswordErrors> {
+	let password_hash = password_hash.to_string();
+	let password = password.to_string();
+	run_hasher(move || verify_password_blocking(&password_hash, &password)).await
+}
+
+/// Runs a verification against a dummy hash, equalizes the response time for unknown users.
+///
+/// Fails only when the pool is overloaded, like the verification of known users, so that it discloses nothing either
+pub async fn verify_dummy_password(password: &str) -> Result<(), PasswordErrors> {
+	let password = password.to_string();
+	match run_hasher(move || verify_password_blocking(&DUMMY_HASH, &password)).await {
+		Err(PasswordErrors::Overloaded) => Err(PasswordErrors::Overloaded),
+		_ => Ok(()),
+	}
+}
+
+fn hash_password_blocking(password: &str) -> Result<String, PasswordErrors> {
 	// Generate salt string from OS entropy
 	let salt = SaltString::generate(&mut OsRng);
 
@@ -26,11 +79,46 @@ pub fn hash_password(password: &str) -> Result<String, PasswordErrors> {
 	Ok(password_hash)
 }
 
-pub fn verify_password(password_hash: &str, password: &str) -> Result<(), PasswordErrors> {
+fn verify_password_blocking(password_hash: &str, password: &str) -> Result<(), PasswordErrors> {
+	// Imported users may still have a hash from their previous system
+	match hash_scheme(password_hash) {
+		Some(HashScheme::Argon2) => {}
+		Some(scheme) => return verify_legacy_password(scheme, password_hash, password),
+		None => return Err(PasswordErrors::HashError),
+	}
+
 	// Create Hash from PHC string
 	let hash = PasswordHash::new(password_hash).map_err(|_| PasswordErrors::HashError)?;
 
+	// The variant and the costs are read from the PHC string
 	ARGON_2
 		.verify_password(password.as_bytes(), &hash)
 		.map_err(|_| PasswordErrors::InvalidPassword)
 }
+
+/// Checks if the hash was created with a legacy scheme, or a different variant or costs than the configured ones
+pub fn needs_rehash(password_hash: &str) -> bool {
+	if hash_scheme(password_hash) != Some(HashScheme::Argon2) {
+		return true;
+	}
+
+	let hash = match PasswordHash::new(password_hash) {
+		Ok(hash) => hash,
+		Err(_) => return true,
+	};
+
+	let settings = &APP_SETTINGS.hasher;
+	let algorithm: Algorithm = settings.variant.into();
+	if hash.algorithm != algorithm.ident() || hash.version != Some(Version::V0x13.into()) {
+		return true;
+	}
+
+	match Params::try_from(&hash) {
+		Ok(params) => {
+			params.m_cost() != settings.memory
+				|| params.t_cost() != settings.iterations
+				|| params.p_cost() != settings.parallelism
+		}
+		Err(_) => true,
+	}
+}
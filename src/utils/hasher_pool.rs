@@ -0,0 +1,106 @@
+use std::{
+	sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+	time::{Duration, Instant},
+};
+
+use actix_web::web;
+use log::{error, warn};
+use once_cell::sync::Lazy;
+use paperclip::actix::Apiv2Schema;
+use serde::{Deserialize, Serialize};
+use tokio::sync::Semaphore;
+
+use crate::settings::APP_SETTINGS;
+
+use super::PasswordErrors;
+
+/// Caps how many hashes run at the same time on the blocking thread pool
+static HASHER_PERMITS: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(APP_SETTINGS.hasher.workers));
+pub static HASHER_METRICS: Lazy<HasherMetrics> = Lazy::new(HasherMetrics::default);
+
+/// Queue times above this are logged as a warning
+const SLOW_QUEUE_TIME: Duration = Duration::from_secs(1);
+
+/// Password hashing pool metrics
+#[derive(Debug, Default)]
+pub struct HasherMetrics {
+	queued: AtomicUsize,
+	completed: AtomicU64,
+	rejected: AtomicU64,
+	queue_time_total_us: AtomicU64,
+	queue_time_max_us: AtomicU64,
+}
+
+/// Point in time copy of the password hashing pool metrics
+#[derive(Clone, Debug, Serialize, Deserialize, Apiv2Schema)]
+#[serde(rename_all = "camelCase")]
+pub struct HasherMetricsSnapshot {
+	/// Jobs waiting for a free worker
+	pub queued: usize,
+	/// Jobs run
+	pub completed: u64,
+	/// Jobs rejected because the queue was full
+	pub rejected: u64,
+	/// Average time spent waiting for a free worker, in microseconds
+	pub queue_time_avg_us: u64,
+	/// Maximum time spent waiting for a free worker, in microseconds
+	pub queue_time_max_us: u64,
+}
+
+impl HasherMetrics {
+	fn record_queue_time(&self, queue_time: Duration) {
+		let queue_time_us = queue_time.as_micros() as u64;
+		self.completed.fetch_add(1, Ordering::Relaxed);
+		self.queue_time_total_us.fetch_add(queue_time_us, Ordering::Relaxed);
+		self.queue_time_max_us.fetch_max(queue_time_us, Ordering::Relaxed);
+
+		if queue_time > SLOW_QUEUE_TIME {
+			warn!("Password hashing queue time: {:?}", queue_time);
+		}
+	}
+
+	pub fn snapshot(&self) -> HasherMetricsSnapshot {
+		let completed = self.completed.load(Ordering::Relaxed);
+		let queue_time_total_us = self.queue_time_total_us.load(Ordering::Relaxed);
+
+		HasherMetricsSnapshot {
+			queued: self.queued.load(Ordering::Relaxed),
+			completed,
+			rejected: self.rejected.load(Ordering::Relaxed),
+			queue_time_avg_us: queue_time_total_us.checked_div(completed).unwrap_or_default(),
+			queue_time_max_us: self.queue_time_max_us.load(Ordering::Relaxed),
+		}
+	}
+}
+
+/// Runs a CPU heavy hashing job on the blocking thread pool.
+///
+/// At most `hasher.workers` jobs run at the same time and at most `hasher.queue` wait for a worker,
+/// further jobs are rejected so that a burst of logins cannot pile up unbounded latency.
+pub async fn run_hasher<F, T>(job: F) -> Result<T, PasswordErrors>
+where
+	F: FnOnce() -> Result<T, PasswordErrors> + Send + 'static,
+	T: Send + 'static,
+{
+	let queued = HASHER_METRICS.queued.fetch_add(1, Ordering::SeqCst);
+	if queued >= APP_SETTINGS.hasher.queue {
+		HASHER_METRICS.queued.fetch_sub(1, Ordering::SeqCst);
+		HASHER_METRICS.rejected.fetch_add(1, Ordering::Relaxed);
+		warn!("Password hashing queue full, rejecting job");
+		return Err(PasswordErrors::Overloaded);
+	}
+
+	let enqueued_at = Instant::now();
+	let permit = HASHER_PERMITS.acquire().await;
+	HASHER_METRICS.queued.fetch_sub(1, Ordering::SeqCst);
+	let _permit = permit.map_err(|e| {
+		error!("{:?}", e);
+		PasswordErrors::HashError
+	})?;
+	HASHER_METRICS.record_queue_time(enqueued_at.elapsed());
+
+	web::block(job).await.map_err(|e| {
+		error!("{:?}", e);
+		PasswordErrors::HashError
+	})?
+}
@@ -0,0 +1,93 @@
+use argon2::PasswordHash;
+use pbkdf2::Pbkdf2;
+use scrypt::Scrypt;
+use sha2::{Digest, Sha512};
+use subtle::ConstantTimeEq;
+
+use super::PasswordErrors;
+
+/// LDAP style salted SHA-512 prefix: `{SSHA512}base64(sha512(password + salt) + salt)`
+const SALTED_SHA512_PREFIX: &str = "{SSHA512}";
+const SHA512_OUTPUT_LENGTH: usize = 64;
+
+/// Password hash formats that can be stored in `User.password`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HashScheme {
+	/// `$argon2id$...`, `$argon2i$...`, `$argon2d$...` PHC strings, the only format new hashes are created with
+	Argon2,
+	/// `$2a$`, `$2b$`, `$2x$`, `$2y$` MCF strings
+	Bcrypt,
+	/// `$scrypt$...` PHC strings
+	Scrypt,
+	/// `$pbkdf2-sha256$...` PHC strings
+	Pbkdf2Sha256,
+	/// `$6$...` SHA-512 crypt MCF strings
+	Sha512Crypt,
+	/// `{SSHA512}...` LDAP salted SHA-512
+	SaltedSha512,
+}
+
+/// Recognizes the format of a stored password hash
+pub fn hash_scheme(password_hash: &str) -> Option<HashScheme> {
+	if password_hash.starts_with("$argon2") {
+		Some(HashScheme::Argon2)
+	} else if ["$2a$", "$2b$", "$2x$", "$2y$"]
+		.iter()
+		.any(|prefix| password_hash.starts_with(prefix))
+	{
+		Some(HashScheme::Bcrypt)
+	} else if password_hash.starts_with("$scrypt$") {
+		Some(HashScheme::Scrypt)
+	} else if password_hash.starts_with("$pbkdf2-sha256$") {
+		Some(HashScheme::Pbkdf2Sha256)
+	} else if password_hash.starts_with("$6$") {
+		Some(HashScheme::Sha512Crypt)
+	} else if password_hash.starts_with(SALTED_SHA512_PREFIX) {
+		Some(HashScheme::SaltedSha512)
+	} else {
+		None
+	}
+}
+
+/// Verifies the password against an imported, non Argon2, hash
+pub fn verify_legacy_password(scheme: HashScheme, password_hash: &str, password: &str) -> Result<(), PasswordErrors> {
+	let valid = match scheme {
+		HashScheme::Argon2 => return Err(PasswordErrors::HashError),
+		HashScheme::Bcrypt => bcrypt::verify(password, password_hash).map_err(|_| PasswordErrors::HashError)?,
+		HashScheme::Scrypt => {
+			use scrypt::password_hash::PasswordVerifier;
+
+			let hash = PasswordHash::new(password_hash).map_err(|_| PasswordErrors::HashError)?;
+			Scrypt.verify_password(password.as_bytes(), &hash).is_ok()
+		}
+		HashScheme::Pbkdf2Sha256 => {
+			use pbkdf2::password_hash::PasswordVerifier;
+
+			let hash = PasswordHash::new(password_hash).map_err(|_| PasswordErrors::HashError)?;
+			Pbkdf2.verify_password(password.as_bytes(), &hash).is_ok()
+		}
+		HashScheme::Sha512Crypt => sha_crypt::sha512_check(password, password_hash).is_ok(),
+		HashScheme::SaltedSha512 => verify_salted_sha512(password_hash, password)?,
+	};
+
+	if valid {
+		Ok(())
+	} else {
+		Err(PasswordErrors::InvalidPassword)
+	}
+}
+
+fn verify_salted_sha512(password_hash: &str, password: &str) -> Result<bool, PasswordErrors> {
+	let decoded = base64::decode(&password_hash[SALTED_SHA512_PREFIX.len()..]).map_err(|_| PasswordErrors::HashError)?;
+	if decoded.len() <= SHA512_OUTPUT_LENGTH {
+		return Err(PasswordErrors::HashError);
+	}
+	let (expected, salt) = decoded.split_at(SHA512_OUTPUT_LENGTH);
+
+	let mut hasher = Sha512::new();
+	hasher.update(password.as_bytes());
+	hasher.update(salt);
+	let digest = hasher.finalize();
+
+	Ok(digest.as_slice().ct_eq(expected).into())
+}
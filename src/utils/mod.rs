@@ -1,9 +1,19 @@
 pub mod hasher;
+pub mod hasher_pool;
+pub mod legacy_hasher;
 pub mod logger;
 pub mod mongo;
+pub mod request;
+pub mod request_id;
 pub mod serializers;
+pub mod token;
 
 pub use hasher::*;
+pub use hasher_pool::*;
+pub use legacy_hasher::*;
 pub use logger::*;
 pub use mongo::*;
+pub use request::*;
+pub use request_id::*;
 pub use serializers::*;
+pub use token::*;
@@ -4,9 +4,18 @@ use wither::{
 	Model,
 };
 
-use crate::{settings::APP_SETTINGS, user::User};
+use crate::{
+	audit::AuditEvent,
+	auth::{KnownDevice, LoginAlert, LoginCode, MagicLink},
+	organization::{Invitation, Membership, Organization},
+	role::{Group, Permission, Role},
+	settings::APP_SETTINGS,
+	signup::SignupInvitation,
+	user::User,
+};
 
-pub async fn init_database() -> Database {
+/// Connects to the database without syncing the indexes
+pub async fn connect_database() -> Database {
 	let db = Client::with_uri_str(&APP_SETTINGS.mongo.uri)
 		.await
 		.expect("Cannot connect to the db")
@@ -14,7 +23,26 @@ pub async fn init_database() -> Database {
 
 	info!("Mongo database initialised");
 
+	db
+}
+
+pub async fn init_database() -> Database {
+	let db = connect_database().await;
+
 	User::sync(&db).await.expect("Failed syncing indexes");
+	Permission::sync(&db).await.expect("Failed syncing indexes");
+	Role::sync(&db).await.expect("Failed syncing indexes");
+	Group::sync(&db).await.expect("Failed syncing indexes");
+	Organization::sync(&db).await.expect("Failed syncing indexes");
+	Membership::sync(&db).await.expect("Failed syncing indexes");
+	Invitation::sync(&db).await.expect("Failed syncing indexes");
+	SignupInvitation::sync(&db).await.expect("Failed syncing indexes");
+	MagicLink::sync(&db).await.expect("Failed syncing indexes");
+	LoginCode::sync(&db).await.expect("Failed syncing indexes");
+	KnownDevice::sync(&db).await.expect("Failed syncing indexes");
+	LoginAlert::sync(&db).await.expect("Failed syncing indexes");
+	// Not synced by wither, which would drop the retention index it does not know
+	AuditEvent::sync_indexes(&db).await.expect("Failed syncing indexes");
 
 	db
 }
@@ -0,0 +1,94 @@
+use std::net::IpAddr;
+
+use actix_web::{http::header, HttpRequest};
+use once_cell::sync::Lazy;
+
+use crate::settings::APP_SETTINGS;
+
+static TRUSTED_PROXIES: Lazy<Vec<IpAddr>> = Lazy::new(|| APP_SETTINGS.server.trusted_proxies());
+
+/// Finds the client behind the trusted proxies.
+///
+/// `X-Forwarded-For` is read from the right, each trusted proxy appends the address it received the request from,
+/// the first untrusted address is the client. The left-most addresses are set by the client and never trusted
+fn forwarded_client(peer: IpAddr, forwarded_for: Option<&str>, trusted: &[IpAddr]) -> IpAddr {
+	let mut client = peer;
+	if !trusted.contains(&client) {
+		return client;
+	}
+
+	for address in forwarded_for.unwrap_or_default().rsplit(',') {
+		match address.trim().parse() {
+			Ok(address) => {
+				client = address;
+				if !trusted.contains(&client) {
+					break;
+				}
+			}
+			// A malformed hop cannot be attributed, the last trusted address stands for the client
+			Err(_) => break,
+		}
+	}
+	client
+}
+
+/// Gets the client IP address.
+///
+/// The `X-Forwarded-For` header is honoured only when the peer is one of the `server.trustedproxies`
+pub fn client_ip(req: &HttpRequest) -> String {
+	let peer = match req.peer_addr() {
+		Some(peer) => peer.ip(),
+		None => return "unknown".to_string(),
+	};
+	let forwarded_for = req
+		.headers()
+		.get("x-forwarded-for")
+		.and_then(|forwarded_for| forwarded_for.to_str().ok());
+
+	forwarded_client(peer, forwarded_for, &TRUSTED_PROXIES).to_string()
+}
+
+/// Gets the client user agent, empty when missing
+pub fn user_agent(req: &HttpRequest) -> String {
+	req
+		.headers()
+		.get(header::USER_AGENT)
+		.and_then(|user_agent| user_agent.to_str().ok())
+		.unwrap_or_default()
+		.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn ip(address: &str) -> IpAddr {
+		address.parse().unwrap()
+	}
+
+	#[test]
+	fn ignores_forwarded_headers_from_untrusted_peers() {
+		let trusted = [ip("10.0.0.1")];
+		assert_eq!(
+			forwarded_client(ip("203.0.113.7"), Some("198.51.100.1"), &trusted),
+			ip("203.0.113.7")
+		);
+	}
+
+	#[test]
+	fn reads_the_client_behind_trusted_proxies() {
+		let trusted = [ip("10.0.0.1"), ip("10.0.0.2")];
+		// The client forged the first address, the proxies appended the real one
+		let forwarded_for = Some("198.51.100.1, 203.0.113.7, 10.0.0.2");
+		assert_eq!(
+			forwarded_client(ip("10.0.0.1"), forwarded_for, &trusted),
+			ip("203.0.113.7")
+		);
+	}
+
+	#[test]
+	fn falls_back_to_the_proxy_without_header() {
+		let trusted = [ip("10.0.0.1")];
+		assert_eq!(forwarded_client(ip("10.0.0.1"), None, &trusted), ip("10.0.0.1"));
+	}
+}
@@ -0,0 +1,113 @@
+use std::rc::Rc;
+
+use actix_web::{
+	dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+	http::header::{HeaderName, HeaderValue},
+	Error, HttpRequest,
+};
+use futures_util::future::{ready, LocalBoxFuture, Ready};
+
+/// The header carrying the request ID, set by the proxy or by the middleware
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Accepts the proxy request IDs that are short and printable, so they cannot forge log lines
+fn is_valid_request_id(request_id: &str) -> bool {
+	!request_id.is_empty()
+		&& request_id.len() <= 64
+		&& request_id
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.')
+}
+
+/// Gets the request ID set by the `RequestIdMiddleware`
+pub fn request_id(req: &HttpRequest) -> String {
+	req
+		.headers()
+		.get(REQUEST_ID_HEADER)
+		.and_then(|request_id| request_id.to_str().ok())
+		.unwrap_or_default()
+		.to_string()
+}
+
+/// Request ID middleware, keeps the valid `X-Request-Id` of the proxy or generates one, and returns it in the response
+pub struct RequestIdMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for RequestIdMiddleware
+where
+	S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+	B: 'static,
+{
+	type Response = ServiceResponse<B>;
+	type Error = Error;
+	type Transform = InnerRequestIdMiddleware<S>;
+	type InitError = ();
+	type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+	fn new_transform(&self, service: S) -> Self::Future {
+		ready(Ok(InnerRequestIdMiddleware {
+			service: Rc::new(service),
+		}))
+	}
+}
+
+pub struct InnerRequestIdMiddleware<S> {
+	service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for InnerRequestIdMiddleware<S>
+where
+	S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+	B: 'static,
+{
+	type Response = ServiceResponse<B>;
+	type Error = Error;
+	type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+	forward_ready!(service);
+
+	fn call(&self, mut req: ServiceRequest) -> Self::Future {
+		let service = Rc::clone(&self.service);
+
+		Box::pin(async move {
+			let header = HeaderName::from_static(REQUEST_ID_HEADER);
+			let request_id = req
+				.headers()
+				.get(&header)
+				.and_then(|request_id| request_id.to_str().ok())
+				.filter(|request_id| is_valid_request_id(request_id))
+				.map(ToString::to_string)
+				.unwrap_or_else(|| nanoid::nanoid!());
+			// Safe to unwrap, the ID is checked or generated printable
+			let value = HeaderValue::from_str(&request_id).unwrap();
+
+			// The handlers and the logger read it from the request
+			req.headers_mut().insert(header.clone(), value.clone());
+
+			let mut res = service.call(req).await?;
+			res.headers_mut().insert(header, value);
+
+			Ok(res)
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn accepts_short_printable_request_ids() {
+		assert!(is_valid_request_id("f3a9c2e1-7b4d-4e8a-9c1f-2d3b4a5c6d7e"));
+		assert!(is_valid_request_id("req_123.abc"));
+		assert!(is_valid_request_id(&"a".repeat(64)));
+	}
+
+	#[test]
+	fn rejects_empty_long_or_unprintable_request_ids() {
+		assert!(!is_valid_request_id(""));
+		assert!(!is_valid_request_id(&"a".repeat(65)));
+		assert!(!is_valid_request_id("abc def"));
+		assert!(!is_valid_request_id("abc\r\nforged log line"));
+		assert!(!is_valid_request_id("caf\u{e9}"));
+	}
+}
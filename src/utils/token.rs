@@ -0,0 +1,12 @@
+use rand::{distributions::Alphanumeric, rngs::OsRng, Rng};
+use sha2::{Digest, Sha256};
+
+/// Generates a random token, for the links and codes sent to the users
+pub fn generate_token() -> String {
+	OsRng.sample_iter(&Alphanumeric).take(32).map(char::from).collect()
+}
+
+/// Hashes a token for storage, the tokens are random enough for an unsalted hash
+pub fn hash_token(token: &str) -> String {
+	format!("{:x}", Sha256::digest(token.as_bytes()))
+}